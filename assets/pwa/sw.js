@@ -0,0 +1,103 @@
+// Service worker of mtrack remote. Keeps the app shell (page, tailwind.css, WASM and JS bundles)
+// cached so the remote still opens when the server is unreachable.
+const CACHE = "mtrack-remote-v2";
+const SHELL = [
+  "/mtrack",
+  "/manifest.webmanifest",
+  "/icons/icon_192x192.png",
+  "/icons/icon_512x512.png",
+];
+// tailwind.css and the WASM and JS bundles are content hashed by the bundler,
+// so they are taken from the links and imports of the cached page.
+const ASSET = /["'](\/[^"'?#]+\.(?:css|js|wasm))["']/g;
+
+function cacheShell(cache) {
+  return cache
+    .addAll(SHELL)
+    .then(() => cache.match("/mtrack"))
+    .then((response) => response.text())
+    .then((html) => {
+      const assets = [...html.matchAll(ASSET)].map((match) => match[1]).filter((path) => path !== "/sw.js");
+      return cache.addAll([...new Set(assets)]);
+    });
+}
+
+function offline() {
+  return new Response("mtrack remote is offline", {
+    status: 503,
+    headers: { "Content-Type": "text/plain" },
+  });
+}
+
+self.addEventListener("install", (event) => {
+  event.waitUntil(
+    caches
+      .open(CACHE)
+      .then(cacheShell)
+      .then(() => self.skipWaiting()),
+  );
+});
+
+self.addEventListener("activate", (event) => {
+  event.waitUntil(
+    caches
+      .keys()
+      .then((keys) => Promise.all(keys.filter((key) => key !== CACHE).map((key) => caches.delete(key))))
+      .then(() => self.clients.claim()),
+  );
+});
+
+self.addEventListener("fetch", (event) => {
+  const request = event.request;
+  const url = new URL(request.url);
+  // Server functions and metrics must always hit the server.
+  if (
+    request.method !== "GET" ||
+    url.origin !== self.location.origin ||
+    url.pathname.startsWith("/api/") ||
+    url.pathname === "/metrics"
+  ) {
+    return;
+  }
+
+  if (request.mode === "navigate") {
+    // Network first so pages are fresh, falling back to the cached page or shell.
+    event.respondWith(
+      fetch(request)
+        .then((response) => {
+          if (response.status >= 500) {
+            throw new Error(`Server answered ${response.status}`);
+          }
+          if (response.ok) {
+            const copy = response.clone();
+            caches.open(CACHE).then((cache) => cache.put(request, copy));
+          }
+          return response;
+        })
+        .catch(() =>
+          caches
+            .match(request)
+            .then((cached) => cached || caches.match("/mtrack"))
+            .then((cached) => cached || offline()),
+        ),
+    );
+    return;
+  }
+
+  // Stale while revalidate for assets, they are content hashed by the bundler.
+  event.respondWith(
+    caches.open(CACHE).then((cache) =>
+      cache.match(request).then((cached) => {
+        const network = fetch(request)
+          .then((response) => {
+            if (response.ok) {
+              cache.put(request, response.clone());
+            }
+            return response;
+          })
+          .catch(() => cached || offline());
+        return cached || network;
+      }),
+    ),
+  );
+});
@@ -0,0 +1,243 @@
+//! Polls `get_state`'s state updates from 50 concurrent clients while commands go through,
+//! against the connection supervisor talking to a simulated mtrack on localhost. Clients
+//! either ask for the whole state every time or long-poll for what changed, as the web
+//! client does, and commands wait for mtrack to confirm them.
+//!
+//! Run with `cargo bench --features server --bench state_polling`.
+
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use rosc::{decoder::MTU, encoder, OscMessage, OscPacket, OscType};
+use tokio::{net::UdpSocket, select, sync::RwLock};
+
+use mtrack_remote::{
+    backend::{
+        autoadvance::{AutoAdvance, AutoAdvanceSettings},
+        command::{run_command, CommandStatus, TransportCommand},
+        config::Config,
+        durations::SongDurations,
+        osc::{
+            ADDR_CURRENT_SETLIST, ADDR_CURRENT_SONG, ADDR_ELAPSED, ADDR_NEXT, ADDR_PLAYLIST,
+            ADDR_SONG, ADDR_STATUS,
+        },
+        server::OscStateMachine,
+        service::Shutdown,
+        supervisor::{self, Snapshots},
+    },
+    components::read_state_update,
+};
+
+const CLIENTS: usize = 50;
+const SONGS: usize = 40;
+const RUN_TIME: Duration = Duration::from_secs(2);
+const COMMAND_INTERVAL: Duration = Duration::from_millis(50);
+/// How often the simulated mtrack reports the elapsed time, more often than mtrack does.
+const ELAPSED_INTERVAL: Duration = Duration::from_millis(10);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy)]
+enum Polling {
+    FullState,
+    LongPoll,
+}
+
+struct Results {
+    polls: u64,
+    polled_for: Duration,
+    commands: u32,
+    failed_commands: u32,
+    command_wait: Duration,
+    max_command_wait: Duration,
+}
+
+fn message(addr: &str, arg: String) -> OscPacket {
+    OscPacket::Message(OscMessage {
+        addr: addr.to_string(),
+        args: vec![OscType::String(arg)],
+    })
+}
+
+/// Answers requests like mtrack, skips songs on next and reports the elapsed time
+/// to whoever sent it something last.
+async fn simulate_mtrack(socket: UdpSocket, is_running: Arc<AtomicBool>) {
+    let setlist = (1..=SONGS)
+        .map(|index| format!("{index}. Song {index}"))
+        .collect::<Vec<_>>();
+    let mut song = 0;
+    let mut elapsed = 0;
+    let mut peer = None;
+    let mut buf = [0; MTU];
+    let mut ticks = tokio::time::interval(ELAPSED_INTERVAL);
+    while is_running.load(Ordering::Relaxed) {
+        let replies = select! {
+            received = socket.recv_from(&mut buf) => {
+                let Ok((size, from)) = received else {
+                    continue;
+                };
+                peer = Some(from);
+                let Ok((_rest, OscPacket::Message(request))) = rosc::decoder::decode_udp(&buf[..size]) else {
+                    continue;
+                };
+                match request.addr.as_str() {
+                    ADDR_PLAYLIST => vec![message(ADDR_CURRENT_SETLIST, setlist.join("\n"))],
+                    ADDR_SONG => vec![message(ADDR_CURRENT_SONG, format!("Song {}", song + 1))],
+                    ADDR_STATUS => vec![message(ADDR_STATUS, "Stopped".to_string())],
+                    ADDR_NEXT => {
+                        song = (song + 1) % SONGS;
+                        vec![message(ADDR_CURRENT_SONG, format!("Song {}", song + 1))]
+                    }
+                    _ => vec![],
+                }
+            }
+            _ = ticks.tick() => {
+                elapsed += 1;
+                vec![message(ADDR_ELAPSED, format!("0:{:02}", elapsed % 60))]
+            }
+        };
+        let Some(peer) = peer else {
+            continue;
+        };
+        for reply in replies {
+            if let Ok(buf) = encoder::encode(&reply) {
+                let _result = socket.send_to(&buf, peer).await;
+            }
+        }
+    }
+}
+
+async fn run(polling: Polling) -> Results {
+    let mtrack = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .await
+        .expect("simulated mtrack binds");
+    let listen_port = std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .and_then(|socket| socket.local_addr())
+        .expect("a free listen port")
+        .port();
+    let config = Config {
+        mtrack_addr: SocketAddr::new(
+            Ipv4Addr::LOCALHOST.into(),
+            mtrack.local_addr().expect("bound").port(),
+        ),
+        listen_port,
+        ..Config::default()
+    };
+
+    let state_machine_option = Arc::new(RwLock::new(None::<OscStateMachine>));
+    let config = Arc::new(RwLock::new(config));
+    let auto_advance = Arc::new(RwLock::new(
+        AutoAdvance::new(AutoAdvanceSettings::default()),
+    ));
+    let durations = Arc::new(RwLock::new(SongDurations::default()));
+    let snapshots = Arc::new(Snapshots::default());
+    let shutdown = Shutdown::new();
+    let is_running = Arc::new(AtomicBool::new(true));
+    let is_polling = Arc::new(AtomicBool::new(true));
+
+    let simulated_mtrack = tokio::spawn(simulate_mtrack(mtrack, is_running.clone()));
+    let supervisor = tokio::spawn(supervisor::run(
+        state_machine_option.clone(),
+        config.clone(),
+        snapshots.clone(),
+        shutdown.clone(),
+    ));
+    let snapshot = snapshots
+        .wait_until(CONNECT_TIMEOUT, |snapshot| {
+            snapshot.is_connected
+                && snapshot
+                    .mtrack_state
+                    .as_ref()
+                    .is_some_and(|mtrack_state| !mtrack_state.setlist.is_empty())
+        })
+        .await;
+    assert!(snapshot.is_connected, "the supervisor connects to mtrack");
+
+    let polls = Arc::new(AtomicU64::new(0));
+    let mut clients = Vec::new();
+    for _client in 0..CLIENTS {
+        let snapshots = snapshots.clone();
+        let config = config.clone();
+        let auto_advance = auto_advance.clone();
+        let durations = durations.clone();
+        let polls = polls.clone();
+        let is_polling = is_polling.clone();
+        clients.push(tokio::spawn(async move {
+            let mut cursor = None;
+            while is_polling.load(Ordering::Relaxed) {
+                let update =
+                    read_state_update(&snapshots, &config, &auto_advance, &durations, cursor).await;
+                if let Polling::LongPoll = polling {
+                    cursor = update.cursor().or(cursor);
+                }
+                polls.fetch_add(1, Ordering::Relaxed);
+                tokio::task::yield_now().await;
+            }
+        }));
+    }
+
+    let started = Instant::now();
+    let mut results = Results {
+        polls: 0,
+        polled_for: Duration::ZERO,
+        commands: 0,
+        failed_commands: 0,
+        command_wait: Duration::ZERO,
+        max_command_wait: Duration::ZERO,
+    };
+    while started.elapsed() < RUN_TIME {
+        tokio::time::sleep(COMMAND_INTERVAL).await;
+        let queued = Instant::now();
+        let result = run_command(&state_machine_option, false, TransportCommand::Next, None).await;
+        let waited = queued.elapsed();
+        results.commands += 1;
+        if !matches!(result, Ok(ack) if ack.status == CommandStatus::Confirmed) {
+            results.failed_commands += 1;
+        }
+        results.command_wait += waited;
+        results.max_command_wait = results.max_command_wait.max(waited);
+    }
+    results.polls = polls.load(Ordering::Relaxed);
+    results.polled_for = started.elapsed();
+
+    // Long polls still waiting return with the next elapsed time from mtrack.
+    is_polling.store(false, Ordering::Relaxed);
+    for client in clients {
+        let _result = client.await;
+    }
+    shutdown.trigger();
+    let _result = supervisor.await;
+    is_running.store(false, Ordering::Relaxed);
+    let _result = simulated_mtrack.await;
+    if let Some(state_machine) = state_machine_option.write().await.take() {
+        state_machine.disconnect().await;
+    }
+    results
+}
+
+fn main() {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("runtime starts");
+    for (name, polling) in [
+        ("full state", Polling::FullState),
+        ("long poll", Polling::LongPoll),
+    ] {
+        let results = runtime.block_on(run(polling));
+        println!(
+            "{name:>10}: {CLIENTS} clients, {:>10.0} polls/s, {} commands ({} unconfirmed), \
+             command wait {:?} avg, {:?} max",
+            results.polls as f64 / results.polled_for.as_secs_f64(),
+            results.commands,
+            results.failed_commands,
+            results.command_wait / results.commands.max(1),
+            results.max_command_wait,
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+
+use super::{command::TransportCommand, osc::MtrackState};
+
+#[cfg(feature = "server")]
+use std::{
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
+
+#[cfg(feature = "server")]
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+
+#[cfg(feature = "server")]
+use tokio::sync::RwLock;
+
+#[cfg(feature = "server")]
+use super::{
+    command::{goto_song, run_bundle, run_command, CommandAck, CommandError},
+    config::Config,
+    metrics::ServerFnTimer,
+    server::OscStateMachine,
+    supervisor::{Snapshots, COMMAND_CONNECT_TIMEOUT},
+};
+
+/// Prefix of the JSON API used for scripting.
+pub const API_PREFIX: &str = "/api/v1";
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ApiStatus {
+    pub connected: bool,
+    pub show_lock: bool,
+    pub mtrack: Option<MtrackState>,
+}
+
+/// Query of `POST /api/v1/stop`, confirming the stop whose answer had the ID `confirm`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct StopQuery {
+    pub confirm: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct GotoRequest {
+    pub song: String,
+}
+
+/// Commands mtrack applies together, `delay_ms` after the request.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BundleRequest {
+    pub commands: Vec<TransportCommand>,
+    #[serde(default)]
+    pub delay_ms: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ScheduledBundle {
+    pub commands: Vec<TransportCommand>,
+    /// When mtrack applies the commands, in milliseconds since the Unix epoch.
+    pub at_ms: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ApiError {
+    pub error: String,
+}
+
+#[cfg(feature = "server")]
+type ApiResult<T> = Result<Json<T>, (StatusCode, Json<ApiError>)>;
+
+#[cfg(feature = "server")]
+#[derive(Clone)]
+pub struct ApiState {
+    pub state_machine_option: Arc<RwLock<Option<OscStateMachine>>>,
+    pub config: Arc<RwLock<Config>>,
+    pub snapshots: Arc<Snapshots>,
+}
+
+/// Routes of the JSON API, commands respect the show lock like the web client.
+#[cfg(feature = "server")]
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route(&format!("{API_PREFIX}/status"), get(get_status))
+        .route(&format!("{API_PREFIX}/setlist"), get(get_setlist))
+        .route(&format!("{API_PREFIX}/play"), post(post_play))
+        .route(&format!("{API_PREFIX}/stop"), post(post_stop))
+        .route(&format!("{API_PREFIX}/next"), post(post_next))
+        .route(&format!("{API_PREFIX}/prev"), post(post_prev))
+        .route(&format!("{API_PREFIX}/goto"), post(post_goto))
+        .route(&format!("{API_PREFIX}/bundle"), post(post_bundle))
+        .with_state(state)
+}
+
+#[cfg(feature = "server")]
+fn api_error(err: CommandError) -> (StatusCode, Json<ApiError>) {
+    let status = match err {
+        CommandError::NotConnected => StatusCode::SERVICE_UNAVAILABLE,
+        CommandError::SongNotFound(_) => StatusCode::NOT_FOUND,
+        CommandError::EmptyBundle => StatusCode::BAD_REQUEST,
+        CommandError::ShowLock(_) => StatusCode::CONFLICT,
+        CommandError::NotConfirmed(_) => StatusCode::GATEWAY_TIMEOUT,
+        CommandError::Send(_) => StatusCode::BAD_GATEWAY,
+    };
+    (
+        status,
+        Json(ApiError {
+            error: err.to_string(),
+        }),
+    )
+}
+
+#[cfg(feature = "server")]
+async fn read_status(state: &ApiState) -> ApiStatus {
+    let snapshot = state.snapshots.load();
+    ApiStatus {
+        connected: snapshot.is_connected,
+        show_lock: state.config.read().await.show_lock,
+        mtrack: snapshot.mtrack_state.clone(),
+    }
+}
+
+#[cfg(feature = "server")]
+async fn get_status(State(state): State<ApiState>) -> Json<ApiStatus> {
+    let _timer = ServerFnTimer::new("api_status");
+    Json(read_status(&state).await)
+}
+
+#[cfg(feature = "server")]
+async fn get_setlist(State(state): State<ApiState>) -> ApiResult<Vec<String>> {
+    let _timer = ServerFnTimer::new("api_setlist");
+    match read_status(&state).await.mtrack {
+        Some(mtrack) => Ok(Json(mtrack.setlist)),
+        None => Err(api_error(CommandError::NotConnected)),
+    }
+}
+
+#[cfg(feature = "server")]
+async fn post_command(
+    state: ApiState,
+    command: TransportCommand,
+    confirms: Option<u64>,
+) -> ApiResult<CommandAck> {
+    if !state
+        .snapshots
+        .wait_connected(COMMAND_CONNECT_TIMEOUT)
+        .await
+    {
+        return Err(api_error(CommandError::NotConnected));
+    }
+    let show_lock = state.config.read().await.show_lock;
+    match run_command(&state.state_machine_option, show_lock, command, confirms).await {
+        Ok(ack) => Ok(Json(ack)),
+        Err(err) => Err(api_error(err)),
+    }
+}
+
+#[cfg(feature = "server")]
+async fn post_play(State(state): State<ApiState>) -> ApiResult<CommandAck> {
+    let _timer = ServerFnTimer::new("api_play");
+    post_command(state, TransportCommand::Play, None).await
+}
+
+#[cfg(feature = "server")]
+async fn post_stop(
+    State(state): State<ApiState>,
+    Query(query): Query<StopQuery>,
+) -> ApiResult<CommandAck> {
+    let _timer = ServerFnTimer::new("api_stop");
+    post_command(state, TransportCommand::Stop, query.confirm).await
+}
+
+#[cfg(feature = "server")]
+async fn post_next(State(state): State<ApiState>) -> ApiResult<CommandAck> {
+    let _timer = ServerFnTimer::new("api_next");
+    post_command(state, TransportCommand::Next, None).await
+}
+
+#[cfg(feature = "server")]
+async fn post_prev(State(state): State<ApiState>) -> ApiResult<CommandAck> {
+    let _timer = ServerFnTimer::new("api_prev");
+    post_command(state, TransportCommand::Prev, None).await
+}
+
+/// Going to a song is refused while playing if the show lock is on.
+#[cfg(feature = "server")]
+async fn post_goto(
+    State(state): State<ApiState>,
+    Json(request): Json<GotoRequest>,
+) -> ApiResult<MtrackState> {
+    let _timer = ServerFnTimer::new("api_goto");
+    if !state
+        .snapshots
+        .wait_connected(COMMAND_CONNECT_TIMEOUT)
+        .await
+    {
+        return Err(api_error(CommandError::NotConnected));
+    }
+    let show_lock = state.config.read().await.show_lock;
+    if let Err(err) = goto_song(&state.state_machine_option, show_lock, &request.song).await {
+        return Err(api_error(err));
+    }
+    match read_status(&state).await.mtrack {
+        Some(mtrack) => Ok(Json(mtrack)),
+        None => Err(api_error(CommandError::NotConnected)),
+    }
+}
+
+#[cfg(feature = "server")]
+async fn post_bundle(
+    State(state): State<ApiState>,
+    Json(request): Json<BundleRequest>,
+) -> ApiResult<ScheduledBundle> {
+    let _timer = ServerFnTimer::new("api_bundle");
+    if request.commands.is_empty() {
+        return Err(api_error(CommandError::EmptyBundle));
+    }
+    if !state
+        .snapshots
+        .wait_connected(COMMAND_CONNECT_TIMEOUT)
+        .await
+    {
+        return Err(api_error(CommandError::NotConnected));
+    }
+    let show_lock = state.config.read().await.show_lock;
+    let delay = Duration::from_millis(request.delay_ms);
+    match run_bundle(
+        &state.state_machine_option,
+        show_lock,
+        &request.commands,
+        delay,
+    )
+    .await
+    {
+        Ok(at) => Ok(Json(ScheduledBundle {
+            commands: request.commands,
+            at_ms: at
+                .duration_since(UNIX_EPOCH)
+                .map(|since_epoch| since_epoch.as_millis() as u64)
+                .unwrap_or_default(),
+        })),
+        Err(err) => Err(api_error(err)),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    #[cfg(feature = "server")]
+    #[test]
+    fn command_errors_map_to_http_statuses() {
+        use axum::http::StatusCode;
+
+        use super::api_error;
+        use crate::backend::command::CommandError;
+
+        let (status, error) = api_error(CommandError::SongNotFound("Encore".to_string()));
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(error.error, "Song Encore is not in the setlist!");
+        let (status, _error) = api_error(CommandError::NotConnected);
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        let (status, _error) = api_error(CommandError::ShowLock("locked".to_string()));
+        assert_eq!(status, StatusCode::CONFLICT);
+    }
+}
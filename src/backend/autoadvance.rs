@@ -0,0 +1,277 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::command::playlist_entry_matches;
+
+#[cfg(feature = "server")]
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "server")]
+use dioxus::logger::tracing::{debug, info, warn};
+
+#[cfg(feature = "server")]
+use tokio::{select, sync::RwLock};
+
+#[cfg(feature = "server")]
+use super::{
+    command::{
+        send_command, wait_for_effect, CommandStatus, ExpectedEffect, TransportCommand,
+        COMMAND_ACK_TIMEOUT,
+    },
+    config::get_config_dir,
+    durations::SongDurations,
+    osc::MtrackState,
+    playback::{get_playback_state, SongEndDetector, PLAYBACK_POLL_INTERVAL},
+    server::OscStateMachine,
+    service::Shutdown,
+};
+
+pub const DEFAULT_GAP_SECS: u16 = 5;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AutoAdvanceError {
+    #[error("Could not get config directory! {0}")]
+    Directory(String),
+    #[error("Could not read auto-advance settings! {0}")]
+    Read(String),
+    #[error("Could not write auto-advance settings! {0}")]
+    Write(String),
+    #[error("Could not (de)serialize auto-advance settings! {0}")]
+    Serde(String),
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AutoAdvanceSettings {
+    pub enabled: bool,
+    pub gap_secs: u16,
+    /// Gaps before individual songs, keyed by their setlist entry.
+    pub song_gaps: BTreeMap<String, u16>,
+}
+
+impl Default for AutoAdvanceSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            gap_secs: DEFAULT_GAP_SECS,
+            song_gaps: BTreeMap::new(),
+        }
+    }
+}
+
+impl AutoAdvanceSettings {
+    /// Gap before `song` starts, matched against setlist entries like the playlist does.
+    pub fn gap_secs_for(&self, song: &str) -> u16 {
+        match song.is_empty() {
+            true => self.gap_secs,
+            false => self
+                .song_gaps
+                .iter()
+                .find(|(entry, _gap)| playlist_entry_matches(entry, song))
+                .map_or(self.gap_secs, |(_entry, gap)| *gap),
+        }
+    }
+}
+
+/// A running countdown until the next song is played.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Countdown {
+    pub song: String,
+    pub remaining_ms: u64,
+}
+
+#[cfg(feature = "server")]
+fn get_settings_file_path() -> Result<PathBuf, AutoAdvanceError> {
+    match get_config_dir() {
+        Ok(dir) => Ok(dir.join("auto_advance.json")),
+        Err(err) => Err(AutoAdvanceError::Directory(err.to_string())),
+    }
+}
+
+#[cfg(feature = "server")]
+impl AutoAdvanceSettings {
+    pub fn read_settings() -> Result<Self, AutoAdvanceError> {
+        let path = get_settings_file_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let serialized = match std::fs::read_to_string(path) {
+            Ok(serialized) => serialized,
+            Err(err) => return Err(AutoAdvanceError::Read(err.to_string())),
+        };
+        match serde_json::from_str(&serialized) {
+            Ok(settings) => Ok(settings),
+            Err(err) => Err(AutoAdvanceError::Serde(err.to_string())),
+        }
+    }
+
+    pub fn write_settings(&self) -> Result<(), AutoAdvanceError> {
+        let serialized = match serde_json::to_string(self) {
+            Ok(serialized) => serialized,
+            Err(err) => return Err(AutoAdvanceError::Serde(err.to_string())),
+        };
+        let path = get_settings_file_path()?;
+        if let Some(dir) = path.parent() {
+            if let Err(err) = std::fs::create_dir_all(dir) {
+                return Err(AutoAdvanceError::Write(err.to_string()));
+            }
+        }
+        match std::fs::write(path, serialized) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(AutoAdvanceError::Write(err.to_string())),
+        }
+    }
+}
+
+/// Auto-advance settings and the currently running countdown, if any.
+#[cfg(feature = "server")]
+#[derive(Debug, Default)]
+pub struct AutoAdvance {
+    pub settings: AutoAdvanceSettings,
+    countdown: Option<(String, Instant)>,
+}
+
+#[cfg(feature = "server")]
+impl AutoAdvance {
+    pub fn new(settings: AutoAdvanceSettings) -> Self {
+        Self {
+            settings,
+            countdown: None,
+        }
+    }
+
+    pub fn get_countdown(&self) -> Option<Countdown> {
+        self.countdown.as_ref().map(|(song, deadline)| Countdown {
+            song: song.clone(),
+            remaining_ms: deadline
+                .saturating_duration_since(Instant::now())
+                .as_millis() as u64,
+        })
+    }
+
+    pub fn cancel(&mut self) {
+        if let Some((song, _deadline)) = self.countdown.take() {
+            info!("Cancelled auto-advance to {song}");
+        }
+    }
+}
+
+/// Skips to the next song, counts down its gap and plays it unless cancelled.
+#[cfg(feature = "server")]
+async fn advance(
+    state_machine_option: &Arc<RwLock<Option<OscStateMachine>>>,
+    auto_advance: &Arc<RwLock<AutoAdvance>>,
+    ended: &MtrackState,
+) {
+    let mtrack = match send_command(state_machine_option, TransportCommand::Next).await {
+        Ok(mtrack) => mtrack,
+        Err(err) => {
+            warn!("Auto-advance could not skip to the next song! {err}");
+            return;
+        }
+    };
+    let effect = ExpectedEffect::new(TransportCommand::Next, ended);
+    if wait_for_effect(mtrack.clone(), &effect, COMMAND_ACK_TIMEOUT).await
+        != CommandStatus::Confirmed
+    {
+        info!("No next song after {}, auto-advance stops", ended.song);
+        return;
+    }
+
+    let song = mtrack.borrow().song.clone();
+    {
+        let mut auto_advance = auto_advance.write().await;
+        let gap = Duration::from_secs(auto_advance.settings.gap_secs_for(&song).into());
+        info!("Auto-advancing to {song} in {gap:?}");
+        auto_advance.countdown = Some((song, Instant::now() + gap));
+    }
+
+    loop {
+        tokio::time::sleep(PLAYBACK_POLL_INTERVAL).await;
+        if mtrack.borrow().is_playing {
+            debug!("Playback was started during the countdown");
+            auto_advance.write().await.cancel();
+            return;
+        }
+        let mut auto_advance = auto_advance.write().await;
+        match auto_advance.countdown.as_ref() {
+            Some((_song, deadline)) if Instant::now() >= *deadline => {
+                auto_advance.countdown = None;
+                break;
+            }
+            Some(_countdown) => continue,
+            None => return,
+        }
+    }
+    if let Err(err) = send_command(state_machine_option, TransportCommand::Play).await {
+        warn!("Auto-advance could not start playback! {err}");
+    }
+}
+
+/// Watches the mtrack state and auto-advances when a song ended on its own,
+/// i.e. nobody sent a stop and it reached its learned duration, until `shutdown`.
+#[cfg(feature = "server")]
+pub async fn run(
+    state_machine_option: Arc<RwLock<Option<OscStateMachine>>>,
+    auto_advance: Arc<RwLock<AutoAdvance>>,
+    durations: Arc<RwLock<SongDurations>>,
+    shutdown: Shutdown,
+) {
+    let mut song_end_detector = SongEndDetector::default();
+    loop {
+        select! {
+            _ = shutdown.wait() => {
+                debug!("Auto-advance stopped");
+                return;
+            }
+            _ = tokio::time::sleep(PLAYBACK_POLL_INTERVAL) => {}
+        }
+        let playback_state = get_playback_state(&state_machine_option).await;
+        let ended = match playback_state.as_ref() {
+            Some((mtrack_state, stopped_by_user)) => {
+                song_end_detector.update(Some(mtrack_state), *stopped_by_user)
+            }
+            None => song_end_detector.update(None, false),
+        };
+        let ended = match ended {
+            Some(ended) => ended,
+            None => continue,
+        };
+        if !durations
+            .read()
+            .await
+            .reached_end(&ended.song, &ended.time_elapsed)
+        {
+            debug!("{} was stopped before its end", ended.song);
+            continue;
+        }
+        if auto_advance.read().await.settings.enabled {
+            select! {
+                _ = shutdown.wait() => {
+                    auto_advance.write().await.cancel();
+                    debug!("Auto-advance stopped");
+                    return;
+                }
+                _ = advance(&state_machine_option, &auto_advance, &ended) => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::AutoAdvanceSettings;
+
+    #[test]
+    fn song_gaps_override_default_gap() {
+        let mut settings = AutoAdvanceSettings::default();
+        settings.song_gaps.insert("2. Ballad".to_string(), 12);
+        assert_eq!(settings.gap_secs_for("Ballad"), 12);
+        assert_eq!(settings.gap_secs_for("Opener"), settings.gap_secs);
+        assert_eq!(settings.gap_secs_for(""), settings.gap_secs);
+    }
+}
@@ -0,0 +1,457 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+#[cfg(feature = "server")]
+use dioxus::logger::tracing::debug;
+
+#[cfg(feature = "server")]
+use tokio::sync::{watch, RwLock};
+
+#[cfg(feature = "server")]
+use super::{
+    server::{OscStateMachine, ServerMessage},
+    showlock::ShowLockError,
+};
+
+use super::osc::MtrackState;
+
+/// Time mtrack has to reflect a command in its state.
+#[cfg(feature = "server")]
+pub const COMMAND_ACK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[cfg(feature = "server")]
+static NEXT_COMMAND_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum CommandError {
+    #[error("OSC state machine is not connected!")]
+    NotConnected,
+    #[error("Could not send command! {0}")]
+    Send(String),
+    #[error("mtrack did not confirm {0}!")]
+    NotConfirmed(&'static str),
+    #[error("Song {0} is not in the setlist!")]
+    SongNotFound(String),
+    #[error("{0}")]
+    ShowLock(String),
+    #[error("A bundle needs at least one command!")]
+    EmptyBundle,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum TransportCommand {
+    Play,
+    Stop,
+    Next,
+    Prev,
+}
+
+impl TransportCommand {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransportCommand::Play => "play",
+            TransportCommand::Stop => "stop",
+            TransportCommand::Next => "next",
+            TransportCommand::Prev => "prev",
+        }
+    }
+
+    #[cfg(feature = "server")]
+    pub fn server_message(&self) -> ServerMessage {
+        match self {
+            TransportCommand::Play => ServerMessage::Play,
+            TransportCommand::Stop => ServerMessage::Stop,
+            TransportCommand::Next => ServerMessage::Next,
+            TransportCommand::Prev => ServerMessage::Prev,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum CommandStatus {
+    Pending,
+    /// Rejected by the show lock until the command is sent again, confirming this answer's ID.
+    ConfirmationRequired,
+    Confirmed,
+    /// mtrack already was in the state the command leads to, so no change could confirm it.
+    AlreadyInEffect,
+    TimedOut,
+    Failed(String),
+}
+
+/// Answer of the server once a command was confirmed by mtrack or timed out.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct CommandAck {
+    pub id: u64,
+    pub command: TransportCommand,
+    pub status: CommandStatus,
+}
+
+/// The change in the mtrack state a command is expected to cause.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExpectedEffect {
+    Playing,
+    Stopped,
+    SongChanged { from: String },
+}
+
+impl ExpectedEffect {
+    pub fn new(command: TransportCommand, before: &MtrackState) -> Self {
+        match command {
+            TransportCommand::Play => ExpectedEffect::Playing,
+            TransportCommand::Stop => ExpectedEffect::Stopped,
+            TransportCommand::Next | TransportCommand::Prev => ExpectedEffect::SongChanged {
+                from: before.song.clone(),
+            },
+        }
+    }
+
+    pub fn is_met(&self, state: &MtrackState) -> bool {
+        match self {
+            ExpectedEffect::Playing => state.is_playing,
+            ExpectedEffect::Stopped => !state.is_playing,
+            ExpectedEffect::SongChanged { from } => &state.song != from,
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+pub fn next_command_id() -> u64 {
+    NEXT_COMMAND_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Watches the mtrack state until `effect` is met or `timeout` elapsed.
+#[cfg(feature = "server")]
+pub async fn wait_for_effect(
+    mut mtrack: watch::Receiver<MtrackState>,
+    effect: &ExpectedEffect,
+    timeout: Duration,
+) -> CommandStatus {
+    match tokio::time::timeout(timeout, mtrack.wait_for(|state| effect.is_met(state))).await {
+        Ok(Ok(_state)) => CommandStatus::Confirmed,
+        Ok(Err(_closed)) => CommandStatus::Failed("Lost connection to mtrack!".to_string()),
+        Err(_elapsed) => CommandStatus::TimedOut,
+    }
+}
+
+/// Sends `command` and returns the mtrack state to watch for its effect.
+/// The state machine is only locked while sending.
+#[cfg(feature = "server")]
+pub async fn send_command(
+    state_machine_option: &Arc<RwLock<Option<OscStateMachine>>>,
+    command: TransportCommand,
+) -> Result<watch::Receiver<MtrackState>, CommandError> {
+    let state_machine_option = state_machine_option.read().await;
+    let state_machine = match state_machine_option.as_ref() {
+        Some(state_machine) => state_machine,
+        None => return Err(CommandError::NotConnected),
+    };
+    let result = match command {
+        TransportCommand::Play => state_machine.play().await,
+        TransportCommand::Stop => state_machine.stop().await,
+        TransportCommand::Next => state_machine.next().await,
+        TransportCommand::Prev => state_machine.prev().await,
+    };
+    if let Err(err) = result {
+        return Err(CommandError::Send(err.to_string()));
+    }
+    match state_machine.mtrack_state() {
+        Some(mtrack) => Ok(mtrack),
+        None => Err(CommandError::NotConnected),
+    }
+}
+
+/// Steps through the setlist with next/prev until `song` is the current song,
+/// refused while playing if `show_lock` is on.
+#[cfg(feature = "server")]
+pub async fn goto_song(
+    state_machine_option: &Arc<RwLock<Option<OscStateMachine>>>,
+    show_lock: bool,
+    song: &str,
+) -> Result<(), CommandError> {
+    let mtrack_state = match state_machine_option.read().await.as_ref() {
+        Some(state_machine) => match state_machine.get_mtrack_data() {
+            Ok(mtrack_state) => mtrack_state,
+            Err(_err) => return Err(CommandError::NotConnected),
+        },
+        None => return Err(CommandError::NotConnected),
+    };
+    check_goto(show_lock, &mtrack_state)?;
+    let target = match mtrack_state
+        .setlist
+        .iter()
+        .position(|entry| playlist_entry_matches(entry, song))
+    {
+        Some(target) => target,
+        None => return Err(CommandError::SongNotFound(song.to_string())),
+    };
+
+    let mut current_state = mtrack_state;
+    for _step in 0..=current_state.setlist.len() {
+        let command = match step_towards(&current_state, target) {
+            Some(command) => command,
+            None => return Ok(()),
+        };
+        let mtrack = send_command(state_machine_option, command).await?;
+        let effect = ExpectedEffect::new(command, &current_state);
+        if wait_for_effect(mtrack.clone(), &effect, COMMAND_ACK_TIMEOUT).await
+            != CommandStatus::Confirmed
+        {
+            return Err(CommandError::NotConfirmed(command.name()));
+        }
+        current_state = mtrack.borrow().clone();
+        // Playback may have been started meanwhile.
+        check_goto(show_lock, &current_state)?;
+    }
+    Err(CommandError::NotConfirmed("goto"))
+}
+
+#[cfg(feature = "server")]
+fn check_goto(show_lock: bool, mtrack_state: &MtrackState) -> Result<(), CommandError> {
+    match show_lock && mtrack_state.is_playing {
+        true => Err(CommandError::ShowLock(
+            ShowLockError::Locked("goto").to_string(),
+        )),
+        false => Ok(()),
+    }
+}
+
+/// Sends `command`, checked against the show lock if `show_lock` is on,
+/// and waits for mtrack to reflect it. `confirms` is the ID of the answer to an earlier
+/// stop of the same client that needed confirmation.
+#[cfg(feature = "server")]
+pub async fn run_command(
+    state_machine_option: &Arc<RwLock<Option<OscStateMachine>>>,
+    show_lock: bool,
+    command: TransportCommand,
+    confirms: Option<u64>,
+) -> Result<CommandAck, CommandError> {
+    let id = next_command_id();
+    let before = {
+        let state_machine_option = state_machine_option.read().await;
+        let state_machine = match state_machine_option.as_ref() {
+            Some(state_machine) if state_machine.is_connected().await => state_machine,
+            _ => return Err(CommandError::NotConnected),
+        };
+        let before = match state_machine.get_mtrack_data() {
+            Ok(before) => before,
+            Err(err) => return Err(CommandError::Send(err.to_string())),
+        };
+        if show_lock {
+            let show_lock_result = match state_machine.show_lock.lock() {
+                Ok(mut show_lock) => show_lock.check(command, before.is_playing, id, confirms),
+                Err(err) => return Err(CommandError::ShowLock(err.to_string())),
+            };
+            match show_lock_result {
+                Ok(_) => {}
+                Err(ShowLockError::ConfirmStop) => {
+                    debug!("Command {id} {command:?} needs confirmation");
+                    return Ok(CommandAck {
+                        id,
+                        command,
+                        status: CommandStatus::ConfirmationRequired,
+                    });
+                }
+                Err(err) => return Err(CommandError::ShowLock(err.to_string())),
+            }
+        }
+        before
+    };
+    let mtrack = send_command(state_machine_option, command).await?;
+    let effect = ExpectedEffect::new(command, &before);
+    if effect.is_met(&before) {
+        debug!("Command {id} {command:?} sent, mtrack already was {effect:?}");
+        return Ok(CommandAck {
+            id,
+            command,
+            status: CommandStatus::AlreadyInEffect,
+        });
+    }
+
+    // The state machine lock is released while waiting, so polling clients are not blocked.
+    debug!("Command {id} {command:?} sent, waiting for {effect:?}");
+    let status = wait_for_effect(mtrack, &effect, COMMAND_ACK_TIMEOUT).await;
+    debug!("Command {id} {command:?}: {status:?}");
+    Ok(CommandAck {
+        id,
+        command,
+        status,
+    })
+}
+
+/// Sends `commands` as one bundle for mtrack to apply together after `delay`
+/// and returns when they are due. While playing with the show lock on only play is allowed,
+/// as a bundle cannot ask for confirmation.
+#[cfg(feature = "server")]
+pub async fn run_bundle(
+    state_machine_option: &Arc<RwLock<Option<OscStateMachine>>>,
+    show_lock: bool,
+    commands: &[TransportCommand],
+    delay: Duration,
+) -> Result<SystemTime, CommandError> {
+    if commands.is_empty() {
+        return Err(CommandError::EmptyBundle);
+    }
+    let state_machine_option = state_machine_option.read().await;
+    let state_machine = match state_machine_option.as_ref() {
+        Some(state_machine) if state_machine.is_connected().await => state_machine,
+        _ => return Err(CommandError::NotConnected),
+    };
+    let is_playing = match state_machine.get_mtrack_data() {
+        Ok(mtrack_state) => mtrack_state.is_playing,
+        Err(err) => return Err(CommandError::Send(err.to_string())),
+    };
+    if show_lock && is_playing {
+        if let Some(command) = commands
+            .iter()
+            .find(|command| **command != TransportCommand::Play)
+        {
+            let err = ShowLockError::Locked(command.name());
+            return Err(CommandError::ShowLock(err.to_string()));
+        }
+    }
+    let at = SystemTime::now() + delay;
+    let messages = commands
+        .iter()
+        .map(TransportCommand::server_message)
+        .collect();
+    match state_machine.send_bundle(messages, at).await {
+        Ok(_) => {
+            debug!("Bundle {commands:?} scheduled for {at:?}");
+            Ok(at)
+        }
+        Err(err) => Err(CommandError::Send(err.to_string())),
+    }
+}
+
+/// Whether setlist `entry` like "3. Someone" is `song`, compared without its number,
+/// so "One" does not match it.
+pub fn playlist_entry_matches(entry: &str, song: &str) -> bool {
+    entry == song || strip_entry_number(entry) == Some(song)
+}
+
+/// The song name of a setlist entry like "3. Someone", `None` without a number.
+fn strip_entry_number(entry: &str) -> Option<&str> {
+    let (number, song) = entry.split_once(". ")?;
+    match !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) {
+        true => Some(song),
+        false => None,
+    }
+}
+
+/// The command moving the current song towards setlist index `target`, `None` once there.
+pub fn step_towards(state: &MtrackState, target: usize) -> Option<TransportCommand> {
+    let current = match state.song.is_empty() {
+        true => None,
+        false => state
+            .setlist
+            .iter()
+            .position(|entry| playlist_entry_matches(entry, &state.song)),
+    };
+    match current {
+        Some(current) if current == target => None,
+        Some(current) if current > target => Some(TransportCommand::Prev),
+        _ => Some(TransportCommand::Next),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{step_towards, ExpectedEffect, TransportCommand};
+    use crate::backend::osc::MtrackState;
+
+    #[cfg(feature = "server")]
+    use super::{wait_for_effect, CommandStatus};
+
+    fn state(is_playing: bool, song: &str) -> MtrackState {
+        MtrackState {
+            is_playing,
+            song: song.to_string(),
+            ..MtrackState::default()
+        }
+    }
+
+    #[test]
+    fn expected_effects_match_state_changes() {
+        let before = state(false, "First");
+        let play = ExpectedEffect::new(TransportCommand::Play, &before);
+        assert!(!play.is_met(&before));
+        assert!(play.is_met(&state(true, "First")));
+
+        let next = ExpectedEffect::new(TransportCommand::Next, &before);
+        assert!(!next.is_met(&state(true, "First")));
+        assert!(next.is_met(&state(false, "Second")));
+    }
+
+    #[test]
+    fn goto_steps_towards_target_song() {
+        let mut current = state(false, "Second");
+        current.setlist = vec!["1. First".into(), "2. Second".into(), "3. Third".into()];
+        assert_eq!(step_towards(&current, 0), Some(TransportCommand::Prev));
+        assert_eq!(step_towards(&current, 1), None);
+        assert_eq!(step_towards(&current, 2), Some(TransportCommand::Next));
+    }
+
+    #[test]
+    fn songs_only_match_whole_entries() {
+        use super::playlist_entry_matches;
+
+        assert!(playlist_entry_matches("3. Someone", "Someone"));
+        assert!(playlist_entry_matches("3. Someone", "3. Someone"));
+        assert!(!playlist_entry_matches("3. Someone", "One"));
+        assert!(!playlist_entry_matches("3. Someone", "one"));
+        assert!(!playlist_entry_matches("Intro. Someone", "Someone"));
+
+        let mut current = state(false, "One");
+        current.setlist = vec!["1. Someone".into(), "2. One".into(), "3. Closer".into()];
+        assert_eq!(step_towards(&current, 1), None);
+        assert_eq!(step_towards(&current, 0), Some(TransportCommand::Prev));
+    }
+
+    #[test]
+    fn play_while_playing_is_already_in_effect() {
+        let playing = state(true, "First");
+        assert!(ExpectedEffect::new(TransportCommand::Play, &playing).is_met(&playing));
+        assert!(!ExpectedEffect::new(TransportCommand::Stop, &playing).is_met(&playing));
+        assert!(!ExpectedEffect::new(TransportCommand::Next, &playing).is_met(&playing));
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn goto_is_refused_while_playing_with_show_lock() {
+        use super::{check_goto, CommandError};
+
+        assert!(matches!(
+            check_goto(true, &state(true, "First")),
+            Err(CommandError::ShowLock(_))
+        ));
+        assert_eq!(check_goto(true, &state(false, "First")), Ok(()));
+        assert_eq!(check_goto(false, &state(true, "First")), Ok(()));
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn commands_are_confirmed_or_time_out() {
+        let (_mtrack_tx, mtrack) = tokio::sync::watch::channel(state(false, "First"));
+        let timeout = std::time::Duration::from_millis(100);
+        let effect = ExpectedEffect::new(TransportCommand::Stop, &state(true, "First"));
+        assert_eq!(
+            wait_for_effect(mtrack.clone(), &effect, timeout).await,
+            CommandStatus::Confirmed
+        );
+
+        let effect = ExpectedEffect::new(TransportCommand::Play, &state(false, "First"));
+        assert_eq!(
+            wait_for_effect(mtrack, &effect, timeout).await,
+            CommandStatus::TimedOut
+        );
+    }
+}
@@ -3,8 +3,10 @@ use std::{
     io::{BufReader, Read, Write},
     net::{Ipv4Addr, SocketAddr},
     path::PathBuf,
+    time::Duration,
 };
 
+use chrono::NaiveTime;
 use dioxus::logger::tracing::debug;
 use serde::{Deserialize, Serialize};
 
@@ -28,20 +30,65 @@ pub enum ConfigError {
     CouldNotCreateDirectory(String),
 }
 
+/// How OSC packets travel between mtrack-remote and mtrack.
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum OscProtocol {
+    #[default]
+    Udp,
+    /// OSC 1.1 over a TCP connection with SLIP framing.
+    Tcp,
+}
+
 #[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct Config {
     pub mtrack_addr: SocketAddr,
     pub listen_port: u16,
+    #[serde(default = "default_oscquery_port")]
+    pub oscquery_port: u16,
+    /// Guards destructive transport commands during playback, toggled separately.
+    #[serde(default)]
+    pub show_lock: bool,
+    /// Local time the show has to end by.
+    #[serde(default)]
+    pub curfew: Option<NaiveTime>,
+    #[serde(default)]
+    pub osc_protocol: OscProtocol,
+    /// How often song, playlist and status are requested again, 0 only does so on connect.
+    #[serde(default = "default_refresh_interval_secs")]
+    pub refresh_interval_secs: u16,
 }
 
 pub const DEFAULT_MTRACK_PORT: u16 = 43234;
 pub const DEFAULT_LISTEN_PORT: u16 = 43236;
+pub const DEFAULT_OSCQUERY_PORT: u16 = 43237;
+pub const DEFAULT_REFRESH_INTERVAL_SECS: u16 = 30;
+
+fn default_oscquery_port() -> u16 {
+    DEFAULT_OSCQUERY_PORT
+}
+
+fn default_refresh_interval_secs() -> u16 {
+    DEFAULT_REFRESH_INTERVAL_SECS
+}
 
 impl Config {
     pub fn new() -> Self {
         Self {
             mtrack_addr: SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), DEFAULT_MTRACK_PORT),
             listen_port: DEFAULT_LISTEN_PORT,
+            oscquery_port: DEFAULT_OSCQUERY_PORT,
+            show_lock: false,
+            curfew: None,
+            osc_protocol: OscProtocol::Udp,
+            refresh_interval_secs: DEFAULT_REFRESH_INTERVAL_SECS,
+        }
+    }
+
+    /// Interval of the periodic refresh, `None` if it is turned off.
+    pub fn refresh_interval(&self) -> Option<Duration> {
+        match self.refresh_interval_secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs.into())),
         }
     }
 }
@@ -54,11 +101,16 @@ impl Default for Config {
                 DEFAULT_MTRACK_PORT,
             ),
             listen_port: DEFAULT_LISTEN_PORT,
+            oscquery_port: DEFAULT_OSCQUERY_PORT,
+            show_lock: false,
+            curfew: None,
+            osc_protocol: OscProtocol::Udp,
+            refresh_interval_secs: DEFAULT_REFRESH_INTERVAL_SECS,
         }
     }
 }
 
-fn get_config_dir() -> Result<PathBuf, ConfigError> {
+pub fn get_config_dir() -> Result<PathBuf, ConfigError> {
     let dir = match dirs::config_local_dir() {
         Some(dir) => dir,
         None => return Err(ConfigError::CouldNotGetConfigDir),
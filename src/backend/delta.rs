@@ -0,0 +1,274 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        LazyLock,
+    },
+};
+
+use super::osc::MtrackState;
+
+/// Revisions are shared by all connections, so they keep increasing across reconnects.
+#[cfg(feature = "server")]
+static NEXT_REVISION: AtomicU64 = AtomicU64::new(1);
+
+/// Random per server run, as revisions start over when the server restarts.
+#[cfg(feature = "server")]
+static EPOCH: LazyLock<u64> = LazyLock::new(|| RandomState::new().build_hasher().finish());
+
+#[cfg(feature = "server")]
+pub fn next_revision() -> u64 {
+    NEXT_REVISION.fetch_add(1, Ordering::Relaxed)
+}
+
+#[cfg(feature = "server")]
+pub fn epoch() -> u64 {
+    *EPOCH
+}
+
+/// The last delta a client applied, passed back to only get what changed since.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct DeltaCursor {
+    pub epoch: u64,
+    pub revision: u64,
+}
+
+/// The revision each field of the mtrack state last changed at.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FieldRevisions {
+    pub is_known: u64,
+    pub is_playing: u64,
+    pub time_elapsed: u64,
+    pub song: u64,
+    pub setlist: u64,
+}
+
+impl FieldRevisions {
+    fn all(revision: u64) -> Self {
+        Self {
+            is_known: revision,
+            is_playing: revision,
+            time_elapsed: revision,
+            song: revision,
+            setlist: revision,
+        }
+    }
+}
+
+/// The fields of the mtrack state that changed since a revision the client knows.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct MtrackDelta {
+    pub epoch: u64,
+    pub revision: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_known: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_playing: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_elapsed: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub song: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub setlist: Option<Vec<String>>,
+}
+
+impl MtrackDelta {
+    /// Whether the current song or setlist changed, e.g. so learned durations have to be resent.
+    pub fn changes_song(&self) -> bool {
+        self.song.is_some() || self.setlist.is_some() || self.is_playing.is_some()
+    }
+
+    pub fn cursor(&self) -> DeltaCursor {
+        DeltaCursor {
+            epoch: self.epoch,
+            revision: self.revision,
+        }
+    }
+}
+
+impl MtrackState {
+    /// A state whose fields are all new to clients knowing an older revision.
+    pub fn at_revision(revision: u64) -> Self {
+        Self {
+            revision,
+            changed: FieldRevisions::all(revision),
+            ..Self::default()
+        }
+    }
+
+    /// Bumps the revision of the fields that differ from `before`,
+    /// returns whether any did.
+    #[cfg(feature = "server")]
+    pub fn mark_changes(&mut self, before: &MtrackState) -> bool {
+        let is_known = self.is_known != before.is_known;
+        let is_playing = self.is_playing != before.is_playing;
+        let time_elapsed = self.time_elapsed != before.time_elapsed;
+        let song = self.song != before.song;
+        let setlist = self.setlist != before.setlist;
+        if !(is_known || is_playing || time_elapsed || song || setlist) {
+            return false;
+        }
+        let revision = next_revision();
+        self.revision = revision;
+        if is_known {
+            self.changed.is_known = revision;
+        }
+        if is_playing {
+            self.changed.is_playing = revision;
+        }
+        if time_elapsed {
+            self.changed.time_elapsed = revision;
+        }
+        if song {
+            self.changed.song = revision;
+        }
+        if setlist {
+            self.changed.setlist = revision;
+        }
+        true
+    }
+
+    /// The fields changed after `since`, all of them without one or if `since`
+    /// is from a server run other than `epoch`.
+    pub fn delta_since(&self, epoch: u64, since: Option<DeltaCursor>) -> MtrackDelta {
+        let since = match since {
+            Some(since) if since.epoch == epoch && since.revision <= self.revision => {
+                since.revision
+            }
+            _ => 0,
+        };
+        MtrackDelta {
+            epoch,
+            revision: self.revision,
+            is_known: (self.changed.is_known > since).then_some(self.is_known),
+            is_playing: (self.changed.is_playing > since).then_some(self.is_playing),
+            time_elapsed: (self.changed.time_elapsed > since).then(|| self.time_elapsed.clone()),
+            song: (self.changed.song > since).then(|| self.song.clone()),
+            setlist: (self.changed.setlist > since).then(|| self.setlist.clone()),
+        }
+    }
+
+    pub fn apply_delta(&mut self, delta: MtrackDelta) {
+        self.revision = delta.revision;
+        if let Some(is_known) = delta.is_known {
+            self.is_known = is_known;
+        }
+        if let Some(is_playing) = delta.is_playing {
+            self.is_playing = is_playing;
+        }
+        if let Some(time_elapsed) = delta.time_elapsed {
+            self.time_elapsed = time_elapsed;
+        }
+        if let Some(song) = delta.song {
+            self.song = song;
+        }
+        if let Some(setlist) = delta.setlist {
+            self.setlist = setlist;
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::backend::osc::MtrackState;
+
+    use super::{DeltaCursor, MtrackDelta};
+
+    const EPOCH: u64 = 7;
+
+    fn cursor(revision: u64) -> Option<DeltaCursor> {
+        Some(DeltaCursor {
+            epoch: EPOCH,
+            revision,
+        })
+    }
+
+    #[test]
+    fn deltas_only_carry_changed_fields() {
+        let mut server_state = MtrackState::at_revision(10);
+        server_state.setlist = vec!["1. Opener".to_string(), "2. Closer".to_string()];
+        server_state.song = "Opener".to_string();
+
+        let mut client_state = MtrackState::default();
+        let full = server_state.delta_since(EPOCH, None);
+        assert_eq!(full.setlist, Some(server_state.setlist.clone()));
+        client_state.apply_delta(full);
+        assert_eq!(client_state.song, "Opener");
+
+        server_state.revision = 11;
+        server_state.changed.time_elapsed = 11;
+        server_state.time_elapsed = "0:01".to_string();
+        let delta = server_state.delta_since(EPOCH, cursor(10));
+        assert_eq!(
+            delta,
+            MtrackDelta {
+                epoch: EPOCH,
+                revision: 11,
+                time_elapsed: Some("0:01".to_string()),
+                ..MtrackDelta::default()
+            }
+        );
+        assert!(!delta.changes_song());
+        client_state.apply_delta(delta);
+        assert_eq!(client_state.revision, 11);
+        assert_eq!(client_state.setlist, server_state.setlist);
+        assert_eq!(client_state.time_elapsed, "0:01");
+
+        // A revision from before a restart of the server gets everything.
+        assert!(server_state
+            .delta_since(EPOCH, cursor(500))
+            .setlist
+            .is_some());
+    }
+
+    #[test]
+    fn deltas_after_a_restart_are_full() {
+        let mut stale_client = MtrackState::at_revision(1);
+        stale_client.song = "Opener".to_string();
+        stale_client.setlist = vec!["1. Opener".to_string()];
+        stale_client.revision = 2;
+        let stale = cursor(2);
+
+        // The restarted server counts revisions from the start again.
+        let mut server_state = MtrackState::at_revision(1);
+        server_state.song = "Closer".to_string();
+        server_state.setlist = vec!["1. Encore".to_string(), "2. Closer".to_string()];
+        server_state.revision = 5;
+        server_state.changed.time_elapsed = 5;
+        server_state.time_elapsed = "0:42".to_string();
+
+        let restarted_epoch = EPOCH + 1;
+        let delta = server_state.delta_since(restarted_epoch, stale);
+        assert_eq!(delta.epoch, restarted_epoch);
+        assert_eq!(delta.song, Some("Closer".to_string()));
+        stale_client.apply_delta(delta);
+        assert_eq!(stale_client.song, server_state.song);
+        assert_eq!(stale_client.setlist, server_state.setlist);
+        assert_eq!(stale_client.time_elapsed, "0:42");
+
+        let same_run = server_state.delta_since(EPOCH, stale);
+        assert_eq!(same_run.song, None);
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn changes_get_a_new_revision() {
+        let before = MtrackState::at_revision(super::next_revision());
+        let mut state = before.clone();
+        assert!(!state.mark_changes(&before));
+        state.song = "Closer".to_string();
+        assert!(state.mark_changes(&before));
+        assert!(state.revision > before.revision);
+        let since = super::DeltaCursor {
+            epoch: super::epoch(),
+            revision: before.revision,
+        };
+        let delta = state.delta_since(super::epoch(), Some(since));
+        assert_eq!(delta.song, Some("Closer".to_string()));
+        assert_eq!(delta.setlist, None);
+    }
+}
@@ -0,0 +1,172 @@
+use std::{collections::BTreeMap, net::SocketAddr};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use std::{net::IpAddr, sync::Arc};
+
+#[cfg(feature = "server")]
+use dioxus::logger::tracing::{debug, info, warn};
+
+#[cfg(feature = "server")]
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+
+#[cfg(feature = "server")]
+use tokio::sync::RwLock;
+
+/// DNS-SD service type mtrack instances are browsed for.
+pub const MTRACK_SERVICE_TYPE: &str = "_mtrack._udp.local.";
+/// DNS-SD service type the web UI is advertised as.
+pub const REMOTE_SERVICE_TYPE: &str = "_mtrack-remote._tcp.local.";
+/// Host name phones can reach the web UI at.
+pub const REMOTE_HOSTNAME: &str = "mtrack-remote.local.";
+
+#[derive(Debug, thiserror::Error)]
+pub enum DiscoveryError {
+    #[error("Could not start mDNS daemon! {0}")]
+    Daemon(String),
+    #[error("Could not browse for mtrack! {0}")]
+    Browse(String),
+    #[error("Could not advertise mtrack-remote! {0}")]
+    Advertise(String),
+}
+
+/// An mtrack instance found on the LAN.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct DiscoveredHost {
+    pub name: String,
+    pub hostname: String,
+    pub addr: SocketAddr,
+}
+
+/// The mtrack instances currently advertised on the LAN, keyed by their full service name.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DiscoveredHosts {
+    hosts: BTreeMap<String, DiscoveredHost>,
+}
+
+impl DiscoveredHosts {
+    pub fn insert(&mut self, fullname: &str, host: DiscoveredHost) {
+        self.hosts.insert(fullname.to_string(), host);
+    }
+
+    pub fn remove(&mut self, fullname: &str) {
+        self.hosts.remove(fullname);
+    }
+
+    pub fn list(&self) -> Vec<DiscoveredHost> {
+        self.hosts.values().cloned().collect()
+    }
+}
+
+/// Instance name of a full service name like `Stage left._mtrack._udp.local.`.
+pub fn get_instance_name(fullname: &str, service_type: &str) -> String {
+    fullname
+        .strip_suffix(service_type)
+        .map(|name| name.trim_end_matches('.'))
+        .unwrap_or(fullname)
+        .to_string()
+}
+
+/// The host a resolved service can be reached at, preferring IPv4 as the config does.
+#[cfg(feature = "server")]
+fn get_discovered_host(info: &ServiceInfo) -> Option<DiscoveredHost> {
+    let mut addresses: Vec<&IpAddr> = info.get_addresses().iter().collect();
+    addresses.sort_by_key(|address| (address.is_ipv6(), address.is_loopback()));
+    let address = addresses.first()?;
+    Some(DiscoveredHost {
+        name: get_instance_name(info.get_fullname(), MTRACK_SERVICE_TYPE),
+        hostname: info.get_hostname().trim_end_matches('.').to_string(),
+        addr: SocketAddr::new(**address, info.get_port()),
+    })
+}
+
+/// Browses for mtrack instances and advertises the web UI on `port`.
+/// The returned daemon has to be shut down on exit.
+#[cfg(feature = "server")]
+pub fn start(
+    hosts: Arc<RwLock<DiscoveredHosts>>,
+    port: u16,
+    is_https: bool,
+) -> Result<ServiceDaemon, DiscoveryError> {
+    let daemon = match ServiceDaemon::new() {
+        Ok(daemon) => daemon,
+        Err(err) => return Err(DiscoveryError::Daemon(err.to_string())),
+    };
+    let receiver = match daemon.browse(MTRACK_SERVICE_TYPE) {
+        Ok(receiver) => receiver,
+        Err(err) => return Err(DiscoveryError::Browse(err.to_string())),
+    };
+    tokio::spawn(async move {
+        while let Ok(event) = receiver.recv_async().await {
+            match event {
+                ServiceEvent::ServiceResolved(info) => match get_discovered_host(&info) {
+                    Some(host) => {
+                        info!("Discovered mtrack {} at {}", host.name, host.addr);
+                        hosts.write().await.insert(info.get_fullname(), host);
+                    }
+                    None => debug!("mtrack {} has no address", info.get_fullname()),
+                },
+                ServiceEvent::ServiceRemoved(_service_type, fullname) => {
+                    debug!("mtrack {fullname} went away");
+                    hosts.write().await.remove(&fullname);
+                }
+                _ => {}
+            }
+        }
+        debug!("Stopped browsing for mtrack");
+    });
+
+    let scheme = if is_https { "https" } else { "http" };
+    let properties = [("path", "/mtrack"), ("scheme", scheme)];
+    let service_info = match ServiceInfo::new(
+        REMOTE_SERVICE_TYPE,
+        "mtrack-remote",
+        REMOTE_HOSTNAME,
+        "",
+        port,
+        &properties[..],
+    ) {
+        Ok(service_info) => service_info.enable_addr_auto(),
+        Err(err) => return Err(DiscoveryError::Advertise(err.to_string())),
+    };
+    match daemon.register(service_info) {
+        Ok(_) => info!("Advertising {REMOTE_SERVICE_TYPE} as {REMOTE_HOSTNAME}"),
+        Err(err) => warn!("{}", DiscoveryError::Advertise(err.to_string())),
+    };
+    Ok(daemon)
+}
+
+/// Withdraws the advertisement and stops browsing.
+#[cfg(feature = "server")]
+pub fn stop(daemon: &ServiceDaemon) {
+    if let Err(err) = daemon.shutdown() {
+        warn!("Could not stop mDNS daemon! {err}");
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    use super::{get_instance_name, DiscoveredHost, DiscoveredHosts, MTRACK_SERVICE_TYPE};
+
+    #[test]
+    fn hosts_are_listed_by_service_name_until_removed() {
+        assert_eq!(
+            get_instance_name("Stage left._mtrack._udp.local.", MTRACK_SERVICE_TYPE),
+            "Stage left"
+        );
+        let host = DiscoveredHost {
+            name: "Stage left".to_string(),
+            hostname: "stage-left.local".to_string(),
+            addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20)), 43234),
+        };
+        let mut hosts = DiscoveredHosts::default();
+        hosts.insert("Stage left._mtrack._udp.local.", host.clone());
+        hosts.insert("Stage left._mtrack._udp.local.", host.clone());
+        assert_eq!(hosts.list(), vec![host]);
+        hosts.remove("Stage left._mtrack._udp.local.");
+        assert!(hosts.list().is_empty());
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::command::playlist_entry_matches;
+
+#[cfg(feature = "server")]
+use std::{path::PathBuf, sync::Arc};
+
+#[cfg(feature = "server")]
+use dioxus::logger::tracing::{debug, info, warn};
+
+#[cfg(feature = "server")]
+use tokio::{select, sync::RwLock};
+
+#[cfg(feature = "server")]
+use super::{
+    config::get_config_dir,
+    playback::{get_playback_state, SongEndDetector, PLAYBACK_POLL_INTERVAL},
+    server::OscStateMachine,
+    service::Shutdown,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum DurationsError {
+    #[error("Could not get config directory! {0}")]
+    Directory(String),
+    #[error("Could not read song durations! {0}")]
+    Read(String),
+    #[error("Could not write song durations! {0}")]
+    Write(String),
+    #[error("Could not (de)serialize song durations! {0}")]
+    Serde(String),
+}
+
+/// The last elapsed time mtrack reports can be up to one report short of the song's end.
+const SONG_END_TOLERANCE_MS: u64 = 1000;
+
+/// Parses elapsed times like `1:02:03`, `2:03` or `2:03.5` into milliseconds.
+pub fn parse_elapsed(elapsed: &str) -> Option<u64> {
+    let mut parts = elapsed.trim().rsplit(':');
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = match parts.next() {
+        Some(minutes) => minutes.parse().ok()?,
+        None => 0,
+    };
+    let hours: u64 = match parts.next() {
+        Some(hours) => hours.parse().ok()?,
+        None => 0,
+    };
+    if parts.next().is_some() || seconds < 0.0 {
+        return None;
+    }
+    Some((hours * 3600 + minutes * 60) * 1000 + (seconds * 1000.0) as u64)
+}
+
+/// Formats milliseconds as `m:ss`.
+pub fn format_duration(duration_ms: u64) -> String {
+    let seconds = duration_ms / 1000;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Song durations learned from playbacks that ended on their own, keyed by song name.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct SongDurations {
+    pub durations_ms: BTreeMap<String, u64>,
+}
+
+impl SongDurations {
+    pub fn learn(&mut self, song: &str, duration_ms: u64) {
+        self.durations_ms.insert(song.to_string(), duration_ms);
+    }
+
+    /// Learned duration of a song or setlist entry, matched like the playlist does.
+    pub fn get(&self, song: &str) -> Option<u64> {
+        if let Some(duration_ms) = self.durations_ms.get(song) {
+            return Some(*duration_ms);
+        }
+        self.durations_ms
+            .iter()
+            .find(|(name, _duration)| !name.is_empty() && playlist_entry_matches(song, name))
+            .map(|(_name, duration)| *duration)
+    }
+
+    /// Whether `time_elapsed` reached the learned duration of `song`, so it ended on its own
+    /// rather than being stopped on mtrack itself. Songs without one are assumed to have ended.
+    pub fn reached_end(&self, song: &str, time_elapsed: &str) -> bool {
+        match (self.get(song), parse_elapsed(time_elapsed)) {
+            (Some(duration_ms), Some(elapsed_ms)) => {
+                elapsed_ms + SONG_END_TOLERANCE_MS >= duration_ms
+            }
+            (Some(_duration_ms), None) => false,
+            (None, _) => true,
+        }
+    }
+
+    /// Learned durations of the setlist entries, keyed by entry.
+    pub fn for_setlist(&self, setlist: &[String]) -> BTreeMap<String, u64> {
+        setlist
+            .iter()
+            .filter_map(|entry| Some((entry.clone(), self.get(entry)?)))
+            .collect()
+    }
+}
+
+#[cfg(feature = "server")]
+fn get_durations_file_path() -> Result<PathBuf, DurationsError> {
+    match get_config_dir() {
+        Ok(dir) => Ok(dir.join("durations.json")),
+        Err(err) => Err(DurationsError::Directory(err.to_string())),
+    }
+}
+
+#[cfg(feature = "server")]
+impl SongDurations {
+    pub fn read_durations() -> Result<Self, DurationsError> {
+        let path = get_durations_file_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let serialized = match std::fs::read_to_string(path) {
+            Ok(serialized) => serialized,
+            Err(err) => return Err(DurationsError::Read(err.to_string())),
+        };
+        match serde_json::from_str(&serialized) {
+            Ok(durations) => Ok(durations),
+            Err(err) => Err(DurationsError::Serde(err.to_string())),
+        }
+    }
+
+    pub fn write_durations(&self) -> Result<(), DurationsError> {
+        let serialized = match serde_json::to_string(self) {
+            Ok(serialized) => serialized,
+            Err(err) => return Err(DurationsError::Serde(err.to_string())),
+        };
+        let path = get_durations_file_path()?;
+        if let Some(dir) = path.parent() {
+            if let Err(err) = std::fs::create_dir_all(dir) {
+                return Err(DurationsError::Write(err.to_string()));
+            }
+        }
+        match std::fs::write(path, serialized) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(DurationsError::Write(err.to_string())),
+        }
+    }
+}
+
+/// Learns the duration of every song that ends on its own from its final elapsed time,
+/// until `shutdown`.
+#[cfg(feature = "server")]
+pub async fn run(
+    state_machine_option: Arc<RwLock<Option<OscStateMachine>>>,
+    durations: Arc<RwLock<SongDurations>>,
+    shutdown: Shutdown,
+) {
+    let mut song_end_detector = SongEndDetector::default();
+    loop {
+        select! {
+            _ = shutdown.wait() => {
+                debug!("Learning song durations stopped");
+                return;
+            }
+            _ = tokio::time::sleep(PLAYBACK_POLL_INTERVAL) => {}
+        }
+        let ended = match get_playback_state(&state_machine_option).await {
+            Some((mtrack_state, stopped_by_user)) => {
+                song_end_detector.update(Some(&mtrack_state), stopped_by_user)
+            }
+            None => song_end_detector.update(None, false),
+        };
+        let ended = match ended {
+            Some(ended) if !ended.song.is_empty() => ended,
+            _ => continue,
+        };
+        let duration_ms = match parse_elapsed(&ended.time_elapsed) {
+            Some(duration_ms) if duration_ms > 0 => duration_ms,
+            _ => continue,
+        };
+        let mut durations = durations.write().await;
+        if !durations.reached_end(&ended.song, &ended.time_elapsed) {
+            info!(
+                "{} stopped at {}, before its learned end",
+                ended.song, ended.time_elapsed
+            );
+            continue;
+        }
+        info!(
+            "Learned duration of {}: {}",
+            ended.song,
+            format_duration(duration_ms)
+        );
+        durations.learn(&ended.song, duration_ms);
+        if let Err(err) = durations.write_durations() {
+            warn!("Could not save song durations! {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{format_duration, parse_elapsed, SongDurations};
+
+    #[test]
+    fn elapsed_times_are_parsed() {
+        assert_eq!(parse_elapsed("3:21"), Some(201_000));
+        assert_eq!(parse_elapsed("1:00:01.5"), Some(3_601_500));
+        assert_eq!(parse_elapsed("42"), Some(42_000));
+        assert_eq!(parse_elapsed(""), None);
+        assert_eq!(parse_elapsed("1:2:3:4"), None);
+        assert_eq!(format_duration(201_999), "3:21");
+    }
+
+    #[test]
+    fn durations_match_setlist_entries() {
+        let mut durations = SongDurations::default();
+        durations.learn("Opener", 201_000);
+        let setlist = vec!["1. Opener".to_string(), "2. Ballad".to_string()];
+        let for_setlist = durations.for_setlist(&setlist);
+        assert_eq!(for_setlist.get("1. Opener"), Some(&201_000));
+        assert_eq!(for_setlist.get("2. Ballad"), None);
+    }
+
+    #[test]
+    fn songs_stopped_early_did_not_reach_their_end() {
+        let mut durations = SongDurations::default();
+        durations.learn("Opener", 201_000);
+        assert!(durations.reached_end("1. Opener", "3:20"));
+        assert!(durations.reached_end("1. Opener", "3:21"));
+        assert!(!durations.reached_end("1. Opener", "1:00"));
+        assert!(!durations.reached_end("1. Opener", ""));
+        assert!(durations.reached_end("2. Ballad", "1:00"));
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{http::header::CONTENT_TYPE, http::StatusCode, response::IntoResponse};
+use dioxus::logger::tracing::error;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramTimer, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    Opts, Registry, TextEncoder,
+};
+
+use super::{command::playlist_entry_matches, osc::MtrackState};
+
+/// Web clients that polled within this window are counted as connected.
+const WEB_CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+pub struct Metrics {
+    registry: Registry,
+    pub osc_packets_sent: IntCounterVec,
+    pub osc_packets_received: IntCounterVec,
+    pub osc_decode_errors: IntCounter,
+    pub osc_rejected_packets: IntCounterVec,
+    pub osc_connects: IntCounter,
+    pub osc_reconnects: IntCounter,
+    pub osc_command_latency: HistogramVec,
+    pub osc_connected: IntGauge,
+    pub current_song_index: IntGauge,
+    pub playing: IntGauge,
+    pub web_clients: IntGauge,
+    pub server_fn_calls: IntCounterVec,
+    pub server_fn_duration: HistogramVec,
+    web_client_last_seen: Mutex<HashMap<IpAddr, Instant>>,
+}
+
+fn counter(name: &str, help: &str) -> IntCounter {
+    IntCounter::new(name, help).expect("valid counter options")
+}
+
+fn counter_vec(name: &str, help: &str, labels: &[&str]) -> IntCounterVec {
+    IntCounterVec::new(Opts::new(name, help), labels).expect("valid counter options")
+}
+
+fn gauge(name: &str, help: &str) -> IntGauge {
+    IntGauge::new(name, help).expect("valid gauge options")
+}
+
+fn histogram_vec(name: &str, help: &str, labels: &[&str]) -> HistogramVec {
+    HistogramVec::new(HistogramOpts::new(name, help), labels).expect("valid histogram options")
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let metrics = Self {
+            registry: Registry::new_custom(Some("mtrack_remote".to_string()), None)
+                .expect("valid registry prefix"),
+            osc_packets_sent: counter_vec(
+                "osc_packets_sent_total",
+                "OSC packets sent to mtrack",
+                &["address"],
+            ),
+            osc_packets_received: counter_vec(
+                "osc_packets_received_total",
+                "OSC packets received on the listen port",
+                &["address"],
+            ),
+            osc_decode_errors: counter("osc_decode_errors_total", "Undecodable OSC packets"),
+            osc_rejected_packets: counter_vec(
+                "osc_rejected_packets_total",
+                "OSC packets dropped before being applied",
+                &["reason"],
+            ),
+            osc_connects: counter("osc_connects_total", "Established OSC connections"),
+            osc_reconnects: counter(
+                "osc_reconnects_total",
+                "OSC connections established after the first one",
+            ),
+            osc_command_latency: histogram_vec(
+                "osc_command_latency_seconds",
+                "Time between queueing a command and sending it to mtrack",
+                &["address"],
+            ),
+            osc_connected: gauge("osc_connected", "Whether the OSC connection is up"),
+            current_song_index: gauge(
+                "current_song_index",
+                "Index of the current song in the setlist, -1 if unknown",
+            ),
+            playing: gauge("playing", "Whether mtrack is playing"),
+            web_clients: gauge("web_clients", "Web clients that polled recently"),
+            server_fn_calls: counter_vec(
+                "server_fn_calls_total",
+                "Server function calls",
+                &["function"],
+            ),
+            server_fn_duration: histogram_vec(
+                "server_fn_duration_seconds",
+                "Server function durations",
+                &["function"],
+            ),
+            web_client_last_seen: Mutex::new(HashMap::new()),
+        };
+
+        let collectors: Vec<Box<dyn prometheus::core::Collector>> = vec![
+            Box::new(metrics.osc_packets_sent.clone()),
+            Box::new(metrics.osc_packets_received.clone()),
+            Box::new(metrics.osc_decode_errors.clone()),
+            Box::new(metrics.osc_rejected_packets.clone()),
+            Box::new(metrics.osc_connects.clone()),
+            Box::new(metrics.osc_reconnects.clone()),
+            Box::new(metrics.osc_command_latency.clone()),
+            Box::new(metrics.osc_connected.clone()),
+            Box::new(metrics.current_song_index.clone()),
+            Box::new(metrics.playing.clone()),
+            Box::new(metrics.web_clients.clone()),
+            Box::new(metrics.server_fn_calls.clone()),
+            Box::new(metrics.server_fn_duration.clone()),
+        ];
+        for collector in collectors {
+            metrics
+                .registry
+                .register(collector)
+                .expect("metric names are unique");
+        }
+        metrics.current_song_index.set(-1);
+        metrics
+    }
+
+    pub fn record_connect(&self) {
+        if self.osc_connects.get() > 0 {
+            self.osc_reconnects.inc();
+        }
+        self.osc_connects.inc();
+    }
+
+    pub fn record_mtrack_state(&self, mtrack_state: &MtrackState) {
+        self.playing.set(mtrack_state.is_playing.into());
+        let index = match mtrack_state.song.is_empty() {
+            true => None,
+            false => mtrack_state
+                .setlist
+                .iter()
+                .position(|entry| playlist_entry_matches(entry, &mtrack_state.song)),
+        };
+        self.current_song_index
+            .set(index.map_or(-1, |index| index as i64));
+    }
+
+    pub fn record_web_client(&self, addr: IpAddr) {
+        let mut last_seen = match self.web_client_last_seen.lock() {
+            Ok(last_seen) => last_seen,
+            Err(err) => {
+                error!("Could not lock web client registry! {err}");
+                return;
+            }
+        };
+        last_seen.insert(addr, Instant::now());
+        Self::prune(&mut last_seen);
+        self.web_clients.set(last_seen.len() as i64);
+    }
+
+    /// Forgets web clients that stopped polling, so the gauge drops without new polls.
+    pub fn prune_web_clients(&self) {
+        let mut last_seen = match self.web_client_last_seen.lock() {
+            Ok(last_seen) => last_seen,
+            Err(err) => {
+                error!("Could not lock web client registry! {err}");
+                return;
+            }
+        };
+        Self::prune(&mut last_seen);
+        self.web_clients.set(last_seen.len() as i64);
+    }
+
+    fn prune(last_seen: &mut HashMap<IpAddr, Instant>) {
+        let now = Instant::now();
+        last_seen.retain(|_addr, seen| now.duration_since(*seen) < WEB_CLIENT_TIMEOUT);
+    }
+
+    pub fn encode(&self) -> Result<String, prometheus::Error> {
+        let mut buf = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buf)?;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+}
+
+/// Counts a server function call and records its duration when dropped.
+pub struct ServerFnTimer {
+    _timer: HistogramTimer,
+}
+
+impl ServerFnTimer {
+    pub fn new(function: &str) -> Self {
+        METRICS.server_fn_calls.with_label_values(&[function]).inc();
+        let histogram = METRICS.server_fn_duration.with_label_values(&[function]);
+        Self {
+            _timer: histogram.start_timer(),
+        }
+    }
+}
+
+/// Handler for the Prometheus `/metrics` endpoint.
+pub async fn serve_metrics() -> impl IntoResponse {
+    METRICS.prune_web_clients();
+    match METRICS.encode() {
+        Ok(body) => (
+            StatusCode::OK,
+            [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+            body,
+        )
+            .into_response(),
+        Err(err) => {
+            error!("Could not encode metrics! {err}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{ServerFnTimer, METRICS, WEB_CLIENT_TIMEOUT};
+    use crate::backend::osc::MtrackState;
+
+    #[test]
+    fn song_index_follows_setlist() {
+        let mtrack_state = MtrackState {
+            is_playing: true,
+            time_elapsed: String::new(),
+            song: "Second".to_string(),
+            setlist: vec!["1. First".to_string(), "2. Second".to_string()],
+            ..MtrackState::default()
+        };
+        METRICS.record_mtrack_state(&mtrack_state);
+        assert_eq!(METRICS.current_song_index.get(), 1);
+        assert_eq!(METRICS.playing.get(), 1);
+    }
+
+    #[test]
+    fn server_fn_calls_are_exported() {
+        drop(ServerFnTimer::new("test_function"));
+        let encoded = METRICS.encode().expect("metrics encode");
+        assert!(
+            encoded.contains("mtrack_remote_server_fn_calls_total{function=\"test_function\"} 1")
+        );
+        assert!(encoded.contains("mtrack_remote_server_fn_duration_seconds_count"));
+    }
+
+    #[test]
+    fn web_clients_are_pruned_without_new_polls() {
+        use std::{net::IpAddr, time::Instant};
+
+        let addr: IpAddr = "192.0.2.1".parse().unwrap();
+        let stale = Instant::now().checked_sub(WEB_CLIENT_TIMEOUT).unwrap();
+        METRICS
+            .web_client_last_seen
+            .lock()
+            .unwrap()
+            .insert(addr, stale);
+        METRICS.web_clients.set(1);
+        METRICS.prune_web_clients();
+        assert_eq!(METRICS.web_clients.get(), 0);
+    }
+}
@@ -1,3 +1,36 @@
+pub mod api;
+pub mod autoadvance;
+pub mod command;
 pub mod config;
+pub mod delta;
+pub mod discovery;
+pub mod durations;
+#[cfg(feature = "server")]
+pub mod metrics;
+pub mod notes;
 pub mod osc;
+#[cfg(feature = "server")]
+pub mod oscquery;
+#[cfg(feature = "server")]
+pub mod oscfilter;
+#[cfg(feature = "server")]
+pub mod osctime;
+#[cfg(feature = "server")]
+pub mod osctransport;
+#[cfg(feature = "server")]
+pub mod playback;
+#[cfg(feature = "server")]
+pub mod pwa;
+pub mod recording;
+pub mod scheduler;
 pub mod server;
+#[cfg(feature = "server")]
+pub mod service;
+pub mod showtiming;
+#[cfg(feature = "server")]
+pub mod showlock;
+#[cfg(feature = "server")]
+pub mod supervisor;
+pub mod themes;
+pub mod tls;
+pub mod traffic;
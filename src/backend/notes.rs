@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::command::playlist_entry_matches;
+
+#[cfg(feature = "server")]
+use std::path::PathBuf;
+
+#[cfg(feature = "server")]
+use super::config::get_config_dir;
+
+#[derive(Debug, thiserror::Error)]
+pub enum NotesError {
+    #[error("Could not get config directory! {0}")]
+    Directory(String),
+    #[error("Could not read song notes! {0}")]
+    Read(String),
+    #[error("Could not write song notes! {0}")]
+    Write(String),
+    #[error("Could not (de)serialize song notes! {0}")]
+    Serde(String),
+}
+
+/// Notes shown to performers on the stage display, keyed by song name.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct SongNotes {
+    pub notes: BTreeMap<String, String>,
+}
+
+impl SongNotes {
+    /// Sets the notes of a song, empty notes remove them.
+    pub fn set(&mut self, song: &str, notes: &str) {
+        match notes.trim().is_empty() {
+            true => self.notes.remove(song),
+            false => self.notes.insert(song.to_string(), notes.to_string()),
+        };
+    }
+
+    /// Notes of a song or setlist entry, matched like the playlist does.
+    pub fn get(&self, song: &str) -> Option<String> {
+        if let Some(notes) = self.notes.get(song) {
+            return Some(notes.clone());
+        }
+        self.notes
+            .iter()
+            .find(|(name, _notes)| !name.is_empty() && playlist_entry_matches(song, name))
+            .map(|(_name, notes)| notes.clone())
+    }
+}
+
+#[cfg(feature = "server")]
+fn get_notes_file_path() -> Result<PathBuf, NotesError> {
+    match get_config_dir() {
+        Ok(dir) => Ok(dir.join("notes.json")),
+        Err(err) => Err(NotesError::Directory(err.to_string())),
+    }
+}
+
+#[cfg(feature = "server")]
+impl SongNotes {
+    pub fn read_notes() -> Result<Self, NotesError> {
+        let path = get_notes_file_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let serialized = match std::fs::read_to_string(path) {
+            Ok(serialized) => serialized,
+            Err(err) => return Err(NotesError::Read(err.to_string())),
+        };
+        match serde_json::from_str(&serialized) {
+            Ok(notes) => Ok(notes),
+            Err(err) => Err(NotesError::Serde(err.to_string())),
+        }
+    }
+
+    pub fn write_notes(&self) -> Result<(), NotesError> {
+        let serialized = match serde_json::to_string(self) {
+            Ok(serialized) => serialized,
+            Err(err) => return Err(NotesError::Serde(err.to_string())),
+        };
+        let path = get_notes_file_path()?;
+        if let Some(dir) = path.parent() {
+            if let Err(err) = std::fs::create_dir_all(dir) {
+                return Err(NotesError::Write(err.to_string()));
+            }
+        }
+        match std::fs::write(path, serialized) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(NotesError::Write(err.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::SongNotes;
+
+    #[test]
+    fn notes_match_setlist_entries_and_empty_notes_are_removed() {
+        let mut notes = SongNotes::default();
+        notes.set("Opener", "Capo 2, count in slow");
+        assert_eq!(
+            notes.get("1. Opener").as_deref(),
+            Some("Capo 2, count in slow")
+        );
+        assert_eq!(notes.get("2. Ballad"), None);
+        notes.set("Opener", "  ");
+        assert_eq!(notes.get("Opener"), None);
+    }
+}
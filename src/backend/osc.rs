@@ -1,45 +1,81 @@
 #[cfg(feature = "server")]
 use std::{
-    net::{IpAddr, Ipv4Addr, SocketAddr},
-    sync::Arc,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
 };
 
 #[cfg(feature = "server")]
 use dioxus::logger::tracing::{debug, error, info, span, warn, Level};
 use serde::{Deserialize, Serialize};
 
-#[cfg(feature = "server")]
-use thiserror::Error;
+use super::delta::FieldRevisions;
 
 #[cfg(feature = "server")]
-use rosc::encoder;
+use thiserror::Error;
 
 #[cfg(feature = "server")]
-use rosc::{decoder::MTU, OscError, OscMessage, OscPacket};
+use rosc::{OscBundle, OscError, OscMessage, OscPacket, OscTime, OscType};
 
 #[cfg(feature = "server")]
 use tokio::{
-    net::UdpSocket,
     select,
     sync::{
         mpsc::{Receiver, Sender},
-        RwLock,
+        watch, RwLock,
     },
     task::JoinHandle,
+    time::{Interval, MissedTickBehavior},
 };
 
+#[cfg(feature = "server")]
+use tokio_util::sync::CancellationToken;
+
 #[cfg(feature = "server")]
 use crate::backend::server::ServerMessage;
 
+#[cfg(feature = "server")]
+use super::metrics::METRICS;
+
+#[cfg(feature = "server")]
+use super::traffic::{TrafficDirection, TrafficLog};
+
 #[cfg(feature = "server")]
 use super::config::Config;
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg(feature = "server")]
+use super::{
+    delta::next_revision,
+    oscfilter::SourceFilter,
+    osctime::{get_delay, BundleScheduler, IMMEDIATELY, MAX_SCHEDULED_BUNDLES},
+    osctransport::{self, OscTransport},
+};
+
+pub const ADDR_PLAY: &str = "/mtrack/play";
+pub const ADDR_STOP: &str = "/mtrack/stop";
+pub const ADDR_NEXT: &str = "/mtrack/next";
+pub const ADDR_PREV: &str = "/mtrack/prev";
+pub const ADDR_PLAYLIST: &str = "/mtrack/playlist";
+pub const ADDR_SONG: &str = "/mtrack/song";
+pub const ADDR_STATUS: &str = "/mtrack/status";
+pub const ADDR_CURRENT_SONG: &str = "/mtrack/playlist/current_song";
+pub const ADDR_CURRENT_SETLIST: &str = "/mtrack/playlist/current";
+pub const ADDR_ELAPSED: &str = "/mtrack/playlist/current_song/elapsed";
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct MtrackState {
     pub is_playing: bool,
     pub time_elapsed: String,
     pub song: String,
     pub setlist: Vec<String>,
+    /// Whether mtrack answered since connecting, the other fields are unknown until then.
+    #[serde(default)]
+    pub is_known: bool,
+    /// Increases with every change, clients pass it back to only get what changed since.
+    #[serde(default)]
+    pub revision: u64,
+    #[serde(skip)]
+    pub changed: FieldRevisions,
 }
 
 impl MtrackState {
@@ -60,15 +96,23 @@ impl MtrackState {
     }
 }
 
+/// Handle to the actor owning the transport to mtrack.
+/// Commands go through its mailbox and state updates come back through a watch channel,
+/// so sending and receiving never wait for each other.
 #[cfg(feature = "server")]
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub struct OscConnection {
-    socket: Arc<RwLock<Option<UdpSocket>>>,
-    mtrack: Arc<RwLock<MtrackState>>,
-    osc_tx: Option<Sender<ServerMessage>>,
-    task_handle: Arc<RwLock<Option<JoinHandle<Result<(), OscTransportError>>>>>,
+    mailbox: Option<Sender<(ServerMessage, Instant)>>,
+    state: watch::Receiver<MtrackState>,
+    cancel: CancellationToken,
+    task_handle: Option<OscTaskHandle>,
+    traffic: Arc<RwLock<TrafficLog>>,
+    last_stop: Arc<Mutex<Option<Instant>>>,
 }
 
+#[cfg(feature = "server")]
+type OscTaskHandle = JoinHandle<Result<(), OscTransportError>>;
+
 #[cfg(feature = "server")]
 #[derive(Debug, Error)]
 pub enum OscTransportError {
@@ -82,16 +126,20 @@ pub enum OscTransportError {
     IoError(String),
     #[error("Could not send OSC message! {0}")]
     Send(String),
-    #[error("Could not lock mtrack state! {0}")]
-    LockMtrackError(String),
+    #[error("OSC packet of {0} bytes is too large!")]
+    TooLarge(usize),
+    #[error("OSC actor has already been started")]
+    AlreadyStarted,
     #[error("Could not join task! {0}")]
     JoinError(tokio::task::JoinError),
 }
 
 #[cfg(feature = "server")]
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum OscRequests {
     GetSetlist,
     GetSong,
+    GetStatus,
     Play,
     Stop,
     Next,
@@ -99,222 +147,176 @@ enum OscRequests {
 }
 
 #[cfg(feature = "server")]
-fn get_udp_buf(request: OscRequests) -> Vec<u8> {
-    let mut buf: Vec<u8> = Vec::new();
-    let message = match request {
-        OscRequests::GetSetlist => OscMessage {
-            addr: "/mtrack/playlist".to_string(),
-            args: vec![],
-        },
-        OscRequests::GetSong => OscMessage {
-            addr: "/mtrack/song".to_string(),
-            args: vec![],
-        },
-        OscRequests::Play => OscMessage {
-            addr: "/mtrack/play".to_string(),
-            args: vec![],
-        },
-        OscRequests::Stop => OscMessage {
-            addr: "/mtrack/stop".to_string(),
-            args: vec![],
-        },
-        OscRequests::Next => OscMessage {
-            addr: "/mtrack/next".to_string(),
-            args: vec![],
-        },
-        OscRequests::Prev => OscMessage {
-            addr: "/mtrack/prev".to_string(),
-            args: vec![],
-        },
-    };
-    let packet = OscPacket::Message(message);
-    match encoder::encode_into(&packet, &mut buf) {
-        Ok(_num_bytes) => buf,
-        Err(err) => {
-            error!("Could not encode osc packet! {err}");
-            buf
+impl OscRequests {
+    fn address(&self) -> &'static str {
+        match self {
+            OscRequests::GetSetlist => ADDR_PLAYLIST,
+            OscRequests::GetSong => ADDR_SONG,
+            OscRequests::GetStatus => ADDR_STATUS,
+            OscRequests::Play => ADDR_PLAY,
+            OscRequests::Stop => ADDR_STOP,
+            OscRequests::Next => ADDR_NEXT,
+            OscRequests::Prev => ADDR_PREV,
         }
     }
+
+    /// Requests to forward, without `GetStatus` as mtrack reports its status on that address.
+    fn from_address(addr: &str) -> Option<Self> {
+        match addr {
+            ADDR_PLAYLIST => Some(OscRequests::GetSetlist),
+            ADDR_SONG => Some(OscRequests::GetSong),
+            ADDR_PLAY => Some(OscRequests::Play),
+            ADDR_STOP => Some(OscRequests::Stop),
+            ADDR_NEXT => Some(OscRequests::Next),
+            ADDR_PREV => Some(OscRequests::Prev),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+fn get_osc_message(request: OscRequests) -> OscMessage {
+    OscMessage {
+        addr: request.address().to_string(),
+        args: vec![],
+    }
+}
+
+/// Builds a message to an arbitrary address, typing arguments as int, float, bool or string.
+#[cfg(feature = "server")]
+fn get_custom_osc_message(address: String, args: Vec<String>) -> OscMessage {
+    let args = args
+        .into_iter()
+        .map(|arg| {
+            if let Ok(value) = arg.parse::<i32>() {
+                OscType::Int(value)
+            } else if let Ok(value) = arg.parse::<f32>() {
+                OscType::Float(value)
+            } else if let Ok(value) = arg.parse::<bool>() {
+                OscType::Bool(value)
+            } else {
+                OscType::String(arg)
+            }
+        })
+        .collect();
+    OscMessage {
+        addr: address,
+        args,
+    }
+}
+
+/// Collects the mtrack commands contained in a packet, e.g. sent by an OSCQuery client.
+/// Whether messages to `addr` are commands forwarded to mtrack rather than state.
+#[cfg(feature = "server")]
+pub fn is_forwarded_address(addr: &str) -> bool {
+    OscRequests::from_address(addr).is_some()
+}
+
+#[cfg(feature = "server")]
+fn get_forwarded_requests(osc_packet: &OscPacket) -> Vec<OscRequests> {
+    match osc_packet {
+        OscPacket::Message(osc_message) => OscRequests::from_address(&osc_message.addr)
+            .into_iter()
+            .collect(),
+        OscPacket::Bundle(osc_bundle) => osc_bundle
+            .content
+            .iter()
+            .flat_map(get_forwarded_requests)
+            .collect(),
+    }
 }
 
 #[cfg(feature = "server")]
 impl Default for OscConnection {
     fn default() -> Self {
-        Self::new()
+        Self::new(Arc::default(), Arc::default())
     }
 }
 
 #[cfg(feature = "server")]
 impl OscConnection {
-    pub fn new() -> Self {
+    /// `last_stop` is updated whenever a stop is sent to mtrack, whichever path sent it.
+    pub fn new(traffic: Arc<RwLock<TrafficLog>>, last_stop: Arc<Mutex<Option<Instant>>>) -> Self {
         debug!("Initializing OscConnection");
-        let socket: Arc<RwLock<Option<UdpSocket>>> = Arc::new(RwLock::new(None));
-        let osc_tx = None;
-        let mtrack_state = MtrackState::default();
-        let mtrack = Arc::new(RwLock::new(mtrack_state));
-        let task_handle = Arc::new(RwLock::new(None));
+        let (_state_tx, state) = watch::channel(MtrackState::default());
         Self {
-            socket,
-            task_handle,
-            mtrack,
-            osc_tx,
+            mailbox: None,
+            state,
+            cancel: CancellationToken::new(),
+            task_handle: None,
+            traffic,
+            last_stop,
         }
     }
 
     pub async fn init_socket(&mut self, config: Config) -> Result<(), OscTransportError> {
-        info!("Initializing socket");
-
-        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), config.listen_port);
-        match UdpSocket::bind(addr).await {
-            Ok(s) => {
-                debug!("Bound UDP socket");
-                let mut writeable_socket = self.socket.write().await;
-                *writeable_socket = Some(s);
-                debug!("Saved socket");
+        info!("Connecting to mtrack over {:?}", config.osc_protocol);
+        match osctransport::connect(&config).await {
+            Ok(transport) => {
+                let source_filter = SourceFilter::from_config(&config);
+                self.start(transport, source_filter, config.refresh_interval())
+                    .await
             }
-            Err(e) => {
-                error!("Failed to bind socket! {addr} {e}");
-                return Err(OscTransportError::IoError(e.to_string()));
+            Err(err) => {
+                error!("Failed to connect to mtrack! {err}");
+                Err(err)
             }
-        };
-
-        let (tx, mut rx) = tokio::sync::mpsc::channel::<ServerMessage>(16);
-        let socket_move = self.socket.clone();
-        let mtrack = self.mtrack.clone();
-        let mtrack_addr = config.mtrack_addr;
-        let osc_task = async move {
-            let span = span!(Level::DEBUG, "OSC>>");
-            let _entered = span.enter();
-            debug!("Started backend coroutine");
-            loop {
-                if socket_move.read().await.is_none() {
-                    debug!("Socket is none, quitting loop!");
-                    break;
-                }
+        }
+    }
 
-                select! {
-                    received_osc_result = OscConnection::read_from_socket(socket_move.clone()) => {
-                        match received_osc_result {
-                            Ok(osc_packet) => OscConnection::handle_osc_packet(&mtrack, &osc_packet).await,
-                            Err(err) => {error!("Error reading OSC packet: {err}"); panic!()}
-                        };
-                    },
-                    message_result = OscConnection::receive_through_channel(&mut rx) => {
-                        debug!("Received something through channel");
-                        debug!("Received {message_result:?}");
-                        let mut socket_write = socket_move.write().await;
-                        let osc_request = match message_result {
-                            ServerMessage::GetSetlist => OscRequests::GetSetlist,
-                            ServerMessage::GetSong => OscRequests::GetSong,
-                            ServerMessage::Play => OscRequests::Play,
-                            ServerMessage::Stop => OscRequests::Stop,
-                            ServerMessage::Next => OscRequests::Next,
-                            ServerMessage::Prev => OscRequests::Prev,
-                            ServerMessage::Disconnect => {
-                                debug!("Received disconnect message.");
-                                *socket_write = None;
-                                break;
-                            },
-                        };
-
-                        match socket_write.as_ref() {
-                            Some(socket) => {
-                                let buf = get_udp_buf(osc_request);
-                                match socket.send_to(&buf, mtrack_addr).await {
-                                    Ok(ok_result) => {
-                                        debug!("Sent UDP message {ok_result}");
-                                    },
-                                    Err(err) => {
-                                        error!("Failed to send through socket! {err}");
-                                    },
-                                };
-                            },
-                            None => {
-                                error!("Socket has not been initialized, cannot write to it!");
-                            },
-                        };
-                    },
-                }
-            }
-            Ok(())
+    /// Spawns the actor exchanging packets with mtrack over `transport`,
+    /// applying only packets `source_filter` accepts and asking for the whole state
+    /// on start and every `refresh_interval`.
+    pub async fn start(
+        &mut self,
+        transport: Box<dyn OscTransport>,
+        source_filter: SourceFilter,
+        refresh_interval: Option<Duration>,
+    ) -> Result<(), OscTransportError> {
+        if self.task_handle.is_some() {
+            return Err(OscTransportError::AlreadyStarted);
+        }
+        let (mailbox, mailbox_rx) = tokio::sync::mpsc::channel(16);
+        let (state_tx, state) = watch::channel(MtrackState::at_revision(next_revision()));
+        let actor = OscActor {
+            transport,
+            source_filter,
+            mailbox: mailbox_rx,
+            state: state_tx,
+            cancel: self.cancel.clone(),
+            traffic: self.traffic.clone(),
+            last_stop: self.last_stop.clone(),
+            scheduler: BundleScheduler::default(),
+            refresh_interval,
         };
-
-        let task_handle = tokio::spawn(osc_task);
-
-        self.osc_tx = Some(tx);
-        let mut task_handle_write = self.task_handle.write().await;
-        *task_handle_write = Some(task_handle);
-
-        debug!("Got join handle");
+        self.task_handle = Some(tokio::spawn(actor.run()));
+        self.mailbox = Some(mailbox);
+        self.state = state;
+        debug!("Started OSC actor");
         Ok(())
     }
 
+    /// Cancels the actor and waits for it to finish.
     pub async fn disconnect(&mut self) -> Result<(), OscTransportError> {
-        info!("Disconnecting socket");
-        match &self.osc_tx {
-            Some(tx) => match tx.send(ServerMessage::Disconnect).await {
-                Ok(_send_result) => {
-                    let task_handle_clone = self.task_handle.clone();
-                    let mut handle_write = task_handle_clone.write().await;
-                    match handle_write.take() {
-                        Some(handle) => match handle.await {
-                            Ok(result) => result,
-                            Err(err) => {
-                                error!("Could not join task! {err}");
-                                Err(OscTransportError::JoinError(err))
-                            }
-                        },
-                        None => Ok(()),
-                    }
-                }
+        info!("Disconnecting from mtrack");
+        self.cancel.cancel();
+        self.mailbox = None;
+        match self.task_handle.take() {
+            Some(handle) => match handle.await {
+                Ok(result) => result,
                 Err(err) => {
-                    error!("Failed to send disconnect message! {err}");
-                    Err(OscTransportError::Send(err.to_string()))
+                    error!("Could not join task! {err}");
+                    Err(OscTransportError::JoinError(err))
                 }
             },
-            None => {
-                error!("osc_tx is None!");
-                Err(OscTransportError::NotInitialized)
-            }
-        }
-    }
-
-    async fn read_from_socket(
-        socket: Arc<RwLock<Option<UdpSocket>>>,
-    ) -> Result<OscPacket, OscTransportError> {
-        let socket = socket.read().await;
-
-        match socket.as_ref() {
-            Some(socket) => {
-                let mut buf = Vec::with_capacity(MTU);
-                let (_bytes_received, _from_address) = match socket.recv_buf_from(&mut buf).await {
-                    Ok(ok_result) => ok_result,
-                    Err(err) => return Err(OscTransportError::Receive(err)),
-                };
-                match rosc::decoder::decode_udp(&buf) {
-                    Ok((_remainder, osc_packet)) => Ok(osc_packet),
-                    Err(err) => Err(OscTransportError::Decode(err)),
-                }
-            }
-            None => {
-                warn!("Socket is None, cannot read from it!");
-                Err(OscTransportError::NotInitialized)
-            }
-        }
-    }
-
-    async fn receive_through_channel(rx: &mut Receiver<ServerMessage>) -> ServerMessage {
-        match rx.recv().await {
-            Some(message) => message,
-            None => ServerMessage::Disconnect,
+            None => Err(OscTransportError::NotInitialized),
         }
     }
 
-    async fn handle_osc_message(state: &Arc<RwLock<MtrackState>>, osc_message: &OscMessage) {
-        let mut state_mut = state.write().await;
+    fn handle_osc_message(state: &mut MtrackState, osc_message: &OscMessage) {
         match osc_message.addr.as_str() {
-            "/mtrack/playlist/current_song" => {
-                (*state_mut).set_current_song(
+            ADDR_CURRENT_SONG => {
+                state.set_current_song(
                     osc_message
                         .args
                         .iter()
@@ -322,8 +324,8 @@ impl OscConnection {
                         .collect(),
                 );
             }
-            "/mtrack/playlist/current" => {
-                (*state_mut).set_setlist(
+            ADDR_CURRENT_SETLIST => {
+                state.set_setlist(
                     osc_message
                         .args
                         .iter()
@@ -336,8 +338,8 @@ impl OscConnection {
                         .collect(),
                 );
             }
-            "/mtrack/playlist/current_song/elapsed" => {
-                (*state_mut).set_time_elapsed(
+            ADDR_ELAPSED => {
+                state.set_time_elapsed(
                     osc_message
                         .args
                         .iter()
@@ -345,8 +347,8 @@ impl OscConnection {
                         .collect(),
                 );
             }
-            "/mtrack/status" => {
-                (*state_mut).set_is_playing(
+            ADDR_STATUS => {
+                state.set_is_playing(
                     osc_message
                         .args
                         .iter()
@@ -358,36 +360,64 @@ impl OscConnection {
                 let addr = &osc_message.addr;
                 debug!("Received unknown OSC address {addr}");
                 let args = &osc_message.args; //.iter().map(|e| e.).join(", ");
-                debug!("args: {args:?}")
+                debug!("args: {args:?}");
+                return;
             }
         };
+        state.is_known = true;
     }
 
-    async fn handle_osc_packet(state: &Arc<RwLock<MtrackState>>, osc_packet: &OscPacket) {
+    fn apply_osc_packet(state: &mut MtrackState, osc_packet: &OscPacket) {
         match osc_packet {
             OscPacket::Message(osc_message) => {
-                OscConnection::handle_osc_message(state, osc_message).await
+                OscConnection::handle_osc_message(state, osc_message)
             }
             OscPacket::Bundle(osc_bundle) => {
                 for osc_packet in osc_bundle.content.iter() {
-                    Box::pin(OscConnection::handle_osc_packet(state, osc_packet)).await;
+                    OscConnection::apply_osc_packet(state, osc_packet);
                 }
             }
         };
     }
 
-    pub async fn is_connected(&self) -> bool {
-        match self.socket.read().await.as_ref() {
-            Some(_socket) => true,
-            None => false,
+    /// Applies a packet from mtrack and records it in the metrics.
+    pub fn handle_osc_packet(state: &watch::Sender<MtrackState>, osc_packet: &OscPacket) {
+        for address in get_addresses(osc_packet) {
+            METRICS
+                .osc_packets_received
+                .with_label_values(&[address_label(address)])
+                .inc();
         }
+        OscConnection::update_state(state, osc_packet);
+        METRICS.record_mtrack_state(&state.borrow());
     }
 
-    pub fn get_state(&self) -> Result<MtrackState, OscTransportError> {
-        match self.mtrack.try_read() {
-            Ok(mtrack) => Ok(mtrack.clone()),
-            Err(err) => Err(OscTransportError::LockMtrackError(err.to_string())),
-        }
+    /// Applies a replayed packet, which is not recorded in the metrics as mtrack did not send it.
+    pub fn replay_osc_packet(state: &watch::Sender<MtrackState>, osc_packet: &OscPacket) {
+        OscConnection::update_state(state, osc_packet);
+    }
+
+    /// Applies a packet, watchers are only woken if the state changed.
+    fn update_state(state: &watch::Sender<MtrackState>, osc_packet: &OscPacket) {
+        state.send_if_modified(|state| {
+            let before = state.clone();
+            OscConnection::apply_osc_packet(state, osc_packet);
+            state.mark_changes(&before)
+        });
+    }
+
+    /// Whether the actor is still running, it stops once the transport fails.
+    pub async fn is_connected(&self) -> bool {
+        self.mailbox.is_some() && !self.cancel.is_cancelled()
+    }
+
+    /// Watches the mtrack state, updated by the actor.
+    pub fn mtrack_state(&self) -> watch::Receiver<MtrackState> {
+        self.state.clone()
+    }
+
+    pub fn get_state(&self) -> MtrackState {
+        self.state.borrow().clone()
     }
 
     pub async fn fetch_song(&self) -> Result<(), OscTransportError> {
@@ -399,8 +429,8 @@ impl OscConnection {
     }
 
     async fn send_osc_message(&self, message: ServerMessage) -> Result<(), OscTransportError> {
-        match &self.osc_tx {
-            Some(tx) => match tx.send(message).await {
+        match &self.mailbox {
+            Some(mailbox) => match mailbox.send((message, Instant::now())).await {
                 Ok(result) => Ok(result),
                 Err(err) => Err(OscTransportError::Send(err.to_string())),
             },
@@ -423,22 +453,510 @@ impl OscConnection {
     pub async fn prev(&self) -> Result<(), OscTransportError> {
         self.send_osc_message(ServerMessage::Prev).await
     }
+
+    pub async fn send_custom(
+        &self,
+        address: String,
+        args: Vec<String>,
+    ) -> Result<(), OscTransportError> {
+        self.send_osc_message(ServerMessage::Custom { address, args })
+            .await
+    }
+    /// Sends `messages` as one bundle for mtrack to apply together at `at`.
+    pub async fn send_bundle(
+        &self,
+        messages: Vec<ServerMessage>,
+        at: SystemTime,
+    ) -> Result<(), OscTransportError> {
+        self.send_osc_message(ServerMessage::Bundle { messages, at })
+            .await
+    }
 }
 
 #[cfg(feature = "server")]
 impl Drop for OscConnection {
     fn drop(&mut self) {
         debug!("Dropping OSC connection");
-        match self.socket.try_read() {
-            Ok(socket_read) => {
-                if socket_read.is_some() {
-                    panic!("Dropping while still connected!")
+        if self.task_handle.is_some() && !self.cancel.is_cancelled() {
+            warn!("Dropping while still connected, cancelling OSC actor");
+            self.cancel.cancel();
+        }
+    }
+}
+
+/// Owns the transport and is the only task using it.
+#[cfg(feature = "server")]
+struct OscActor {
+    transport: Box<dyn OscTransport>,
+    source_filter: SourceFilter,
+    mailbox: Receiver<(ServerMessage, Instant)>,
+    state: watch::Sender<MtrackState>,
+    cancel: CancellationToken,
+    traffic: Arc<RwLock<TrafficLog>>,
+    last_stop: Arc<Mutex<Option<Instant>>>,
+    scheduler: BundleScheduler,
+    refresh_interval: Option<Duration>,
+}
+
+#[cfg(feature = "server")]
+impl OscActor {
+    async fn run(mut self) -> Result<(), OscTransportError> {
+        let span = span!(Level::DEBUG, "OSC>>");
+        let _entered = span.enter();
+        // However the actor ends, the connection reports itself as disconnected.
+        let _cancel_on_exit = self.cancel.clone().drop_guard();
+        debug!("Started OSC actor");
+        let mut refresh = self.refresh_interval.map(|period| {
+            let mut refresh = tokio::time::interval_at((Instant::now() + period).into(), period);
+            refresh.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            refresh
+        });
+        self.sync().await;
+        loop {
+            let next_due = self.scheduler.next_due();
+            select! {
+                biased;
+                _ = self.cancel.cancelled() => {
+                    debug!("OSC actor cancelled");
+                    return Ok(());
                 }
+                _ = tokio::time::sleep_until(next_due.unwrap_or_else(Instant::now).into()), if next_due.is_some() => {
+                    let (messages, rejected) = self.scheduler.take_due(Instant::now(), SystemTime::now());
+                    self.apply(messages, rejected, self.transport.peer_addr()).await;
+                }
+                received = self.transport.recv() => match received {
+                    Ok((osc_packet, from_address)) => self.receive(osc_packet, from_address).await,
+                    Err(OscTransportError::Decode(err)) => {
+                        METRICS.osc_decode_errors.inc();
+                        warn!("Could not decode OSC packet! {err}");
+                    }
+                    Err(OscTransportError::TooLarge(size)) => {
+                        METRICS.osc_rejected_packets.with_label_values(&["size"]).inc();
+                        warn!("Rejected OSC packet of {size} bytes!");
+                    }
+                    Err(err) => {
+                        error!("Lost connection to mtrack! {err}");
+                        return Err(err);
+                    }
+                },
+                message = self.mailbox.recv() => match message {
+                    Some((message, queued_at)) => self.send(message, queued_at).await,
+                    None => {
+                        debug!("Mailbox closed, stopping OSC actor");
+                        return Ok(());
+                    }
+                },
+                _ = next_refresh(&mut refresh) => self.sync().await,
             }
-            Err(err) => {
-                panic!("Could not get read lock on socket! {err}")
+        }
+    }
+
+    /// Applies a packet to the mtrack state unless it is from an unknown source or malformed.
+    async fn receive(&mut self, osc_packet: OscPacket, from_address: SocketAddr) {
+        if let Err(rejection) = self.source_filter.check(&osc_packet, from_address) {
+            METRICS
+                .osc_rejected_packets
+                .with_label_values(&[rejection.reason()])
+                .inc();
+            warn!("Rejected OSC packet from {from_address}! {rejection}");
+            return;
+        }
+        self.traffic.write().await.push_packet(
+            TrafficDirection::Inbound,
+            &osc_packet,
+            from_address,
+        );
+        // Bundles with a future time tag are applied once they are due.
+        let (messages, rejected) =
+            self.scheduler
+                .take_due_messages(osc_packet, Instant::now(), SystemTime::now());
+        self.apply(messages, rejected, from_address).await;
+    }
+
+    /// Applies due messages and shows bundles from `from_address` that did not fit
+    /// into the schedule in the traffic log.
+    async fn apply(
+        &self,
+        messages: Vec<OscPacket>,
+        rejected: Vec<OscBundle>,
+        from_address: SocketAddr,
+    ) {
+        if !rejected.is_empty() {
+            METRICS
+                .osc_rejected_packets
+                .with_label_values(&["schedule_full"])
+                .inc_by(rejected.len() as u64);
+            warn!(
+                "Rejected {} OSC bundles from {from_address}, {MAX_SCHEDULED_BUNDLES} are scheduled already!",
+                rejected.len()
+            );
+            let mut traffic = self.traffic.write().await;
+            for osc_bundle in rejected {
+                traffic.push_rejected(&OscPacket::Bundle(osc_bundle), from_address);
+            }
+        }
+        for osc_packet in messages {
+            OscConnection::handle_osc_packet(&self.state, &osc_packet);
+            self.forward_requests(&osc_packet).await;
+        }
+    }
+
+    async fn send_packet(&self, osc_packet: &OscPacket) -> Result<(), OscTransportError> {
+        self.transport.send(osc_packet).await?;
+        let now = Instant::now();
+        if let Some(stopped_at) = get_stop_time(osc_packet, now, now, SystemTime::now()) {
+            if let Ok(mut last_stop) = self.last_stop.lock() {
+                *last_stop = Some(stopped_at);
+            }
+        }
+        for address in get_addresses(osc_packet) {
+            METRICS
+                .osc_packets_sent
+                .with_label_values(&[address_label(address)])
+                .inc();
+        }
+        self.traffic.write().await.push_packet(
+            TrafficDirection::Outbound,
+            osc_packet,
+            self.transport.peer_addr(),
+        );
+        Ok(())
+    }
+
+    async fn send_message(&self, osc_message: &OscMessage) -> Result<(), OscTransportError> {
+        self.send_packet(&OscPacket::Message(osc_message.clone()))
+            .await
+    }
+
+    async fn send(&self, message: ServerMessage, queued_at: Instant) {
+        debug!("Received {message:?} through mailbox");
+        let osc_packet = get_server_osc_packet(message);
+        match self.send_packet(&osc_packet).await {
+            Ok(_) => {
+                let address = match &osc_packet {
+                    OscPacket::Message(osc_message) => osc_message.addr.as_str(),
+                    OscPacket::Bundle(_) => "#bundle",
+                };
+                debug!("Sent OSC packet {address}");
+                let label = match &osc_packet {
+                    OscPacket::Message(_) => address_label(address),
+                    OscPacket::Bundle(_) => address,
+                };
+                METRICS
+                    .osc_command_latency
+                    .with_label_values(&[label])
+                    .observe(queued_at.elapsed().as_secs_f64());
+            }
+            Err(err) => error!("Failed to send to mtrack! {err}"),
+        }
+    }
+
+    /// Asks mtrack for the playlist, song and status, so the state is complete
+    /// without waiting for mtrack to send updates on its own.
+    async fn sync(&self) {
+        debug!("Requesting state from mtrack");
+        for request in [
+            OscRequests::GetSetlist,
+            OscRequests::GetSong,
+            OscRequests::GetStatus,
+        ] {
+            if let Err(err) = self.send_message(&get_osc_message(request)).await {
+                error!("Could not request {request:?} from mtrack! {err}");
+            }
+        }
+    }
+
+    async fn forward_requests(&self, osc_packet: &OscPacket) {
+        for request in get_forwarded_requests(osc_packet) {
+            debug!("Forwarding {request:?} to mtrack");
+            if let Err(err) = self.send_message(&get_osc_message(request)).await {
+                error!("Failed to forward request! {err}");
+            }
+        }
+    }
+}
+
+/// Ticks of the refresh interval, never if refreshing is turned off.
+#[cfg(feature = "server")]
+async fn next_refresh(refresh: &mut Option<Interval>) {
+    match refresh {
+        Some(refresh) => {
+            refresh.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// The OSC packet for a message from the server, bundles keep their time tag.
+#[cfg(feature = "server")]
+fn get_server_osc_packet(message: ServerMessage) -> OscPacket {
+    let osc_message = match message {
+        ServerMessage::GetSetlist => get_osc_message(OscRequests::GetSetlist),
+        ServerMessage::GetSong => get_osc_message(OscRequests::GetSong),
+        ServerMessage::Play => get_osc_message(OscRequests::Play),
+        ServerMessage::Stop => get_osc_message(OscRequests::Stop),
+        ServerMessage::Next => get_osc_message(OscRequests::Next),
+        ServerMessage::Prev => get_osc_message(OscRequests::Prev),
+        ServerMessage::Custom { address, args } => get_custom_osc_message(address, args),
+        ServerMessage::Bundle { messages, at } => {
+            return OscPacket::Bundle(OscBundle {
+                timetag: OscTime::try_from(at).unwrap_or(IMMEDIATELY),
+                content: messages.into_iter().map(get_server_osc_packet).collect(),
+            })
+        }
+    };
+    OscPacket::Message(osc_message)
+}
+
+/// Addresses of all messages in `osc_packet`.
+#[cfg(feature = "server")]
+fn get_addresses(osc_packet: &OscPacket) -> Vec<&str> {
+    match osc_packet {
+        OscPacket::Message(osc_message) => vec![osc_message.addr.as_str()],
+        OscPacket::Bundle(osc_bundle) => {
+            osc_bundle.content.iter().flat_map(get_addresses).collect()
+        }
+    }
+}
+
+/// When a stop in `osc_packet` takes effect if it is `due` by then, bundles stop
+/// once their time tag is due.
+#[cfg(feature = "server")]
+fn get_stop_time(
+    osc_packet: &OscPacket,
+    due: Instant,
+    now: Instant,
+    system_now: SystemTime,
+) -> Option<Instant> {
+    match osc_packet {
+        OscPacket::Message(osc_message) => (osc_message.addr == ADDR_STOP).then_some(due),
+        OscPacket::Bundle(osc_bundle) => {
+            let delay = get_delay(osc_bundle.timetag, system_now).unwrap_or_default();
+            let due = due.max(now + delay);
+            osc_bundle
+                .content
+                .iter()
+                .filter_map(|content| get_stop_time(content, due, now, system_now))
+                .max()
+        }
+    }
+}
+
+/// The metric label for `address`, anything but the mtrack addresses is counted
+/// as "other" so senders cannot create a time series per address.
+#[cfg(feature = "server")]
+fn address_label(address: &str) -> &'static str {
+    match address {
+        ADDR_PLAY => ADDR_PLAY,
+        ADDR_STOP => ADDR_STOP,
+        ADDR_NEXT => ADDR_NEXT,
+        ADDR_PREV => ADDR_PREV,
+        ADDR_PLAYLIST => ADDR_PLAYLIST,
+        ADDR_SONG => ADDR_SONG,
+        ADDR_STATUS => ADDR_STATUS,
+        ADDR_CURRENT_SONG => ADDR_CURRENT_SONG,
+        ADDR_CURRENT_SETLIST => ADDR_CURRENT_SETLIST,
+        ADDR_ELAPSED => ADDR_ELAPSED,
+        _ => "other",
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    /// Receives the requests the actor sends on start and on every refresh.
+    #[cfg(feature = "server")]
+    async fn expect_sync(mtrack: &crate::backend::osctransport::MemoryTransport) {
+        use rosc::OscPacket;
+
+        use super::{ADDR_PLAYLIST, ADDR_SONG, ADDR_STATUS};
+        use crate::backend::osctransport::OscTransport;
+
+        for address in [ADDR_PLAYLIST, ADDR_SONG, ADDR_STATUS] {
+            let (packet, _from_address) = mtrack.recv().await.unwrap();
+            assert!(matches!(packet, OscPacket::Message(message) if message.addr == address));
+        }
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn unknown_addresses_share_a_metric_label() {
+        use super::{address_label, ADDR_ELAPSED};
+
+        assert_eq!(address_label(ADDR_ELAPSED), ADDR_ELAPSED);
+        assert_eq!(address_label("/random/1234"), "other");
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn actor_exchanges_packets_and_stops_deterministically() {
+        use std::{net::SocketAddr, time::Duration};
+
+        use rosc::{OscMessage, OscPacket, OscType};
+
+        use super::{OscConnection, ADDR_PLAY, ADDR_STATUS};
+        use crate::backend::{
+            oscfilter::SourceFilter,
+            osctransport::{MemoryTransport, OscTransport},
+        };
+
+        let addr: SocketAddr = "127.0.0.1:43236".parse().unwrap();
+        let mtrack_addr: SocketAddr = "127.0.0.1:43234".parse().unwrap();
+        let (transport, mtrack) = MemoryTransport::pair(addr, mtrack_addr);
+        let mut osc_connection = OscConnection::default();
+        osc_connection
+            .start(
+                Box::new(transport),
+                SourceFilter::new(mtrack_addr.ip(), &[]),
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(osc_connection.is_connected().await);
+        expect_sync(&mtrack).await;
+        assert!(!osc_connection.get_state().is_known);
+
+        osc_connection.play().await.unwrap();
+        let (packet, from_address) = mtrack.recv().await.unwrap();
+        assert_eq!(from_address, addr);
+        assert!(matches!(packet, OscPacket::Message(message) if message.addr == ADDR_PLAY));
+
+        let mut state = osc_connection.mtrack_state();
+        let status = OscPacket::Message(OscMessage {
+            addr: ADDR_STATUS.to_string(),
+            args: vec![OscType::String("Playing".to_string())],
+        });
+        mtrack.send(&status).await.unwrap();
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            state.wait_for(|state| state.is_playing),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        osc_connection.disconnect().await.unwrap();
+        assert!(!osc_connection.is_connected().await);
+        assert!(osc_connection.play().await.is_err());
+        assert!(osc_connection.get_state().is_playing);
+        assert!(osc_connection.get_state().is_known);
+
+        // Losing mtrack stops the actor, so the connection is reestablished on the next request.
+        let (transport, mtrack) = MemoryTransport::pair(addr, mtrack_addr);
+        let mut osc_connection = OscConnection::default();
+        osc_connection
+            .start(
+                Box::new(transport),
+                SourceFilter::new(mtrack_addr.ip(), &[]),
+                None,
+            )
+            .await
+            .unwrap();
+        drop(mtrack);
+        for _ in 0..100 {
+            if !osc_connection.is_connected().await {
+                break;
             }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!osc_connection.is_connected().await);
+        assert!(osc_connection.disconnect().await.is_err());
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn bundles_are_sent_and_applied_at_their_time_tag() {
+        use std::{
+            net::SocketAddr,
+            time::{Duration, SystemTime},
+        };
+
+        use rosc::{OscBundle, OscMessage, OscPacket, OscTime, OscType};
+
+        use super::{OscConnection, ADDR_STATUS};
+        use crate::backend::{
+            oscfilter::SourceFilter,
+            osctransport::{MemoryTransport, OscTransport},
+            server::ServerMessage,
+        };
+
+        let addr: SocketAddr = "127.0.0.1:43236".parse().unwrap();
+        let mtrack_addr: SocketAddr = "127.0.0.1:43234".parse().unwrap();
+        let (transport, mtrack) = MemoryTransport::pair(addr, mtrack_addr);
+        let mut osc_connection = OscConnection::default();
+        osc_connection
+            .start(
+                Box::new(transport),
+                SourceFilter::new(mtrack_addr.ip(), &[]),
+                None,
+            )
+            .await
+            .unwrap();
+        expect_sync(&mtrack).await;
+
+        let at = SystemTime::now() + Duration::from_secs(1);
+        osc_connection
+            .send_bundle(
+                vec![
+                    ServerMessage::Stop,
+                    ServerMessage::Next,
+                    ServerMessage::Play,
+                ],
+                at,
+            )
+            .await
+            .unwrap();
+        let (packet, _from_address) = mtrack.recv().await.unwrap();
+        let OscPacket::Bundle(bundle) = packet else {
+            panic!("Expected a bundle, got {packet:?}");
         };
-        {}
+        assert_eq!(bundle.timetag, OscTime::try_from(at).unwrap());
+        assert_eq!(bundle.content.len(), 3);
+
+        let status = OscPacket::Bundle(OscBundle {
+            timetag: OscTime::try_from(SystemTime::now() + Duration::from_millis(200)).unwrap(),
+            content: vec![OscPacket::Message(OscMessage {
+                addr: ADDR_STATUS.to_string(),
+                args: vec![OscType::String("Playing".to_string())],
+            })],
+        });
+        let mut state = osc_connection.mtrack_state();
+        mtrack.send(&status).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!osc_connection.get_state().is_playing);
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            state.wait_for(|state| state.is_playing),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        osc_connection.disconnect().await.unwrap();
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn state_is_requested_on_start_and_refreshed() {
+        use std::{net::SocketAddr, time::Duration};
+
+        use super::OscConnection;
+        use crate::backend::{oscfilter::SourceFilter, osctransport::MemoryTransport};
+
+        let addr: SocketAddr = "127.0.0.1:43236".parse().unwrap();
+        let mtrack_addr: SocketAddr = "127.0.0.1:43234".parse().unwrap();
+        let (transport, mtrack) = MemoryTransport::pair(addr, mtrack_addr);
+        let mut osc_connection = OscConnection::default();
+        osc_connection
+            .start(
+                Box::new(transport),
+                SourceFilter::new(mtrack_addr.ip(), &[]),
+                Some(Duration::from_millis(50)),
+            )
+            .await
+            .unwrap();
+        expect_sync(&mtrack).await;
+        tokio::time::timeout(Duration::from_secs(1), expect_sync(&mtrack))
+            .await
+            .unwrap();
+        osc_connection.disconnect().await.unwrap();
     }
 }
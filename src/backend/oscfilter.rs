@@ -0,0 +1,240 @@
+use std::{
+    collections::HashSet,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::{LazyLock, OnceLock, RwLock},
+};
+
+use dioxus::logger::tracing::{info, warn};
+use rosc::OscPacket;
+
+use super::{config::Config, osc::is_forwarded_address};
+
+/// Largest packet accepted over stream transports, UDP is limited to the datagram buffer.
+pub const MAX_PACKET_SIZE: usize = 65_536;
+/// Bundles nested deeper than this are rejected before being applied.
+pub const MAX_BUNDLE_DEPTH: usize = 4;
+
+/// Sources accepted besides the mtrack host, set once from the command line.
+static EXTRA_SOURCES: OnceLock<Vec<IpAddr>> = OnceLock::new();
+
+/// Clients the OSCQuery service told where to send commands, only their commands are
+/// accepted while the server runs.
+static OSCQUERY_PEERS: LazyLock<RwLock<HashSet<IpAddr>>> = LazyLock::new(RwLock::default);
+
+pub fn set_extra_sources(sources: Vec<IpAddr>) {
+    if EXTRA_SOURCES.set(sources).is_err() {
+        warn!("Allowed OSC sources were already set!");
+    }
+}
+
+/// Allows commands from an OSCQuery client, so they get forwarded to mtrack.
+pub fn allow_oscquery_peer(peer: IpAddr) {
+    let peer = get_allowed_address(peer);
+    match OSCQUERY_PEERS.write() {
+        Ok(mut peers) => {
+            if peers.insert(peer) {
+                info!("Accepting OSC packets from OSCQuery client {peer}");
+            }
+        }
+        Err(err) => warn!("Could not allow OSCQuery client {peer}! {err}"),
+    }
+}
+
+fn is_oscquery_peer(source: IpAddr) -> bool {
+    match OSCQUERY_PEERS.read() {
+        Ok(peers) => peers.contains(&source),
+        Err(_err) => false,
+    }
+}
+
+/// The address packets from `addr` arrive from, an unspecified address like `0.0.0.0`
+/// stands for this host.
+fn get_allowed_address(addr: IpAddr) -> IpAddr {
+    match addr.to_canonical() {
+        IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        addr => addr,
+    }
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum PacketRejection {
+    #[error("{0} is not an allowed source")]
+    Source(IpAddr),
+    #[error("{0} may only send commands")]
+    NotACommand(IpAddr),
+    #[error("Bundles are nested deeper than {MAX_BUNDLE_DEPTH} levels")]
+    BundleDepth,
+}
+
+impl PacketRejection {
+    /// Label of the rejected packets metric.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            PacketRejection::Source(_) => "source",
+            PacketRejection::NotACommand(_) => "not_a_command",
+            PacketRejection::BundleDepth => "bundle_depth",
+        }
+    }
+}
+
+/// Decides which incoming packets get applied to the mtrack state.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceFilter {
+    allowed: Vec<IpAddr>,
+}
+
+impl SourceFilter {
+    /// Allows the mtrack host and `extra`, OSCQuery clients may only send commands.
+    /// An unspecified address like `0.0.0.0` only allows this host.
+    pub fn new(mtrack_host: IpAddr, extra: &[IpAddr]) -> Self {
+        let mut allowed = vec![get_allowed_address(mtrack_host)];
+        allowed.extend(extra.iter().copied().map(get_allowed_address));
+        Self { allowed }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        let extra = EXTRA_SOURCES.get().map(Vec::as_slice).unwrap_or_default();
+        Self::new(config.mtrack_addr.ip(), extra)
+    }
+
+    /// Whether `source` may change the mtrack state.
+    pub fn allows(&self, source: IpAddr) -> bool {
+        self.allowed.contains(&source.to_canonical())
+    }
+
+    pub fn check(&self, osc_packet: &OscPacket, from: SocketAddr) -> Result<(), PacketRejection> {
+        if !self.allows(from.ip()) {
+            if !is_oscquery_peer(from.ip().to_canonical()) {
+                return Err(PacketRejection::Source(from.ip()));
+            }
+            if !is_only_commands(osc_packet) {
+                return Err(PacketRejection::NotACommand(from.ip()));
+            }
+        }
+        if exceeds_bundle_depth(osc_packet, MAX_BUNDLE_DEPTH) {
+            return Err(PacketRejection::BundleDepth);
+        }
+        Ok(())
+    }
+}
+
+/// Whether all messages in `osc_packet` are commands to forward to mtrack.
+fn is_only_commands(osc_packet: &OscPacket) -> bool {
+    match osc_packet {
+        OscPacket::Message(osc_message) => is_forwarded_address(&osc_message.addr),
+        OscPacket::Bundle(osc_bundle) => osc_bundle.content.iter().all(is_only_commands),
+    }
+}
+
+/// Whether bundles in `osc_packet` are nested more than `max_depth` levels,
+/// only descending `max_depth` levels to find out.
+pub fn exceeds_bundle_depth(osc_packet: &OscPacket, max_depth: usize) -> bool {
+    match osc_packet {
+        OscPacket::Message(_) => false,
+        OscPacket::Bundle(_) if max_depth == 0 => true,
+        OscPacket::Bundle(osc_bundle) => osc_bundle
+            .content
+            .iter()
+            .any(|osc_packet| exceeds_bundle_depth(osc_packet, max_depth - 1)),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::{IpAddr, SocketAddr};
+
+    use rosc::{OscBundle, OscMessage, OscPacket, OscTime};
+
+    use super::{
+        allow_oscquery_peer, exceeds_bundle_depth, PacketRejection, SourceFilter, MAX_BUNDLE_DEPTH,
+    };
+
+    fn nested(depth: usize) -> OscPacket {
+        let mut osc_packet = OscPacket::Message(OscMessage {
+            addr: "/mtrack/playlist/current_song".to_string(),
+            args: vec![],
+        });
+        for _ in 0..depth {
+            osc_packet = OscPacket::Bundle(OscBundle {
+                timetag: OscTime::from((0, 1)),
+                content: vec![osc_packet],
+            });
+        }
+        osc_packet
+    }
+
+    #[test]
+    fn only_allowed_sources_are_accepted() {
+        let mtrack_host: IpAddr = "192.168.1.20".parse().unwrap();
+        let console: IpAddr = "192.168.1.30".parse().unwrap();
+        let filter = SourceFilter::new(mtrack_host, &[console]);
+        assert!(filter.allows(mtrack_host));
+        assert!(filter.allows(console));
+        assert!(filter.allows("::ffff:192.168.1.20".parse().unwrap()));
+        assert!(!filter.allows("192.168.1.66".parse().unwrap()));
+
+        let spoofer: SocketAddr = "192.168.1.66:43234".parse().unwrap();
+        assert_eq!(
+            filter.check(&nested(0), spoofer),
+            Err(PacketRejection::Source(spoofer.ip()))
+        );
+        let local = SourceFilter::new("0.0.0.0".parse().unwrap(), &[]);
+        assert_eq!(
+            local.check(&nested(0), spoofer),
+            Err(PacketRejection::Source(spoofer.ip()))
+        );
+        assert!(local.allows("127.0.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn oscquery_clients_may_only_send_commands() {
+        use crate::backend::osc::ADDR_PLAY;
+
+        let filter = SourceFilter::new("192.168.1.20".parse().unwrap(), &[]);
+        let client: SocketAddr = "203.0.113.7:43234".parse().unwrap();
+        let play = OscPacket::Bundle(OscBundle {
+            timetag: OscTime::from((0, 1)),
+            content: vec![OscPacket::Message(OscMessage {
+                addr: ADDR_PLAY.to_string(),
+                args: vec![],
+            })],
+        });
+        assert_eq!(
+            filter.check(&play, client),
+            Err(PacketRejection::Source(client.ip()))
+        );
+
+        allow_oscquery_peer(client.ip());
+        assert_eq!(filter.check(&play, client), Ok(()));
+        // Spoofing the current song stays impossible for OSCQuery clients.
+        assert!(!filter.allows(client.ip()));
+        assert_eq!(
+            filter.check(&nested(0), client),
+            Err(PacketRejection::NotACommand(client.ip()))
+        );
+        let other: SocketAddr = "203.0.113.8:43234".parse().unwrap();
+        assert_eq!(
+            filter.check(&play, other),
+            Err(PacketRejection::Source(other.ip()))
+        );
+    }
+
+    #[test]
+    fn deeply_nested_bundles_are_rejected() {
+        assert!(!exceeds_bundle_depth(
+            &nested(MAX_BUNDLE_DEPTH),
+            MAX_BUNDLE_DEPTH
+        ));
+        assert!(exceeds_bundle_depth(
+            &nested(MAX_BUNDLE_DEPTH + 1),
+            MAX_BUNDLE_DEPTH
+        ));
+        let mtrack: SocketAddr = "127.0.0.1:43234".parse().unwrap();
+        let filter = SourceFilter::new(mtrack.ip(), &[]);
+        assert_eq!(
+            filter.check(&nested(MAX_BUNDLE_DEPTH + 1), mtrack),
+            Err(PacketRejection::BundleDepth)
+        );
+    }
+}
@@ -0,0 +1,457 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    net::{Ipv4Addr, SocketAddr},
+    sync::Arc,
+};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo, State,
+    },
+    http::{StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use dioxus::logger::tracing::{debug, info, warn};
+use rosc::{encoder, OscMessage, OscPacket, OscType};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error;
+use tokio::{net::TcpListener, select, sync::RwLock};
+
+use super::{
+    config::{Config, OscProtocol},
+    osc::{
+        MtrackState, ADDR_CURRENT_SETLIST, ADDR_CURRENT_SONG, ADDR_ELAPSED, ADDR_NEXT, ADDR_PLAY,
+        ADDR_PLAYLIST, ADDR_PREV, ADDR_SONG, ADDR_STATUS, ADDR_STOP,
+    },
+    oscfilter::allow_oscquery_peer,
+    service::Shutdown,
+    supervisor::Snapshots,
+};
+
+pub const ACCESS_NONE: u8 = 0;
+pub const ACCESS_READ: u8 = 1;
+pub const ACCESS_WRITE: u8 = 2;
+
+const COMMAND_ADDRESSES: [(&str, &str); 6] = [
+    (ADDR_PLAY, "Start playback of the current song"),
+    (ADDR_STOP, "Stop playback"),
+    (ADDR_NEXT, "Skip to the next song"),
+    (ADDR_PREV, "Skip to the previous song"),
+    (ADDR_PLAYLIST, "Request the current playlist"),
+    (ADDR_SONG, "Request the current song"),
+];
+
+const VALUE_ADDRESSES: [(&str, &str); 4] = [
+    (ADDR_STATUS, "Playback status, either Playing or Stopped"),
+    (ADDR_CURRENT_SONG, "Name of the current song"),
+    (ADDR_ELAPSED, "Time elapsed in the current song"),
+    (
+        ADDR_CURRENT_SETLIST,
+        "Songs of the current playlist, separated by newlines",
+    ),
+];
+
+#[derive(Debug, Error)]
+pub enum OscQueryError {
+    #[error("Could not bind OSCQuery listener! {0}")]
+    Bind(String),
+    #[error("OSCQuery server failed! {0}")]
+    Serve(String),
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct OscQueryNode {
+    #[serde(rename = "FULL_PATH")]
+    pub full_path: String,
+    #[serde(rename = "DESCRIPTION", skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "ACCESS")]
+    pub access: u8,
+    #[serde(rename = "TYPE", skip_serializing_if = "Option::is_none")]
+    pub osc_type: Option<String>,
+    #[serde(rename = "VALUE", skip_serializing_if = "Vec::is_empty")]
+    pub value: Vec<Value>,
+    #[serde(rename = "CONTENTS", skip_serializing_if = "BTreeMap::is_empty")]
+    pub contents: BTreeMap<String, OscQueryNode>,
+}
+
+impl OscQueryNode {
+    fn container(full_path: &str) -> Self {
+        Self {
+            full_path: full_path.to_string(),
+            description: None,
+            access: ACCESS_NONE,
+            osc_type: None,
+            value: vec![],
+            contents: BTreeMap::new(),
+        }
+    }
+
+    fn command(full_path: &str, description: &str) -> Self {
+        Self {
+            description: Some(description.to_string()),
+            access: ACCESS_WRITE,
+            ..Self::container(full_path)
+        }
+    }
+
+    fn state_value(full_path: &str, description: &str, value: Option<String>) -> Self {
+        Self {
+            description: Some(description.to_string()),
+            access: ACCESS_READ,
+            osc_type: Some("s".to_string()),
+            value: value.into_iter().map(Value::String).collect(),
+            ..Self::container(full_path)
+        }
+    }
+
+    /// Inserts a node at its full path, creating containers for missing parents.
+    fn insert(&mut self, node: OscQueryNode) {
+        let segments: Vec<&str> = node
+            .full_path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        let mut current = self;
+        let mut path = String::new();
+        for segment in segments {
+            path = format!("{path}/{segment}");
+            current = current
+                .contents
+                .entry(segment.to_string())
+                .or_insert_with(|| OscQueryNode::container(&path));
+        }
+        current.description = node.description;
+        current.access = node.access;
+        current.osc_type = node.osc_type;
+        current.value = node.value;
+    }
+
+    pub fn find(&self, path: &str) -> Option<&OscQueryNode> {
+        path.split('/')
+            .filter(|segment| !segment.is_empty())
+            .try_fold(self, |node, segment| node.contents.get(segment))
+    }
+}
+
+/// Current value of a state address as reported to OSCQuery clients.
+fn get_state_value(address: &str, mtrack_state: &MtrackState) -> Option<String> {
+    match address {
+        ADDR_STATUS => Some(
+            match mtrack_state.is_playing {
+                true => "Playing",
+                false => "Stopped",
+            }
+            .to_string(),
+        ),
+        ADDR_CURRENT_SONG => Some(mtrack_state.song.clone()),
+        ADDR_ELAPSED => Some(mtrack_state.time_elapsed.clone()),
+        ADDR_CURRENT_SETLIST => Some(mtrack_state.setlist.join("\n")),
+        _ => None,
+    }
+}
+
+pub fn get_namespace(mtrack_state: Option<&MtrackState>) -> OscQueryNode {
+    let mut root = OscQueryNode::container("/");
+    root.description = Some("mtrack-remote".to_string());
+    for (address, description) in COMMAND_ADDRESSES {
+        root.insert(OscQueryNode::command(address, description));
+    }
+    for (address, description) in VALUE_ADDRESSES {
+        let value = mtrack_state.and_then(|state| get_state_value(address, state));
+        root.insert(OscQueryNode::state_value(address, description, value));
+    }
+    root
+}
+
+/// Over UDP, clients send to the listen port and commands are forwarded to mtrack.
+/// Over TCP nothing listens there, as mtrack-remote connects to mtrack, so clients are
+/// pointed at mtrack's TCP port, on this host unless mtrack runs elsewhere.
+pub fn get_host_info(config: &Config) -> Value {
+    let mut host_info = json!({
+        "NAME": "mtrack-remote",
+        "EXTENSIONS": {
+            "ACCESS": true,
+            "VALUE": true,
+            "DESCRIPTION": true,
+            "TYPE": true,
+            "CONTENTS": true,
+            "LISTEN": true,
+            "PATH_CHANGED": false,
+        },
+    });
+    match config.osc_protocol {
+        OscProtocol::Udp => {
+            host_info["OSC_PORT"] = json!(config.listen_port);
+            host_info["OSC_TRANSPORT"] = json!("UDP");
+        }
+        OscProtocol::Tcp => {
+            let mtrack_ip = config.mtrack_addr.ip();
+            if !mtrack_ip.is_loopback() && !mtrack_ip.is_unspecified() {
+                host_info["OSC_IP"] = json!(mtrack_ip.to_string());
+            }
+            host_info["OSC_PORT"] = json!(config.mtrack_addr.port());
+            host_info["OSC_TRANSPORT"] = json!("TCP");
+        }
+    }
+    host_info
+}
+
+#[derive(Clone)]
+struct OscQueryService {
+    snapshots: Arc<Snapshots>,
+    config: Arc<RwLock<Config>>,
+    shutdown: Shutdown,
+}
+
+impl OscQueryService {
+    /// The state the supervisor published last, without locking the state machine.
+    fn get_mtrack_state(&self) -> Option<MtrackState> {
+        self.snapshots.load().mtrack_state.clone()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ListenCommand {
+    #[serde(rename = "COMMAND")]
+    command: String,
+    #[serde(rename = "DATA")]
+    data: String,
+}
+
+/// Serves the OSCQuery namespace over HTTP and value updates over WebSocket until shutdown.
+pub async fn serve(
+    snapshots: Arc<Snapshots>,
+    config: Arc<RwLock<Config>>,
+    shutdown: Shutdown,
+) -> Result<(), OscQueryError> {
+    let port = config.read().await.oscquery_port;
+    let addr = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), port);
+    let listener = match TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(err) => return Err(OscQueryError::Bind(err.to_string())),
+    };
+    info!("Serving OSCQuery on {addr}");
+
+    let service = OscQueryService {
+        snapshots,
+        config,
+        shutdown: shutdown.clone(),
+    };
+    let router = Router::new().fallback(handle_request).with_state(service);
+    let serving = axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move {
+        shutdown.wait().await;
+    });
+    match serving.await {
+        Ok(_) => Ok(()),
+        Err(err) => Err(OscQueryError::Serve(err.to_string())),
+    }
+}
+
+async fn handle_request(
+    State(service): State<OscQueryService>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    websocket: Option<WebSocketUpgrade>,
+    uri: Uri,
+) -> Response {
+    if let Some(websocket) = websocket {
+        allow_oscquery_peer(peer.ip());
+        return websocket.on_upgrade(move |socket| handle_websocket(socket, service));
+    }
+
+    if uri.query() == Some("HOST_INFO") {
+        // The client learns the listen port from the host info and sends commands there.
+        allow_oscquery_peer(peer.ip());
+        let config = *service.config.read().await;
+        return Json(get_host_info(&config)).into_response();
+    }
+
+    let mtrack_state = service.get_mtrack_state();
+    let namespace = get_namespace(mtrack_state.as_ref());
+    let Some(node) = namespace.find(uri.path()) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    match uri.query() {
+        None => Json(node).into_response(),
+        Some(attribute) => match serde_json::to_value(node) {
+            Ok(Value::Object(mut attributes)) => match attributes.remove(attribute) {
+                Some(value) => Json(json!({ attribute: value })).into_response(),
+                None => StatusCode::NO_CONTENT.into_response(),
+            },
+            _ => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        },
+    }
+}
+
+fn encode_value_message(address: &str, value: String) -> Option<Vec<u8>> {
+    let packet = OscPacket::Message(OscMessage {
+        addr: address.to_string(),
+        args: vec![OscType::String(value)],
+    });
+    match encoder::encode(&packet) {
+        Ok(buf) => Some(buf),
+        Err(err) => {
+            warn!("Could not encode OSCQuery value! {err}");
+            None
+        }
+    }
+}
+
+/// Sends the values of the `listening` addresses that changed since they were last sent,
+/// returns false once the client went away.
+async fn send_changed_values(
+    socket: &mut WebSocket,
+    listening: &BTreeSet<String>,
+    sent_values: &mut BTreeMap<String, String>,
+    mtrack_state: Option<&MtrackState>,
+) -> bool {
+    let Some(mtrack_state) = mtrack_state else {
+        return true;
+    };
+    for address in listening.iter() {
+        let Some(value) = get_state_value(address, mtrack_state) else {
+            continue;
+        };
+        if sent_values.get(address) == Some(&value) {
+            continue;
+        }
+        sent_values.insert(address.clone(), value.clone());
+        let Some(buf) = encode_value_message(address, value) else {
+            continue;
+        };
+        if socket.send(Message::Binary(buf)).await.is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+/// Sends values as the supervisor publishes them, so clients never lock the state machine.
+async fn handle_websocket(mut socket: WebSocket, service: OscQueryService) {
+    debug!("OSCQuery client connected");
+    let mut listening: BTreeSet<String> = BTreeSet::new();
+    let mut sent_values: BTreeMap<String, String> = BTreeMap::new();
+    let mut version = service.snapshots.subscribe();
+
+    loop {
+        select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str::<ListenCommand>(&text) {
+                    Ok(command) => match command.command.as_str() {
+                        "LISTEN" => {
+                            listening.insert(command.data);
+                            let mtrack_state = service.get_mtrack_state();
+                            if !send_changed_values(&mut socket, &listening, &mut sent_values, mtrack_state.as_ref()).await {
+                                debug!("OSCQuery client went away");
+                                return;
+                            }
+                        }
+                        "IGNORE" => {
+                            listening.remove(&command.data);
+                            sent_values.remove(&command.data);
+                        }
+                        unknown => debug!("Ignoring OSCQuery command {unknown}"),
+                    },
+                    Err(err) => warn!("Could not parse OSCQuery command! {err}"),
+                },
+                Some(Ok(Message::Close(_))) | None => break,
+                Some(Ok(_)) => {}
+                Some(Err(err)) => {
+                    warn!("OSCQuery websocket error! {err}");
+                    break;
+                }
+            },
+            changed = version.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                let mtrack_state = service.get_mtrack_state();
+                if !send_changed_values(&mut socket, &listening, &mut sent_values, mtrack_state.as_ref()).await {
+                    debug!("OSCQuery client went away");
+                    return;
+                }
+            },
+            _ = service.shutdown.wait() => {
+                // Upgraded connections are not closed by the graceful shutdown.
+                if let Err(err) = socket.send(Message::Close(None)).await {
+                    debug!("Could not close OSCQuery websocket! {err}");
+                }
+                break;
+            },
+        }
+    }
+    debug!("OSCQuery client disconnected");
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{get_host_info, get_namespace, ACCESS_READ, ACCESS_WRITE};
+    use crate::backend::{
+        config::{Config, OscProtocol},
+        osc::{MtrackState, ADDR_ELAPSED, ADDR_PLAY, ADDR_PLAYLIST, ADDR_STATUS},
+    };
+
+    #[test]
+    fn namespace_contains_commands_and_values() {
+        let namespace = get_namespace(None);
+        let play = namespace.find(ADDR_PLAY).expect("play is described");
+        assert_eq!(play.access, ACCESS_WRITE);
+        let elapsed = namespace.find(ADDR_ELAPSED).expect("elapsed is described");
+        assert_eq!(elapsed.access, ACCESS_READ);
+        assert_eq!(elapsed.full_path, ADDR_ELAPSED);
+        assert!(elapsed.value.is_empty());
+    }
+
+    #[test]
+    fn playlist_is_command_and_container() {
+        let namespace = get_namespace(None);
+        let playlist = namespace
+            .find(ADDR_PLAYLIST)
+            .expect("playlist is described");
+        assert_eq!(playlist.access, ACCESS_WRITE);
+        assert!(playlist.contents.contains_key("current_song"));
+        assert!(playlist.contents.contains_key("current"));
+    }
+
+    #[test]
+    fn namespace_reports_state_values() {
+        let mtrack_state = MtrackState {
+            is_playing: true,
+            ..MtrackState::default()
+        };
+        let namespace = get_namespace(Some(&mtrack_state));
+        let status = namespace.find(ADDR_STATUS).expect("status is described");
+        assert_eq!(status.value, vec![serde_json::json!("Playing")]);
+        assert!(namespace.find("/mtrack/unknown").is_none());
+    }
+
+    #[test]
+    fn host_info_advertises_listen_port() {
+        let config = Config::default();
+        let host_info = get_host_info(&config);
+        assert_eq!(host_info["OSC_PORT"], config.listen_port);
+        assert_eq!(host_info["OSC_TRANSPORT"], "UDP");
+        assert!(host_info.get("OSC_IP").is_none());
+    }
+
+    #[test]
+    fn host_info_advertises_mtrack_over_tcp() {
+        let config = Config {
+            osc_protocol: OscProtocol::Tcp,
+            mtrack_addr: "192.168.1.20:43235".parse().unwrap(),
+            ..Config::default()
+        };
+        let host_info = get_host_info(&config);
+        assert_eq!(host_info["OSC_PORT"], 43235);
+        assert_eq!(host_info["OSC_TRANSPORT"], "TCP");
+        assert_eq!(host_info["OSC_IP"], "192.168.1.20");
+    }
+}
@@ -0,0 +1,223 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use rosc::{OscBundle, OscPacket, OscTime};
+
+/// Time tag of bundles to apply as soon as they are received.
+pub const IMMEDIATELY: OscTime = OscTime {
+    seconds: 0,
+    fractional: 1,
+};
+
+/// Seconds between the OSC epoch in 1900 and the Unix epoch.
+const UNIX_OFFSET_SECS: u32 = 2_208_988_800;
+
+/// Future bundles held at once, further ones are rejected and returned to be logged.
+pub const MAX_SCHEDULED_BUNDLES: usize = 64;
+
+/// How long to wait until `timetag`, `None` if it is due already.
+pub fn get_delay(timetag: OscTime, now: SystemTime) -> Option<Duration> {
+    // Tags before 1970, including `IMMEDIATELY`, cannot be converted and are due anyway.
+    if timetag.seconds < UNIX_OFFSET_SECS {
+        return None;
+    }
+    match SystemTime::from(timetag).duration_since(now) {
+        Ok(delay) if !delay.is_zero() => Some(delay),
+        _ => None,
+    }
+}
+
+/// Time tag for `delay` from `now`, falling back to `IMMEDIATELY` if it cannot be represented.
+pub fn get_timetag(delay: Duration, now: SystemTime) -> OscTime {
+    OscTime::try_from(now + delay).unwrap_or(IMMEDIATELY)
+}
+
+/// Holds bundles with a future time tag until they are due, as the OSC spec asks.
+#[derive(Debug, Default)]
+pub struct BundleScheduler {
+    /// Bundles by due time, bundles due at the same time keep their order.
+    scheduled: Vec<(Instant, OscBundle)>,
+}
+
+impl BundleScheduler {
+    /// The messages of `osc_packet` due now, bundles with a future time tag are held back.
+    /// Also returns the bundles rejected as too many are scheduled already.
+    pub fn take_due_messages(
+        &mut self,
+        osc_packet: OscPacket,
+        now: Instant,
+        system_now: SystemTime,
+    ) -> (Vec<OscPacket>, Vec<OscBundle>) {
+        let mut messages = Vec::new();
+        let mut rejected = Vec::new();
+        self.collect(osc_packet, now, system_now, &mut messages, &mut rejected);
+        (messages, rejected)
+    }
+
+    fn collect(
+        &mut self,
+        osc_packet: OscPacket,
+        now: Instant,
+        system_now: SystemTime,
+        messages: &mut Vec<OscPacket>,
+        rejected: &mut Vec<OscBundle>,
+    ) {
+        let osc_bundle = match osc_packet {
+            OscPacket::Message(_) => {
+                messages.push(osc_packet);
+                return;
+            }
+            OscPacket::Bundle(osc_bundle) => osc_bundle,
+        };
+        match get_delay(osc_bundle.timetag, system_now) {
+            Some(_delay) if self.scheduled.len() >= MAX_SCHEDULED_BUNDLES => {
+                rejected.push(osc_bundle);
+            }
+            Some(delay) => {
+                let due = now + delay;
+                let position = self.scheduled.partition_point(|(other, _)| *other <= due);
+                self.scheduled.insert(position, (due, osc_bundle));
+            }
+            None => {
+                for osc_packet in osc_bundle.content {
+                    self.collect(osc_packet, now, system_now, messages, rejected);
+                }
+            }
+        }
+    }
+
+    /// When the next bundle is due.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.scheduled.first().map(|(due, _)| *due)
+    }
+
+    /// Messages of all bundles due by `now`, nested bundles dated later stay scheduled.
+    /// Also returns the nested bundles rejected.
+    pub fn take_due(
+        &mut self,
+        now: Instant,
+        system_now: SystemTime,
+    ) -> (Vec<OscPacket>, Vec<OscBundle>) {
+        let due_count = self.scheduled.partition_point(|(due, _)| *due <= now);
+        let mut messages = Vec::new();
+        let mut rejected = Vec::new();
+        for (_due, osc_bundle) in self.scheduled.drain(..due_count).collect::<Vec<_>>() {
+            for osc_packet in osc_bundle.content {
+                self.collect(osc_packet, now, system_now, &mut messages, &mut rejected);
+            }
+        }
+        (messages, rejected)
+    }
+
+    pub fn len(&self) -> usize {
+        self.scheduled.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scheduled.is_empty()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::time::{Duration, Instant, SystemTime};
+
+    use rosc::{OscBundle, OscMessage, OscPacket};
+
+    use super::{get_delay, get_timetag, BundleScheduler, IMMEDIATELY, MAX_SCHEDULED_BUNDLES};
+
+    fn message(addr: &str) -> OscPacket {
+        OscPacket::Message(OscMessage {
+            addr: addr.to_string(),
+            args: vec![],
+        })
+    }
+
+    fn bundle(delay: Duration, system_now: SystemTime, content: Vec<OscPacket>) -> OscPacket {
+        OscPacket::Bundle(OscBundle {
+            timetag: get_timetag(delay, system_now),
+            content,
+        })
+    }
+
+    #[test]
+    fn time_tags_convert_to_delays() {
+        let system_now = SystemTime::now();
+        assert_eq!(get_delay(IMMEDIATELY, system_now), None);
+        let delay = get_delay(get_timetag(Duration::from_secs(2), system_now), system_now);
+        assert!(delay.is_some_and(|delay| delay.abs_diff(Duration::from_secs(2)).as_millis() < 1));
+        let past = get_timetag(Duration::ZERO, system_now - Duration::from_secs(1));
+        assert_eq!(get_delay(past, system_now), None);
+    }
+
+    #[test]
+    fn future_bundles_are_applied_when_due() {
+        let now = Instant::now();
+        let system_now = SystemTime::now();
+        let mut scheduler = BundleScheduler::default();
+
+        let immediate = OscPacket::Bundle(OscBundle {
+            timetag: IMMEDIATELY,
+            content: vec![
+                message("/mtrack/status"),
+                bundle(Duration::from_secs(1), system_now, vec![message("/later")]),
+            ],
+        });
+        let (messages, rejected) = scheduler.take_due_messages(immediate, now, system_now);
+        assert_eq!(messages, vec![message("/mtrack/status")]);
+        assert!(rejected.is_empty());
+        assert_eq!(scheduler.len(), 1);
+
+        let sooner = bundle(
+            Duration::from_millis(500),
+            system_now,
+            vec![message("/sooner")],
+        );
+        let (messages, _rejected) = scheduler.take_due_messages(sooner, now, system_now);
+        assert!(messages.is_empty());
+        let next_due = scheduler.next_due().unwrap();
+        assert!(next_due < now + Duration::from_secs(1));
+
+        assert_eq!(
+            scheduler.take_due(next_due, system_now),
+            (vec![message("/sooner")], vec![])
+        );
+        let later = now + Duration::from_millis(1001);
+        assert_eq!(
+            scheduler.take_due(later, system_now),
+            (vec![message("/later")], vec![])
+        );
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn bundles_beyond_the_limit_are_rejected() {
+        let now = Instant::now();
+        let system_now = SystemTime::now();
+        let mut scheduler = BundleScheduler::default();
+        for _ in 0..MAX_SCHEDULED_BUNDLES - 1 {
+            let future = bundle(Duration::from_secs(60), system_now, vec![]);
+            scheduler.take_due_messages(future, now, system_now);
+        }
+        let last = bundle(Duration::from_secs(60), system_now, vec![message("/last")]);
+        let (_messages, rejected) = scheduler.take_due_messages(last, now, system_now);
+        assert!(rejected.is_empty());
+        assert_eq!(scheduler.len(), MAX_SCHEDULED_BUNDLES);
+
+        let beyond = bundle(Duration::from_secs(1), system_now, vec![message("/beyond")]);
+        let (_messages, rejected) = scheduler.take_due_messages(beyond.clone(), now, system_now);
+        assert_eq!(
+            rejected
+                .into_iter()
+                .map(OscPacket::Bundle)
+                .collect::<Vec<_>>(),
+            vec![beyond]
+        );
+        assert_eq!(scheduler.len(), MAX_SCHEDULED_BUNDLES);
+
+        // Messages due now still get through while the schedule is full.
+        let (messages, rejected) =
+            scheduler.take_due_messages(message("/mtrack/status"), now, system_now);
+        assert_eq!(messages, vec![message("/mtrack/status")]);
+        assert!(rejected.is_empty());
+    }
+}
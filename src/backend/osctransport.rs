@@ -0,0 +1,440 @@
+use std::{
+    fmt::Debug,
+    io,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use dioxus::logger::tracing::debug;
+use rosc::{decoder::MTU, encoder, OscPacket};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpStream, UdpSocket,
+    },
+    sync::{mpsc, Mutex},
+};
+
+use super::{
+    config::{Config, OscProtocol},
+    osc::OscTransportError,
+    oscfilter::MAX_PACKET_SIZE,
+};
+
+/// Time mtrack gets to accept a TCP connection, the state machine is locked meanwhile.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+/// Moves OSC packets between mtrack-remote and mtrack.
+#[async_trait]
+pub trait OscTransport: Debug + Send + Sync {
+    /// Waits for the next packet and where it came from.
+    /// Has to be cancel safe, it is raced against outgoing messages.
+    async fn recv(&self) -> Result<(OscPacket, SocketAddr), OscTransportError>;
+
+    /// Sends a packet to mtrack.
+    async fn send(&self, packet: &OscPacket) -> Result<(), OscTransportError>;
+
+    /// Address packets are sent to.
+    fn peer_addr(&self) -> SocketAddr;
+}
+
+/// Opens the transport selected in the config.
+pub async fn connect(config: &Config) -> Result<Box<dyn OscTransport>, OscTransportError> {
+    match config.osc_protocol {
+        OscProtocol::Udp => {
+            let transport = UdpTransport::bind(config.listen_port, config.mtrack_addr).await?;
+            Ok(Box::new(transport))
+        }
+        OscProtocol::Tcp => {
+            let transport = TcpSlipTransport::connect(config.mtrack_addr).await?;
+            Ok(Box::new(transport))
+        }
+    }
+}
+
+fn encode_packet(packet: &OscPacket) -> Result<Vec<u8>, OscTransportError> {
+    match encoder::encode(packet) {
+        Ok(buf) => Ok(buf),
+        Err(err) => Err(OscTransportError::Send(err.to_string())),
+    }
+}
+
+fn decode_packet(buf: &[u8]) -> Result<OscPacket, OscTransportError> {
+    match rosc::decoder::decode_udp(buf) {
+        Ok((_remainder, packet)) => Ok(packet),
+        Err(err) => Err(OscTransportError::Decode(err)),
+    }
+}
+
+/// OSC over UDP, mtrack answers on the local listen port.
+#[derive(Debug)]
+pub struct UdpTransport {
+    socket: UdpSocket,
+    mtrack_addr: SocketAddr,
+}
+
+impl UdpTransport {
+    pub async fn bind(
+        listen_port: u16,
+        mtrack_addr: SocketAddr,
+    ) -> Result<Self, OscTransportError> {
+        // OSCQuery advertises the listen port, so clients on the network have to reach it.
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), listen_port);
+        match UdpSocket::bind(addr).await {
+            Ok(socket) => {
+                debug!("Bound UDP socket to {addr}");
+                Ok(Self {
+                    socket,
+                    mtrack_addr,
+                })
+            }
+            Err(err) => Err(OscTransportError::IoError(format!("{addr} {err}"))),
+        }
+    }
+}
+
+#[async_trait]
+impl OscTransport for UdpTransport {
+    async fn recv(&self) -> Result<(OscPacket, SocketAddr), OscTransportError> {
+        // One byte more than allowed, so a full buffer means the datagram was too large.
+        let mut buf = Vec::with_capacity(MTU + 1);
+        let (bytes_received, from_address) = match self.socket.recv_buf_from(&mut buf).await {
+            Ok(ok_result) => ok_result,
+            Err(err) => return Err(OscTransportError::Receive(err)),
+        };
+        if bytes_received > MTU {
+            return Err(OscTransportError::TooLarge(bytes_received));
+        }
+        Ok((decode_packet(&buf)?, from_address))
+    }
+
+    async fn send(&self, packet: &OscPacket) -> Result<(), OscTransportError> {
+        let buf = encode_packet(packet)?;
+        match self.socket.send_to(&buf, self.mtrack_addr).await {
+            Ok(_bytes_sent) => Ok(()),
+            Err(err) => Err(OscTransportError::Send(err.to_string())),
+        }
+    }
+
+    fn peer_addr(&self) -> SocketAddr {
+        self.mtrack_addr
+    }
+}
+
+/// Wraps a packet in a SLIP frame as OSC 1.1 does over stream transports.
+pub fn slip_encode(data: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(data.len() + 2);
+    frame.push(SLIP_END);
+    for byte in data {
+        match *byte {
+            SLIP_END => frame.extend([SLIP_ESC, SLIP_ESC_END]),
+            SLIP_ESC => frame.extend([SLIP_ESC, SLIP_ESC_ESC]),
+            byte => frame.push(byte),
+        }
+    }
+    frame.push(SLIP_END);
+    frame
+}
+
+/// Removes the first complete SLIP frame from `buf` and returns its unescaped content.
+/// Frames can be empty, e.g. between the double END bytes of back to back packets.
+pub fn take_slip_frame(buf: &mut Vec<u8>) -> Option<Vec<u8>> {
+    let end = buf.iter().position(|byte| *byte == SLIP_END)?;
+    let frame: Vec<u8> = buf.drain(..=end).take(end).collect();
+    let mut data = Vec::with_capacity(frame.len());
+    let mut is_escaped = false;
+    for byte in frame {
+        match (is_escaped, byte) {
+            (false, SLIP_ESC) => is_escaped = true,
+            (true, SLIP_ESC_END) => {
+                data.push(SLIP_END);
+                is_escaped = false;
+            }
+            (true, SLIP_ESC_ESC) => {
+                data.push(SLIP_ESC);
+                is_escaped = false;
+            }
+            (_, byte) => {
+                data.push(byte);
+                is_escaped = false;
+            }
+        }
+    }
+    Some(data)
+}
+
+/// Bytes read but not yet part of a complete frame, kept across cancelled reads.
+#[derive(Debug, Default)]
+pub struct SlipBuffer {
+    buf: Vec<u8>,
+    /// Set while dropping the rest of a frame that exceeded the size limit.
+    is_discarding: bool,
+}
+
+impl SlipBuffer {
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// The next complete frame, or the size of a frame too large to be accepted.
+    pub fn take_frame(&mut self) -> Option<Result<Vec<u8>, usize>> {
+        loop {
+            match take_slip_frame(&mut self.buf) {
+                Some(_rest) if self.is_discarding => self.is_discarding = false,
+                Some(frame) if frame.is_empty() => continue,
+                Some(frame) if frame.len() > MAX_PACKET_SIZE => return Some(Err(frame.len())),
+                Some(frame) => return Some(Ok(frame)),
+                None if self.buf.len() > MAX_PACKET_SIZE => {
+                    let size = self.buf.len();
+                    self.buf.clear();
+                    self.is_discarding = true;
+                    return Some(Err(size));
+                }
+                None => return None,
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SlipReader {
+    stream: OwnedReadHalf,
+    buffer: SlipBuffer,
+}
+
+/// OSC 1.1 over a TCP connection to mtrack, packets framed with SLIP.
+#[derive(Debug)]
+pub struct TcpSlipTransport {
+    reader: Mutex<SlipReader>,
+    writer: Mutex<OwnedWriteHalf>,
+    mtrack_addr: SocketAddr,
+}
+
+impl TcpSlipTransport {
+    pub async fn connect(mtrack_addr: SocketAddr) -> Result<Self, OscTransportError> {
+        let stream = match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(mtrack_addr))
+            .await
+        {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(err)) => return Err(OscTransportError::IoError(format!("{mtrack_addr} {err}"))),
+            Err(_elapsed) => {
+                return Err(OscTransportError::IoError(format!(
+                    "{mtrack_addr} did not accept the connection in time"
+                )))
+            }
+        };
+        if let Err(err) = stream.set_nodelay(true) {
+            debug!("Could not disable Nagle's algorithm! {err}");
+        }
+        debug!("Connected to {mtrack_addr} over TCP");
+        let (stream, writer) = stream.into_split();
+        Ok(Self {
+            reader: Mutex::new(SlipReader {
+                stream,
+                buffer: SlipBuffer::default(),
+            }),
+            writer: Mutex::new(writer),
+            mtrack_addr,
+        })
+    }
+}
+
+#[async_trait]
+impl OscTransport for TcpSlipTransport {
+    async fn recv(&self) -> Result<(OscPacket, SocketAddr), OscTransportError> {
+        let mut reader = self.reader.lock().await;
+        loop {
+            match reader.buffer.take_frame() {
+                Some(Ok(frame)) => return Ok((decode_packet(&frame)?, self.mtrack_addr)),
+                Some(Err(size)) => return Err(OscTransportError::TooLarge(size)),
+                None => {}
+            }
+            let mut chunk = [0u8; 4096];
+            let bytes_read = match reader.stream.read(&mut chunk).await {
+                Ok(0) => {
+                    return Err(OscTransportError::Receive(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "mtrack closed the connection",
+                    )))
+                }
+                Ok(bytes_read) => bytes_read,
+                Err(err) => return Err(OscTransportError::Receive(err)),
+            };
+            reader.buffer.push(&chunk[..bytes_read]);
+        }
+    }
+
+    async fn send(&self, packet: &OscPacket) -> Result<(), OscTransportError> {
+        let frame = slip_encode(&encode_packet(packet)?);
+        match self.writer.lock().await.write_all(&frame).await {
+            Ok(_) => Ok(()),
+            Err(err) => Err(OscTransportError::Send(err.to_string())),
+        }
+    }
+
+    fn peer_addr(&self) -> SocketAddr {
+        self.mtrack_addr
+    }
+}
+
+/// One end of an in-process transport, lets tests stand in for mtrack without sockets.
+#[derive(Debug)]
+pub struct MemoryTransport {
+    rx: Mutex<mpsc::Receiver<OscPacket>>,
+    tx: mpsc::Sender<OscPacket>,
+    peer_addr: SocketAddr,
+}
+
+impl MemoryTransport {
+    /// Two connected ends, packets sent on one are received on the other.
+    pub fn pair(addr: SocketAddr, peer_addr: SocketAddr) -> (Self, Self) {
+        let (tx, peer_rx) = mpsc::channel(64);
+        let (peer_tx, rx) = mpsc::channel(64);
+        (
+            Self {
+                rx: Mutex::new(rx),
+                tx,
+                peer_addr,
+            },
+            Self {
+                rx: Mutex::new(peer_rx),
+                tx: peer_tx,
+                peer_addr: addr,
+            },
+        )
+    }
+}
+
+#[async_trait]
+impl OscTransport for MemoryTransport {
+    async fn recv(&self) -> Result<(OscPacket, SocketAddr), OscTransportError> {
+        match self.rx.lock().await.recv().await {
+            Some(packet) => Ok((packet, self.peer_addr)),
+            None => Err(OscTransportError::Receive(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "peer is gone",
+            ))),
+        }
+    }
+
+    async fn send(&self, packet: &OscPacket) -> Result<(), OscTransportError> {
+        match self.tx.send(packet.clone()).await {
+            Ok(_) => Ok(()),
+            Err(err) => Err(OscTransportError::Send(err.to_string())),
+        }
+    }
+
+    fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::SocketAddr;
+
+    use rosc::{OscMessage, OscPacket, OscType};
+
+    use super::{
+        decode_packet, encode_packet, slip_encode, take_slip_frame, MemoryTransport, OscTransport,
+        SlipBuffer, UdpTransport, SLIP_END, SLIP_ESC,
+    };
+    use crate::backend::oscfilter::MAX_PACKET_SIZE;
+
+    #[test]
+    fn slip_frames_survive_escaping_and_partial_reads() {
+        let data = vec![1, SLIP_END, 2, SLIP_ESC, 3];
+        let frame = slip_encode(&data);
+        assert_eq!(frame.iter().filter(|byte| **byte == SLIP_END).count(), 2);
+
+        let mut buf = frame[..4].to_vec();
+        // The leading END closes an empty frame.
+        assert_eq!(take_slip_frame(&mut buf), Some(vec![]));
+        assert_eq!(take_slip_frame(&mut buf), None);
+        buf.extend_from_slice(&frame[4..]);
+        buf.extend_from_slice(&slip_encode(&[4]));
+        assert_eq!(take_slip_frame(&mut buf), Some(data));
+        assert_eq!(take_slip_frame(&mut buf), Some(vec![]));
+        assert_eq!(take_slip_frame(&mut buf), Some(vec![4]));
+        assert!(buf.is_empty());
+
+        let packet = OscPacket::Message(OscMessage {
+            addr: "/mtrack/status".to_string(),
+            args: vec![OscType::String("Playing".to_string())],
+        });
+        let mut buf = slip_encode(&encode_packet(&packet).unwrap());
+        take_slip_frame(&mut buf);
+        let frame = take_slip_frame(&mut buf).unwrap();
+        assert_eq!(decode_packet(&frame).unwrap(), packet);
+    }
+
+    #[test]
+    fn oversized_slip_frames_are_dropped() {
+        let mut buffer = SlipBuffer::default();
+        buffer.push(&[SLIP_END]);
+        buffer.push(&vec![1; MAX_PACKET_SIZE + 1]);
+        assert_eq!(buffer.take_frame(), Some(Err(MAX_PACKET_SIZE + 1)));
+        assert_eq!(buffer.take_frame(), None);
+        // The rest of the oversized frame is dropped, the next one gets through.
+        buffer.push(&[1, 1, SLIP_END]);
+        buffer.push(&slip_encode(&[2]));
+        assert_eq!(buffer.take_frame(), Some(Ok(vec![2])));
+        assert_eq!(buffer.take_frame(), None);
+    }
+
+    #[tokio::test]
+    async fn udp_datagrams_up_to_the_mtu_are_accepted() {
+        use rosc::decoder::MTU;
+        use tokio::net::UdpSocket;
+
+        use crate::backend::osc::OscTransportError;
+
+        let transport = UdpTransport::bind(0, "127.0.0.1:43234".parse().unwrap())
+            .await
+            .unwrap();
+        let port = transport.socket.local_addr().unwrap().port();
+        let sender = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let packet = |size: usize| {
+            // Address and type tags take 20 bytes, the terminator pads the string by 4.
+            OscPacket::Message(OscMessage {
+                addr: "/mtrack/status".to_string(),
+                args: vec![OscType::String("x".repeat(size - 24))],
+            })
+        };
+        let full = packet(MTU);
+        let buf = encode_packet(&full).unwrap();
+        assert_eq!(buf.len(), MTU);
+        sender.send_to(&buf, ("127.0.0.1", port)).await.unwrap();
+        assert_eq!(transport.recv().await.unwrap().0, full);
+
+        let buf = encode_packet(&packet(MTU + 4)).unwrap();
+        sender.send_to(&buf, ("127.0.0.1", port)).await.unwrap();
+        assert!(matches!(
+            transport.recv().await,
+            Err(OscTransportError::TooLarge(size)) if size > MTU
+        ));
+    }
+
+    #[tokio::test]
+    async fn memory_transport_delivers_to_the_other_end() {
+        let addr: SocketAddr = "127.0.0.1:43236".parse().unwrap();
+        let mtrack_addr: SocketAddr = "127.0.0.1:43234".parse().unwrap();
+        let (remote, mtrack) = MemoryTransport::pair(addr, mtrack_addr);
+        let packet = OscPacket::Message(OscMessage {
+            addr: "/mtrack/play".to_string(),
+            args: vec![],
+        });
+        remote.send(&packet).await.unwrap();
+        assert_eq!(mtrack.recv().await.unwrap(), (packet, addr));
+        assert_eq!(remote.peer_addr(), mtrack_addr);
+        drop(mtrack);
+        assert!(remote.recv().await.is_err());
+    }
+}
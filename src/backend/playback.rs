@@ -0,0 +1,162 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::sync::RwLock;
+
+use super::{command::COMMAND_ACK_TIMEOUT, osc::MtrackState, server::OscStateMachine};
+
+/// Interval of background tasks watching the mtrack state.
+pub const PLAYBACK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Current mtrack state and whether stop was requested just before.
+pub async fn get_playback_state(
+    state_machine_option: &Arc<RwLock<Option<OscStateMachine>>>,
+) -> Option<(MtrackState, bool)> {
+    let state_machine_option = state_machine_option.read().await;
+    let state_machine = state_machine_option.as_ref()?;
+    let mtrack_state = state_machine.get_mtrack_data().ok()?;
+    let stopped_by_user = state_machine.stopped_within(COMMAND_ACK_TIMEOUT);
+    Some((mtrack_state, stopped_by_user))
+}
+
+/// Detects songs ending on their own, as opposed to being stopped by a command.
+#[derive(Debug, Default)]
+pub struct SongEndDetector {
+    last_playing: Option<MtrackState>,
+}
+
+impl SongEndDetector {
+    /// Returns the last state seen while playing once the song ended on its own.
+    pub fn update(
+        &mut self,
+        state: Option<&MtrackState>,
+        stopped_by_user: bool,
+    ) -> Option<MtrackState> {
+        let state = match state {
+            Some(state) => state,
+            None => {
+                self.last_playing = None;
+                return None;
+            }
+        };
+        if state.is_playing {
+            self.last_playing = Some(state.clone());
+            return None;
+        }
+        let last_playing = self.last_playing.take()?;
+        match stopped_by_user {
+            true => None,
+            false => Some(last_playing),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::SongEndDetector;
+    use crate::backend::osc::MtrackState;
+
+    fn state(is_playing: bool, time_elapsed: &str) -> MtrackState {
+        MtrackState {
+            is_playing,
+            time_elapsed: time_elapsed.to_string(),
+            song: "Opener".to_string(),
+            setlist: vec![],
+            ..MtrackState::default()
+        }
+    }
+
+    #[test]
+    fn only_natural_song_ends_are_detected() {
+        let mut detector = SongEndDetector::default();
+        assert_eq!(detector.update(Some(&state(false, "")), false), None);
+        detector.update(Some(&state(true, "0:01")), false);
+        detector.update(Some(&state(true, "3:21")), false);
+        let ended = detector.update(Some(&state(false, "")), false);
+        assert_eq!(
+            ended.map(|state| state.time_elapsed),
+            Some("3:21".to_string())
+        );
+        assert_eq!(detector.update(Some(&state(false, "")), false), None);
+
+        detector.update(Some(&state(true, "1:00")), false);
+        assert_eq!(detector.update(Some(&state(false, "")), true), None);
+    }
+
+    #[tokio::test]
+    async fn forwarded_stops_do_not_end_songs() {
+        use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+        use rosc::{OscMessage, OscPacket, OscType};
+        use tokio::sync::RwLock;
+
+        use super::get_playback_state;
+        use crate::backend::{
+            osc::{OscConnection, ADDR_STATUS, ADDR_STOP},
+            oscfilter::SourceFilter,
+            osctransport::{MemoryTransport, OscTransport},
+            server::{OscStateMachine, State},
+        };
+
+        let addr: SocketAddr = "127.0.0.1:43236".parse().unwrap();
+        let mtrack_addr: SocketAddr = "127.0.0.1:43234".parse().unwrap();
+        let (transport, mtrack) = MemoryTransport::pair(addr, mtrack_addr);
+        let mut state_machine = OscStateMachine::new();
+        let mut osc_connection = OscConnection::new(
+            state_machine.traffic.clone(),
+            state_machine.last_stop.clone(),
+        );
+        osc_connection
+            .start(
+                Box::new(transport),
+                SourceFilter::new(mtrack_addr.ip(), &[]),
+                None,
+            )
+            .await
+            .unwrap();
+        let mut mtrack_state = osc_connection.mtrack_state();
+        state_machine.state = State::Connected(osc_connection);
+        let state_machine_option = Arc::new(RwLock::new(Some(state_machine)));
+        let status = |status: &str| {
+            OscPacket::Message(OscMessage {
+                addr: ADDR_STATUS.to_string(),
+                args: vec![OscType::String(status.to_string())],
+            })
+        };
+
+        let mut detector = SongEndDetector::default();
+        mtrack.send(&status("Playing")).await.unwrap();
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            mtrack_state.wait_for(|state| state.is_playing),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        let (playing, stopped_by_user) = get_playback_state(&state_machine_option).await.unwrap();
+        assert_eq!(detector.update(Some(&playing), stopped_by_user), None);
+
+        // An OSCQuery client's stop arrives on the listen port and is forwarded to mtrack.
+        let stop = OscPacket::Message(OscMessage {
+            addr: ADDR_STOP.to_string(),
+            args: vec![],
+        });
+        mtrack.send(&stop).await.unwrap();
+        loop {
+            let (packet, _from_address) = mtrack.recv().await.unwrap();
+            if packet == stop {
+                break;
+            }
+        }
+        mtrack.send(&status("Stopped")).await.unwrap();
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            mtrack_state.wait_for(|state| !state.is_playing),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        let (stopped, stopped_by_user) = get_playback_state(&state_machine_option).await.unwrap();
+        assert!(stopped_by_user);
+        assert_eq!(detector.update(Some(&stopped), stopped_by_user), None);
+    }
+}
@@ -0,0 +1,44 @@
+use axum::{
+    http::header::{CACHE_CONTROL, CONTENT_TYPE},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+
+const MANIFEST: &str = include_str!("../../assets/pwa/manifest.webmanifest");
+const SERVICE_WORKER: &str = include_str!("../../assets/pwa/sw.js");
+const ICON_192: &[u8] = include_bytes!("../../assets/pwa/icon_192x192.png");
+const ICON_512: &[u8] = include_bytes!("../../assets/pwa/icon_512x512.png");
+
+/// Routes of the web app manifest and service worker.
+/// They need stable paths at the root, so they are not bundled as hashed assets.
+pub fn router() -> Router {
+    Router::new()
+        .route("/manifest.webmanifest", get(serve_manifest))
+        .route("/sw.js", get(serve_service_worker))
+        .route("/icons/icon_192x192.png", get(serve_icon_192))
+        .route("/icons/icon_512x512.png", get(serve_icon_512))
+}
+
+async fn serve_manifest() -> impl IntoResponse {
+    ([(CONTENT_TYPE, "application/manifest+json")], MANIFEST)
+}
+
+/// Served uncached so browsers pick up a new service worker right away.
+async fn serve_service_worker() -> impl IntoResponse {
+    (
+        [
+            (CONTENT_TYPE, "application/javascript"),
+            (CACHE_CONTROL, "no-cache"),
+        ],
+        SERVICE_WORKER,
+    )
+}
+
+async fn serve_icon_192() -> impl IntoResponse {
+    ([(CONTENT_TYPE, "image/png")], ICON_192)
+}
+
+async fn serve_icon_512() -> impl IntoResponse {
+    ([(CONTENT_TYPE, "image/png")], ICON_512)
+}
@@ -0,0 +1,343 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "server")]
+use dioxus::logger::tracing::{debug, info, warn};
+
+#[cfg(feature = "server")]
+use rosc::{encoder, OscPacket};
+
+#[cfg(feature = "server")]
+use tokio::{
+    sync::{watch, RwLock},
+    task::JoinHandle,
+};
+
+#[cfg(feature = "server")]
+use super::{
+    config::get_config_dir,
+    delta::next_revision,
+    osc::{MtrackState, OscConnection},
+    traffic::{TrafficDirection, TrafficLog},
+};
+
+#[cfg(feature = "server")]
+const RECORDING_EXTENSION: &str = "jsonl";
+
+#[derive(Debug, thiserror::Error)]
+pub enum RecordingError {
+    #[error("Invalid recording name! {0}")]
+    InvalidName(String),
+    #[error("Could not get recordings directory! {0}")]
+    Directory(String),
+    #[error("Recording IO error! {0}")]
+    Io(String),
+    #[error("Could not encode recorded packet! {0}")]
+    Encode(String),
+    #[error("Could not decode recorded packet! {0}")]
+    Decode(String),
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RecordingInfo {
+    pub name: String,
+    pub size_bytes: u64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RecordingStatus {
+    pub recording: Option<String>,
+    pub replaying: Option<String>,
+    pub replay_finished: bool,
+}
+
+/// A single OSC packet of a recording, stored as one JSON line.
+#[cfg(feature = "server")]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RecordedPacket {
+    pub offset_ms: u64,
+    pub direction: TrafficDirection,
+    pub peer: String,
+    pub packet: Vec<u8>,
+}
+
+#[cfg(feature = "server")]
+fn get_recordings_dir() -> Result<PathBuf, RecordingError> {
+    match get_config_dir() {
+        Ok(dir) => Ok(dir.join("recordings")),
+        Err(err) => Err(RecordingError::Directory(err.to_string())),
+    }
+}
+
+#[cfg(feature = "server")]
+pub fn get_recording_path(name: &str) -> Result<PathBuf, RecordingError> {
+    let is_valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !is_valid {
+        return Err(RecordingError::InvalidName(name.to_string()));
+    }
+    Ok(get_recordings_dir()?.join(format!("{name}.{RECORDING_EXTENSION}")))
+}
+
+#[cfg(feature = "server")]
+pub fn list_recordings() -> Result<Vec<RecordingInfo>, RecordingError> {
+    let dir = get_recordings_dir()?;
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    let read_dir = match std::fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(err) => return Err(RecordingError::Io(err.to_string())),
+    };
+    let mut recordings: Vec<RecordingInfo> = read_dir
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == RECORDING_EXTENSION)
+        })
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().to_string();
+            let size_bytes = std::fs::metadata(&path).ok()?.len();
+            Some(RecordingInfo { name, size_bytes })
+        })
+        .collect();
+    recordings.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(recordings)
+}
+
+#[cfg(feature = "server")]
+pub fn read_recording(path: &Path) -> Result<Vec<RecordedPacket>, RecordingError> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) => return Err(RecordingError::Io(err.to_string())),
+    };
+    let mut packets = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => return Err(RecordingError::Io(err.to_string())),
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<RecordedPacket>(&line) {
+            Ok(packet) => packets.push(packet),
+            Err(err) => return Err(RecordingError::Decode(err.to_string())),
+        }
+    }
+    Ok(packets)
+}
+
+/// Writes all OSC packets passing through the traffic log to a recording file.
+#[cfg(feature = "server")]
+#[derive(Debug)]
+pub struct Recorder {
+    name: String,
+    writer: BufWriter<File>,
+    started: Instant,
+}
+
+#[cfg(feature = "server")]
+impl Recorder {
+    pub fn create(name: &str) -> Result<Self, RecordingError> {
+        let path = get_recording_path(name)?;
+        Self::create_at(name, &path)
+    }
+
+    pub fn create_at(name: &str, path: &Path) -> Result<Self, RecordingError> {
+        if let Some(dir) = path.parent() {
+            if let Err(err) = std::fs::create_dir_all(dir) {
+                return Err(RecordingError::Io(err.to_string()));
+            }
+        }
+        let file = match File::create(path) {
+            Ok(file) => file,
+            Err(err) => return Err(RecordingError::Io(err.to_string())),
+        };
+        info!("Recording OSC traffic to {path:?}");
+        Ok(Self {
+            name: name.to_string(),
+            writer: BufWriter::new(file),
+            started: Instant::now(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn record(
+        &mut self,
+        direction: TrafficDirection,
+        osc_packet: &OscPacket,
+        peer: SocketAddr,
+    ) -> Result<(), RecordingError> {
+        let packet = match encoder::encode(osc_packet) {
+            Ok(packet) => packet,
+            Err(err) => return Err(RecordingError::Encode(err.to_string())),
+        };
+        let recorded = RecordedPacket {
+            offset_ms: self.started.elapsed().as_millis() as u64,
+            direction,
+            peer: peer.to_string(),
+            packet,
+        };
+        let line = match serde_json::to_string(&recorded) {
+            Ok(line) => line,
+            Err(err) => return Err(RecordingError::Encode(err.to_string())),
+        };
+        match writeln!(self.writer, "{line}") {
+            Ok(_) => Ok(()),
+            Err(err) => Err(RecordingError::Io(err.to_string())),
+        }
+    }
+
+    pub fn finish(mut self) -> Result<(), RecordingError> {
+        debug!("Finishing recording {}", self.name);
+        match self.writer.flush() {
+            Ok(_) => Ok(()),
+            Err(err) => Err(RecordingError::Io(err.to_string())),
+        }
+    }
+}
+
+/// Feeds a recording back into the mtrack state, without a connection to mtrack.
+#[cfg(feature = "server")]
+#[derive(Debug)]
+pub struct Replay {
+    name: String,
+    mtrack: watch::Receiver<MtrackState>,
+    handle: JoinHandle<()>,
+}
+
+#[cfg(feature = "server")]
+impl Replay {
+    /// Starts replaying, `speed` of 1.0 keeps the original timing.
+    pub fn start(
+        name: &str,
+        packets: Vec<RecordedPacket>,
+        speed: f64,
+        traffic: Arc<RwLock<TrafficLog>>,
+    ) -> Self {
+        info!("Replaying recording {name} at {speed}x");
+        let (mtrack_tx, mtrack) = watch::channel(MtrackState::at_revision(next_revision()));
+        let speed = if speed > 0.0 { speed } else { 1.0 };
+        let handle = tokio::spawn(async move {
+            let started = Instant::now();
+            for recorded in packets {
+                let due = Duration::from_secs_f64(recorded.offset_ms as f64 / 1000.0 / speed);
+                tokio::time::sleep(due.saturating_sub(started.elapsed())).await;
+
+                let osc_packet = match rosc::decoder::decode_udp(&recorded.packet) {
+                    Ok((_remainder, osc_packet)) => osc_packet,
+                    Err(err) => {
+                        warn!("Skipping undecodable recorded packet! {err}");
+                        continue;
+                    }
+                };
+                let peer = recorded
+                    .peer
+                    .parse()
+                    .unwrap_or(SocketAddr::from(([0, 0, 0, 0], 0)));
+                traffic
+                    .write()
+                    .await
+                    .push_packet(recorded.direction, &osc_packet, peer);
+                if recorded.direction == TrafficDirection::Inbound {
+                    OscConnection::replay_osc_packet(&mtrack_tx, &osc_packet);
+                }
+            }
+            debug!("Replay finished");
+        });
+        Self {
+            name: name.to_string(),
+            mtrack,
+            handle,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    pub fn get_state(&self) -> MtrackState {
+        self.mtrack.borrow().clone()
+    }
+
+    pub fn mtrack_state(&self) -> watch::Receiver<MtrackState> {
+        self.mtrack.clone()
+    }
+
+    pub fn stop(self) {
+        debug!("Stopping replay {}", self.name);
+        self.handle.abort();
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    #[cfg(feature = "server")]
+    use super::{get_recording_path, read_recording, Recorder, Replay};
+    #[cfg(feature = "server")]
+    use crate::backend::traffic::TrafficDirection;
+    #[cfg(feature = "server")]
+    use rosc::{OscMessage, OscPacket, OscType};
+
+    #[cfg(feature = "server")]
+    fn song_packet(song: &str) -> OscPacket {
+        OscPacket::Message(OscMessage {
+            addr: "/mtrack/playlist/current_song".to_string(),
+            args: vec![OscType::String(song.to_string())],
+        })
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn recording_names_are_restricted() {
+        assert!(get_recording_path("rehearsal-2026_10_18").is_ok());
+        assert!(get_recording_path("../config").is_err());
+        assert!(get_recording_path("").is_err());
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn recording_roundtrip_and_replay() {
+        let path = std::env::temp_dir().join("mtrack-remote-recording-test.jsonl");
+        let peer = "127.0.0.1:43234".parse().unwrap();
+        let mut recorder = Recorder::create_at("test", &path).unwrap();
+        recorder
+            .record(TrafficDirection::Inbound, &song_packet("First"), peer)
+            .unwrap();
+        recorder
+            .record(TrafficDirection::Inbound, &song_packet("Second"), peer)
+            .unwrap();
+        recorder.finish().unwrap();
+
+        let packets = read_recording(&path).unwrap();
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0].direction, TrafficDirection::Inbound);
+
+        let replay = Replay::start("test", packets, 100.0, Default::default());
+        while !replay.is_finished() {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        assert_eq!(replay.get_state().song, "Second");
+        std::fs::remove_file(path).unwrap();
+    }
+}
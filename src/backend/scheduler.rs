@@ -0,0 +1,327 @@
+use chrono::{Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use std::{path::PathBuf, sync::Arc};
+
+#[cfg(feature = "server")]
+use dioxus::logger::tracing::{debug, info, warn};
+
+#[cfg(feature = "server")]
+use tokio::{select, sync::RwLock};
+
+#[cfg(feature = "server")]
+use super::{
+    command::{goto_song, send_command, CommandError, TransportCommand},
+    config::get_config_dir,
+    server::OscStateMachine,
+    service::Shutdown,
+    supervisor::{Snapshots, COMMAND_CONNECT_TIMEOUT},
+};
+
+/// Number of executed actions kept in the schedule history.
+pub const SCHEDULE_HISTORY_LENGTH: usize = 50;
+
+/// Actions due longer ago than this, e.g. while the server was down, are skipped.
+pub const MAX_LATENESS_SECS: i64 = 60;
+
+#[cfg(feature = "server")]
+const SCHEDULER_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+#[derive(Debug, thiserror::Error)]
+pub enum SchedulerError {
+    #[error("Could not get config directory! {0}")]
+    Directory(String),
+    #[error("Could not read schedule! {0}")]
+    Read(String),
+    #[error("Could not write schedule! {0}")]
+    Write(String),
+    #[error("Could not (de)serialize schedule! {0}")]
+    Serde(String),
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ScheduledAction {
+    GotoSong(String),
+    Play,
+    Stop,
+    Osc { address: String, args: Vec<String> },
+}
+
+impl ScheduledAction {
+    pub fn describe(&self) -> String {
+        match self {
+            ScheduledAction::GotoSong(song) => format!("go to {song}"),
+            ScheduledAction::Play => "play".to_string(),
+            ScheduledAction::Stop => "stop".to_string(),
+            ScheduledAction::Osc { address, args } => format!("send {address} {}", args.join(" ")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Recurrence {
+    Once,
+    Daily,
+    Weekly,
+}
+
+impl Recurrence {
+    fn interval(&self) -> Option<Duration> {
+        match self {
+            Recurrence::Once => None,
+            Recurrence::Daily => Some(Duration::days(1)),
+            Recurrence::Weekly => Some(Duration::weeks(1)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ScheduleEntry {
+    pub id: u64,
+    /// Local wall-clock time of the next execution.
+    pub next_run: NaiveDateTime,
+    pub recurrence: Recurrence,
+    pub action: ScheduledAction,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ExecutedAction {
+    pub entry_id: u64,
+    pub action: ScheduledAction,
+    pub scheduled_for: NaiveDateTime,
+    pub executed_at: NaiveDateTime,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Schedule {
+    next_id: u64,
+    pub entries: Vec<ScheduleEntry>,
+    /// Most recent first.
+    pub executed: Vec<ExecutedAction>,
+}
+
+impl Schedule {
+    pub fn add(
+        &mut self,
+        next_run: NaiveDateTime,
+        recurrence: Recurrence,
+        action: ScheduledAction,
+    ) -> u64 {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.entries.push(ScheduleEntry {
+            id,
+            next_run,
+            recurrence,
+            action,
+        });
+        self.entries.sort_by_key(|entry| entry.next_run);
+        id
+    }
+
+    pub fn remove(&mut self, id: u64) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|entry| entry.id != id);
+        self.entries.len() != len
+    }
+
+    /// Takes the entries due at `now`, recurring entries are rescheduled after `now`.
+    pub fn take_due(&mut self, now: NaiveDateTime) -> Vec<ScheduleEntry> {
+        let (due, mut pending): (Vec<ScheduleEntry>, Vec<ScheduleEntry>) = self
+            .entries
+            .drain(..)
+            .partition(|entry| entry.next_run <= now);
+        for entry in due.iter() {
+            if let Some(interval) = entry.recurrence.interval() {
+                let mut next_run = entry.next_run;
+                while next_run <= now {
+                    next_run += interval;
+                }
+                pending.push(ScheduleEntry {
+                    next_run,
+                    ..entry.clone()
+                });
+            }
+        }
+        pending.sort_by_key(|entry| entry.next_run);
+        self.entries = pending;
+        due
+    }
+
+    pub fn record(&mut self, executed: ExecutedAction) {
+        self.executed.insert(0, executed);
+        self.executed.truncate(SCHEDULE_HISTORY_LENGTH);
+    }
+}
+
+#[cfg(feature = "server")]
+fn get_schedule_file_path() -> Result<PathBuf, SchedulerError> {
+    match get_config_dir() {
+        Ok(dir) => Ok(dir.join("schedule.json")),
+        Err(err) => Err(SchedulerError::Directory(err.to_string())),
+    }
+}
+
+#[cfg(feature = "server")]
+impl Schedule {
+    pub fn read_schedule() -> Result<Self, SchedulerError> {
+        let path = get_schedule_file_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let serialized = match std::fs::read_to_string(path) {
+            Ok(serialized) => serialized,
+            Err(err) => return Err(SchedulerError::Read(err.to_string())),
+        };
+        match serde_json::from_str(&serialized) {
+            Ok(schedule) => Ok(schedule),
+            Err(err) => Err(SchedulerError::Serde(err.to_string())),
+        }
+    }
+
+    pub fn write_schedule(&self) -> Result<(), SchedulerError> {
+        let serialized = match serde_json::to_string(self) {
+            Ok(serialized) => serialized,
+            Err(err) => return Err(SchedulerError::Serde(err.to_string())),
+        };
+        let path = get_schedule_file_path()?;
+        if let Some(dir) = path.parent() {
+            if let Err(err) = std::fs::create_dir_all(dir) {
+                return Err(SchedulerError::Write(err.to_string()));
+            }
+        }
+        match std::fs::write(path, serialized) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(SchedulerError::Write(err.to_string())),
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+async fn execute(
+    state_machine_option: &Arc<RwLock<Option<OscStateMachine>>>,
+    action: &ScheduledAction,
+) -> Result<(), String> {
+    match action {
+        ScheduledAction::GotoSong(song) => match goto_song(state_machine_option, false, song).await
+        {
+            Ok(_) => Ok(()),
+            Err(err) => Err(err.to_string()),
+        },
+        ScheduledAction::Play => {
+            match send_command(state_machine_option, TransportCommand::Play).await {
+                Ok(_mtrack) => Ok(()),
+                Err(err) => Err(err.to_string()),
+            }
+        }
+        ScheduledAction::Stop => {
+            match send_command(state_machine_option, TransportCommand::Stop).await {
+                Ok(_mtrack) => Ok(()),
+                Err(err) => Err(err.to_string()),
+            }
+        }
+        ScheduledAction::Osc { address, args } => {
+            match state_machine_option.read().await.as_ref() {
+                Some(state_machine) => {
+                    match state_machine
+                        .send_custom(address.clone(), args.clone())
+                        .await
+                    {
+                        Ok(_) => Ok(()),
+                        Err(err) => Err(err.to_string()),
+                    }
+                }
+                None => Err("OSC state machine is None!".to_string()),
+            }
+        }
+    }
+}
+
+/// Executes due scheduled actions through the OSC state machine until `shutdown`,
+/// waiting for the supervisor to reconnect first if the connection was lost.
+#[cfg(feature = "server")]
+pub async fn run(
+    state_machine_option: Arc<RwLock<Option<OscStateMachine>>>,
+    snapshots: Arc<Snapshots>,
+    schedule: Arc<RwLock<Schedule>>,
+    shutdown: Shutdown,
+) {
+    loop {
+        select! {
+            _ = shutdown.wait() => {
+                debug!("Scheduler stopped");
+                return;
+            }
+            _ = tokio::time::sleep(SCHEDULER_POLL_INTERVAL) => {}
+        }
+        let now = chrono::Local::now().naive_local();
+        let due = schedule.write().await.take_due(now);
+        if due.is_empty() {
+            continue;
+        }
+        for entry in due {
+            let error = match now - entry.next_run > Duration::seconds(MAX_LATENESS_SECS) {
+                true => Some("missed".to_string()),
+                false => {
+                    info!("Running scheduled action: {}", entry.action.describe());
+                    match snapshots.wait_connected(COMMAND_CONNECT_TIMEOUT).await {
+                        true => execute(&state_machine_option, &entry.action).await.err(),
+                        false => Some(CommandError::NotConnected.to_string()),
+                    }
+                }
+            };
+            if let Some(error) = error.as_ref() {
+                warn!(
+                    "Scheduled action {} failed! {error}",
+                    entry.action.describe()
+                );
+            }
+            schedule.write().await.record(ExecutedAction {
+                entry_id: entry.id,
+                action: entry.action,
+                scheduled_for: entry.next_run,
+                executed_at: chrono::Local::now().naive_local(),
+                error,
+            });
+        }
+        if let Err(err) = schedule.read().await.write_schedule() {
+            warn!("Could not save schedule! {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{Recurrence, Schedule, ScheduledAction};
+    use chrono::{Duration, NaiveDate};
+
+    #[test]
+    fn due_entries_are_taken_and_recurring_ones_rescheduled() {
+        let at = NaiveDate::from_ymd_opt(2026, 10, 18)
+            .unwrap()
+            .and_hms_opt(20, 55, 0)
+            .unwrap();
+        let mut schedule = Schedule::default();
+        let once = schedule.add(at, Recurrence::Once, ScheduledAction::Play);
+        let daily = schedule.add(
+            at,
+            Recurrence::Daily,
+            ScheduledAction::GotoSong("Intro".to_string()),
+        );
+        schedule.add(
+            at + Duration::hours(1),
+            Recurrence::Once,
+            ScheduledAction::Stop,
+        );
+
+        assert!(schedule.take_due(at - Duration::seconds(1)).is_empty());
+        let due: Vec<u64> = schedule.take_due(at).iter().map(|entry| entry.id).collect();
+        assert_eq!(due, vec![once, daily]);
+        assert_eq!(schedule.entries.len(), 2);
+        assert_eq!(schedule.entries[1].next_run, at + Duration::days(1));
+        assert!(schedule.remove(daily));
+        assert!(!schedule.remove(once));
+    }
+}
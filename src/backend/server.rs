@@ -9,6 +9,27 @@ use super::{config::Config, osc::OscConnection};
 #[cfg(feature = "server")]
 use super::osc::MtrackState;
 
+#[cfg(feature = "server")]
+use super::metrics::METRICS;
+
+#[cfg(feature = "server")]
+use super::traffic::TrafficLog;
+
+#[cfg(feature = "server")]
+use super::recording::{get_recording_path, read_recording, Recorder, RecordingStatus, Replay};
+
+#[cfg(feature = "server")]
+use super::showlock::ShowLock;
+
+#[cfg(feature = "server")]
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+#[cfg(feature = "server")]
+use tokio::sync::{watch, RwLock};
+
 #[derive(Debug, Error)]
 pub enum OscStateMachineError {
     #[error("Could not connect to OSC endpoint! {0}")]
@@ -19,6 +40,10 @@ pub enum OscStateMachineError {
     NotConnected,
     #[error("OSC error! {0}")]
     Osc(String),
+    #[error("Replaying a recording, commands are disabled!")]
+    Replaying,
+    #[error("Recording error! {0}")]
+    Recording(String),
 }
 
 #[cfg(feature = "server")]
@@ -26,6 +51,7 @@ pub enum OscStateMachineError {
 pub enum State {
     Disconnected,
     Connected(OscConnection),
+    Replaying(Replay),
 }
 
 #[cfg(feature = "server")]
@@ -35,6 +61,11 @@ impl State {}
 #[derive(Debug)]
 pub struct OscStateMachine {
     pub state: State,
+    pub traffic: Arc<RwLock<TrafficLog>>,
+    pub show_lock: Arc<Mutex<ShowLock>>,
+    /// When the last stop sent to mtrack took effect, to tell stops from songs ending.
+    /// Set by the OSC actor, so forwarded, custom and bundled stops count as well.
+    pub last_stop: Arc<Mutex<Option<Instant>>>,
 }
 
 #[derive(Debug)]
@@ -45,6 +76,9 @@ impl Default for OscStateMachine {
     fn default() -> Self {
         Self {
             state: State::Disconnected,
+            traffic: Arc::default(),
+            show_lock: Arc::default(),
+            last_stop: Arc::default(),
         }
     }
 }
@@ -55,16 +89,28 @@ impl OscStateMachine {
         Self::default()
     }
 
+    /// A running replay counts as connected, so clients keep showing its state.
+    /// A connection whose transport was lost does not, so it gets reestablished.
     pub async fn is_connected(&self) -> bool {
         match &self.state {
             State::Disconnected => false,
-            State::Connected(_) => true,
+            State::Connected(osc_connection) => osc_connection.is_connected().await,
+            State::Replaying(_) => true,
         }
     }
 
     pub async fn disconnect(self) -> Self {
         match self.state {
             State::Disconnected => self,
+            State::Replaying(replay) => {
+                replay.stop();
+                Self {
+                    state: State::Disconnected,
+                    traffic: self.traffic,
+                    show_lock: self.show_lock,
+                    last_stop: self.last_stop,
+                }
+            }
             State::Connected(mut osc_connection) => {
                 match osc_connection.disconnect().await {
                     Ok(_) => debug!("Disconnected properly."),
@@ -72,6 +118,9 @@ impl OscStateMachine {
                 };
                 Self {
                     state: State::Disconnected,
+                    traffic: self.traffic,
+                    show_lock: self.show_lock,
+                    last_stop: self.last_stop,
                 }
             }
         }
@@ -79,9 +128,11 @@ impl OscStateMachine {
 
     pub async fn ensure_connection(mut self, config: Config) -> Self {
         if !self.is_connected().await {
-            let mut osc_connection = OscConnection::new();
+            let mut osc_connection =
+                OscConnection::new(self.traffic.clone(), self.last_stop.clone());
             match osc_connection.init_socket(config).await {
                 Ok(_) => {
+                    METRICS.record_connect();
                     self.state = State::Connected(osc_connection);
                 }
                 Err(error) => {
@@ -94,19 +145,89 @@ impl OscStateMachine {
         self
     }
 
+    /// Disconnects from mtrack and feeds the named recording into the mtrack state.
+    /// On error the unchanged state machine is handed back.
+    pub async fn start_replay(
+        self,
+        name: &str,
+        speed: f64,
+    ) -> Result<Self, (Self, OscStateMachineError)> {
+        let packets = match get_recording_path(name).and_then(|path| read_recording(&path)) {
+            Ok(packets) => packets,
+            Err(err) => return Err((self, OscStateMachineError::Recording(err.to_string()))),
+        };
+        let disconnected = self.disconnect().await;
+        let replay = Replay::start(name, packets, speed, disconnected.traffic.clone());
+        Ok(Self {
+            state: State::Replaying(replay),
+            traffic: disconnected.traffic,
+            show_lock: disconnected.show_lock,
+            last_stop: disconnected.last_stop,
+        })
+    }
+
+    pub async fn start_recording(&self, name: &str) -> Result<(), OscStateMachineError> {
+        let recorder = match Recorder::create(name) {
+            Ok(recorder) => recorder,
+            Err(err) => return Err(OscStateMachineError::Recording(err.to_string())),
+        };
+        match self.traffic.write().await.start_recording(recorder) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(OscStateMachineError::Recording(err.to_string())),
+        }
+    }
+
+    pub async fn stop_recording(&self) -> Result<Option<String>, OscStateMachineError> {
+        match self.traffic.write().await.stop_recording() {
+            Ok(name) => Ok(name),
+            Err(err) => Err(OscStateMachineError::Recording(err.to_string())),
+        }
+    }
+
+    pub async fn get_recording_status(&self) -> RecordingStatus {
+        let recording = self.traffic.read().await.recording_name();
+        match &self.state {
+            State::Replaying(replay) => RecordingStatus {
+                recording,
+                replaying: Some(replay.name().to_string()),
+                replay_finished: replay.is_finished(),
+            },
+            _ => RecordingStatus {
+                recording,
+                ..RecordingStatus::default()
+            },
+        }
+    }
+
     pub fn get_mtrack_data(&self) -> Result<MtrackState, OscStateMachineError> {
         match &self.state {
             State::Disconnected => Err(OscStateMachineError::NotConnected),
-            State::Connected(osc_connection) => match osc_connection.get_state() {
-                Ok(mtrack) => Ok(mtrack),
-                Err(err) => Err(OscStateMachineError::Osc(err.to_string())),
-            },
+            State::Connected(osc_connection) => Ok(osc_connection.get_state()),
+            State::Replaying(replay) => Ok(replay.get_state()),
+        }
+    }
+
+    /// Mtrack state commands act on or the replayed one, `None` while disconnected.
+    pub fn mtrack_state(&self) -> Option<watch::Receiver<MtrackState>> {
+        match &self.state {
+            State::Connected(osc_connection) => Some(osc_connection.mtrack_state()),
+            State::Replaying(replay) => Some(replay.mtrack_state()),
+            State::Disconnected => None,
+        }
+    }
+
+    /// Whether stop was requested within the last `window`.
+    pub fn stopped_within(&self, window: Duration) -> bool {
+        match self.last_stop.lock() {
+            Ok(last_stop) => last_stop.is_some_and(|stopped| stopped.elapsed() <= window),
+            Err(_err) => false,
         }
     }
 
     pub async fn play(&self) -> Result<(), OscStateMachineError> {
         match &self.state {
             State::Disconnected => Err(OscStateMachineError::NotConnected),
+            State::Replaying(_) => Err(OscStateMachineError::Replaying),
             State::Connected(osc_connection) => match osc_connection.play().await {
                 Ok(result) => {
                     debug!("Asked OSC routine to play song");
@@ -124,6 +245,7 @@ impl OscStateMachine {
         debug!("Stop..");
         match &self.state {
             State::Disconnected => Err(OscStateMachineError::NotConnected),
+            State::Replaying(_) => Err(OscStateMachineError::Replaying),
             State::Connected(osc_connection) => match osc_connection.stop().await {
                 Ok(result) => {
                     debug!("Asked OSC routine to stop song");
@@ -141,6 +263,7 @@ impl OscStateMachine {
         debug!("Next..");
         match &self.state {
             State::Disconnected => Err(OscStateMachineError::NotConnected),
+            State::Replaying(_) => Err(OscStateMachineError::Replaying),
             State::Connected(osc_connection) => match osc_connection.next().await {
                 Ok(result) => {
                     debug!("Asked OSC routine to skip to next song");
@@ -158,6 +281,7 @@ impl OscStateMachine {
         debug!("Prev..");
         match &self.state {
             State::Disconnected => Err(OscStateMachineError::NotConnected),
+            State::Replaying(_) => Err(OscStateMachineError::Replaying),
             State::Connected(osc_connection) => match osc_connection.prev().await {
                 Ok(result) => {
                     debug!("Asked OSC routine to skip to prev song");
@@ -170,17 +294,79 @@ impl OscStateMachine {
             },
         }
     }
+
+    /// Sends an arbitrary OSC message to mtrack.
+    pub async fn send_custom(
+        &self,
+        address: String,
+        args: Vec<String>,
+    ) -> Result<(), OscStateMachineError> {
+        match &self.state {
+            State::Disconnected => Err(OscStateMachineError::NotConnected),
+            State::Replaying(_) => Err(OscStateMachineError::Replaying),
+            State::Connected(osc_connection) => {
+                match osc_connection.send_custom(address, args).await {
+                    Ok(result) => Ok(result),
+                    Err(err) => Err(OscStateMachineError::Osc(err.to_string())),
+                }
+            }
+        }
+    }
+    /// Sends `messages` as one time-tagged bundle for mtrack to apply together at `at`.
+    pub async fn send_bundle(
+        &self,
+        messages: Vec<ServerMessage>,
+        at: SystemTime,
+    ) -> Result<(), OscStateMachineError> {
+        match &self.state {
+            State::Disconnected => Err(OscStateMachineError::NotConnected),
+            State::Replaying(_) => Err(OscStateMachineError::Replaying),
+            State::Connected(osc_connection) => {
+                match osc_connection.send_bundle(messages, at).await {
+                    Ok(result) => {
+                        debug!("Asked OSC routine to send bundle");
+                        Ok(result)
+                    }
+                    Err(err) => {
+                        error!("Could not request sending bundle! {err:?}");
+                        Err(OscStateMachineError::Osc(err.to_string()))
+                    }
+                }
+            }
+        }
+    }
 }
 
-#[derive(Debug)]
+/// Connects the shared state machine to mtrack unless it already is.
+#[cfg(feature = "server")]
+pub async fn ensure_connected(
+    state_machine_option: &Arc<RwLock<Option<OscStateMachine>>>,
+    config: Config,
+) {
+    let mut state_machine_option = state_machine_option.write().await;
+    let state_machine = state_machine_option.take().unwrap_or_default();
+    *state_machine_option = Some(state_machine.ensure_connection(config).await);
+}
+
+#[cfg(feature = "server")]
+#[derive(Debug, PartialEq)]
 pub enum ServerMessage {
     GetSetlist,
     GetSong,
-    Disconnect,
     Play,
     Stop,
     Next,
     Prev,
+    /// An arbitrary OSC message, e.g. from a scheduled action.
+    Custom {
+        address: String,
+        args: Vec<String>,
+    },
+    /// Messages mtrack should apply together at `at`.
+    Bundle {
+        messages: Vec<ServerMessage>,
+        at: SystemTime,
+    },
 }
 
 #[cfg(test)]
@@ -0,0 +1,278 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use dioxus::logger::tracing::{debug, error, info, warn};
+use tokio::sync::{watch, RwLock};
+
+use super::server::OscStateMachine;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ServiceError {
+    #[error("Could not notify the service manager! {0}")]
+    Notify(String),
+    #[error("Could not write PID file! {0}")]
+    PidFile(String),
+    #[error("Could not listen for signals! {0}")]
+    Signal(String),
+}
+
+/// Tells long running tasks and push channels that the server is shutting down.
+#[derive(Clone, Debug)]
+pub struct Shutdown {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self {
+            sender: Arc::new(watch::Sender::new(false)),
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// Resolves once the shutdown was triggered, right away if it already was.
+    pub async fn wait(&self) {
+        let mut receiver = self.sender.subscribe();
+        // The sender lives as long as `self`, so waiting cannot fail.
+        let _ = receiver.wait_for(|triggered| *triggered).await;
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() -> Result<&'static str, ServiceError> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = match signal(SignalKind::terminate()) {
+        Ok(terminate) => terminate,
+        Err(err) => return Err(ServiceError::Signal(err.to_string())),
+    };
+    let mut interrupt = match signal(SignalKind::interrupt()) {
+        Ok(interrupt) => interrupt,
+        Err(err) => return Err(ServiceError::Signal(err.to_string())),
+    };
+    tokio::select! {
+        _ = terminate.recv() => Ok("SIGTERM"),
+        _ = interrupt.recv() => Ok("SIGINT"),
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() -> Result<&'static str, ServiceError> {
+    match tokio::signal::ctrl_c().await {
+        Ok(_) => Ok("Ctrl-C"),
+        Err(err) => Err(ServiceError::Signal(err.to_string())),
+    }
+}
+
+/// Triggers `shutdown` on SIGTERM or SIGINT.
+pub async fn listen_for_signals(shutdown: Shutdown) {
+    match wait_for_signal().await {
+        Ok(signal) => info!("Received {signal}, shutting down"),
+        Err(err) => {
+            error!("{err}");
+            return;
+        }
+    }
+    if let Err(err) = notify("STOPPING=1") {
+        warn!("{err}");
+    }
+    shutdown.trigger();
+}
+
+/// Sends `state` to systemd if started as a `Type=notify` service.
+/// Returns whether there was a service manager to notify.
+pub fn notify(state: &str) -> Result<bool, ServiceError> {
+    let Some(socket_path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return Ok(false);
+    };
+    send_notification(&socket_path, state)?;
+    debug!("Notified service manager: {state}");
+    Ok(true)
+}
+
+#[cfg(unix)]
+fn send_notification(socket_path: &std::ffi::OsStr, state: &str) -> Result<(), ServiceError> {
+    use std::os::unix::{ffi::OsStrExt, net::UnixDatagram};
+
+    let socket = match UnixDatagram::unbound() {
+        Ok(socket) => socket,
+        Err(err) => return Err(ServiceError::Notify(err.to_string())),
+    };
+    let result = match socket_path.as_bytes().strip_prefix(b"@") {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::{linux::net::SocketAddrExt, unix::net::SocketAddr};
+
+            match SocketAddr::from_abstract_name(name) {
+                Ok(address) => socket.send_to_addr(state.as_bytes(), &address),
+                Err(err) => Err(err),
+            }
+        }
+        _ => socket.send_to(state.as_bytes(), socket_path),
+    };
+    match result {
+        Ok(_) => Ok(()),
+        Err(err) => Err(ServiceError::Notify(err.to_string())),
+    }
+}
+
+#[cfg(not(unix))]
+fn send_notification(_socket_path: &std::ffi::OsStr, _state: &str) -> Result<(), ServiceError> {
+    Err(ServiceError::Notify(
+        "Only supported on Unix systems".to_string(),
+    ))
+}
+
+/// Interval systemd expects watchdog pings in, if the watchdog is enabled for this process.
+pub fn watchdog_interval() -> Option<Duration> {
+    parse_watchdog_interval(
+        std::env::var("WATCHDOG_USEC").ok().as_deref(),
+        std::env::var("WATCHDOG_PID").ok().as_deref(),
+        std::process::id(),
+    )
+}
+
+fn parse_watchdog_interval(
+    usec: Option<&str>,
+    pid: Option<&str>,
+    own_pid: u32,
+) -> Option<Duration> {
+    if let Some(pid) = pid {
+        if pid.parse::<u32>().ok()? != own_pid {
+            return None;
+        }
+    }
+    match usec?.parse::<u64>().ok()? {
+        0 => None,
+        usec => Some(Duration::from_micros(usec)),
+    }
+}
+
+/// Pings the systemd watchdog at half its interval until shutdown.
+pub async fn run_watchdog(shutdown: Shutdown) {
+    let Some(interval) = watchdog_interval() else {
+        return;
+    };
+    info!(
+        "Pinging the service manager watchdog every {:?}",
+        interval / 2
+    );
+    let mut ticker = tokio::time::interval(interval / 2);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                if let Err(err) = notify("WATCHDOG=1") {
+                    warn!("{err}");
+                }
+            }
+            _ = shutdown.wait() => break,
+        }
+    }
+}
+
+/// File holding the process ID while the server runs, removed on drop.
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    pub fn create(path: &Path) -> Result<Self, ServiceError> {
+        if let Some(dir) = path.parent() {
+            if let Err(err) = std::fs::create_dir_all(dir) {
+                return Err(ServiceError::PidFile(err.to_string()));
+            }
+        }
+        match std::fs::write(path, format!("{}\n", std::process::id())) {
+            Ok(_) => Ok(Self {
+                path: path.to_path_buf(),
+            }),
+            Err(err) => Err(ServiceError::PidFile(err.to_string())),
+        }
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            warn!("Could not remove PID file {}! {err}", self.path.display());
+        }
+    }
+}
+
+/// Stops a running recording and disconnects from mtrack.
+pub async fn disconnect_osc(state_machine_option: &Arc<RwLock<Option<OscStateMachine>>>) {
+    let Some(state_machine) = state_machine_option.write().await.take() else {
+        return;
+    };
+    match state_machine.stop_recording().await {
+        Ok(Some(name)) => info!("Stopped recording {name}"),
+        Ok(None) => {}
+        Err(err) => warn!("Could not stop recording! {err}"),
+    }
+    state_machine.disconnect().await;
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::time::Duration;
+
+    use super::{parse_watchdog_interval, PidFile, Shutdown};
+
+    #[test]
+    fn watchdog_interval_is_only_used_by_its_process() {
+        assert_eq!(
+            parse_watchdog_interval(Some("2000000"), None, 42),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            parse_watchdog_interval(Some("2000000"), Some("42"), 42),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            parse_watchdog_interval(Some("2000000"), Some("7"), 42),
+            None
+        );
+        assert_eq!(parse_watchdog_interval(Some("0"), None, 42), None);
+        assert_eq!(parse_watchdog_interval(None, None, 42), None);
+    }
+
+    #[test]
+    fn pid_file_is_removed_on_drop() {
+        let path = std::env::temp_dir().join(format!("mtrack-remote-{}.pid", std::process::id()));
+        let pid_file = PidFile::create(&path).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content.trim(), std::process::id().to_string());
+        drop(pid_file);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn shutdown_wakes_waiters_triggered_before_and_after() {
+        let shutdown = Shutdown::new();
+        let waiter = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.wait().await }
+        });
+        shutdown.trigger();
+        waiter.await.unwrap();
+        assert!(shutdown.is_triggered());
+        shutdown.wait().await;
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use super::command::TransportCommand;
+
+/// Window in which a second stop press confirms stopping during a locked show.
+pub const STOP_CONFIRM_WINDOW: Duration = Duration::from_secs(3);
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum ShowLockError {
+    #[error("Show lock is on, {0} is disabled during playback!")]
+    Locked(&'static str),
+    #[error("Show lock is on, press stop again to confirm!")]
+    ConfirmStop,
+}
+
+/// Guards destructive transport commands while mtrack is playing.
+#[derive(Debug, Default)]
+pub struct ShowLock {
+    /// Stops waiting for confirmation by command ID, so each client only confirms its own.
+    pending_stops: HashMap<u64, Instant>,
+}
+
+impl ShowLock {
+    /// Checks command `id`, a stop is let through if it `confirms` the ID of a stop
+    /// that asked for confirmation within the window.
+    pub fn check(
+        &mut self,
+        command: TransportCommand,
+        is_playing: bool,
+        id: u64,
+        confirms: Option<u64>,
+    ) -> Result<(), ShowLockError> {
+        self.check_at(command, is_playing, id, confirms, Instant::now())
+    }
+
+    fn check_at(
+        &mut self,
+        command: TransportCommand,
+        is_playing: bool,
+        id: u64,
+        confirms: Option<u64>,
+        now: Instant,
+    ) -> Result<(), ShowLockError> {
+        if !is_playing {
+            return Ok(());
+        }
+        match command {
+            TransportCommand::Play => Ok(()),
+            TransportCommand::Next | TransportCommand::Prev => {
+                Err(ShowLockError::Locked(command.name()))
+            }
+            TransportCommand::Stop => {
+                self.pending_stops.retain(|_id, requested_at| {
+                    now.duration_since(*requested_at) <= STOP_CONFIRM_WINDOW
+                });
+                match confirms.and_then(|confirms| self.pending_stops.remove(&confirms)) {
+                    Some(_requested_at) => Ok(()),
+                    None => {
+                        self.pending_stops.insert(id, now);
+                        Err(ShowLockError::ConfirmStop)
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{ShowLock, ShowLockError, STOP_CONFIRM_WINDOW};
+    use crate::backend::command::TransportCommand;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn skipping_is_rejected_only_while_playing() {
+        let mut show_lock = ShowLock::default();
+        assert_eq!(
+            show_lock.check(TransportCommand::Next, true, 1, None),
+            Err(ShowLockError::Locked("next"))
+        );
+        assert_eq!(
+            show_lock.check(TransportCommand::Prev, false, 2, None),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn stop_needs_confirmation_within_window() {
+        let mut show_lock = ShowLock::default();
+        let start = Instant::now();
+        let late = start + STOP_CONFIRM_WINDOW + Duration::from_millis(1);
+        assert_eq!(
+            show_lock.check_at(TransportCommand::Stop, true, 1, None, start),
+            Err(ShowLockError::ConfirmStop)
+        );
+        assert_eq!(
+            show_lock.check_at(TransportCommand::Stop, true, 2, Some(1), late),
+            Err(ShowLockError::ConfirmStop)
+        );
+        assert_eq!(
+            show_lock.check_at(
+                TransportCommand::Stop,
+                true,
+                3,
+                Some(2),
+                late + Duration::from_secs(1)
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn stops_are_only_confirmed_by_their_own_client() {
+        let mut show_lock = ShowLock::default();
+        let start = Instant::now();
+        // An accidental tap on one phone.
+        assert_eq!(
+            show_lock.check_at(TransportCommand::Stop, true, 1, None, start),
+            Err(ShowLockError::ConfirmStop)
+        );
+        // Another client pressing stop once does not confirm it.
+        let soon = start + Duration::from_secs(1);
+        assert_eq!(
+            show_lock.check_at(TransportCommand::Stop, true, 2, None, soon),
+            Err(ShowLockError::ConfirmStop)
+        );
+        assert_eq!(
+            show_lock.check_at(TransportCommand::Stop, true, 3, Some(2), soon),
+            Ok(())
+        );
+        // A confirmation only counts once.
+        assert_eq!(
+            show_lock.check_at(TransportCommand::Stop, true, 4, Some(2), soon),
+            Err(ShowLockError::ConfirmStop)
+        );
+    }
+}
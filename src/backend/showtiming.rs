@@ -0,0 +1,166 @@
+use chrono::{Duration, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    command::playlist_entry_matches,
+    durations::{parse_elapsed, SongDurations},
+    osc::MtrackState,
+};
+
+/// Estimated timing of the rest of the show, based on learned song durations.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ShowTiming {
+    /// Sum of the known durations of all setlist songs.
+    pub total_ms: u64,
+    /// Known time left of the current and all following songs.
+    pub remaining_ms: u64,
+    /// Remaining songs without a learned duration, not part of the estimate.
+    pub unknown_songs: usize,
+    pub projected_end: NaiveDateTime,
+    pub curfew: Option<NaiveDateTime>,
+    /// How far the projected end exceeds the curfew.
+    pub over_curfew_ms: u64,
+    /// Remaining songs that could be dropped to end before the curfew.
+    pub suggested_drops: Vec<String>,
+}
+
+/// The occurrence of `curfew` closest to `now`, so curfews after midnight work.
+fn get_curfew_datetime(now: NaiveDateTime, curfew: NaiveTime) -> NaiveDateTime {
+    let today = now.date().and_time(curfew);
+    [today - Duration::days(1), today, today + Duration::days(1)]
+        .into_iter()
+        .min_by_key(|candidate| (*candidate - now).num_seconds().abs())
+        .unwrap_or(today)
+}
+
+/// Picks songs to drop: the shortest single song covering `over_ms`, or else the longest songs.
+fn get_suggested_drops(mut candidates: Vec<(String, u64)>, over_ms: u64) -> Vec<String> {
+    if over_ms == 0 {
+        return vec![];
+    }
+    candidates.sort_by_key(|(_song, duration_ms)| *duration_ms);
+    if let Some((song, _duration_ms)) = candidates
+        .iter()
+        .find(|(_song, duration_ms)| *duration_ms >= over_ms)
+    {
+        return vec![song.clone()];
+    }
+    let mut dropped_ms = 0;
+    let mut drops = vec![];
+    for (song, duration_ms) in candidates.into_iter().rev() {
+        if dropped_ms >= over_ms {
+            break;
+        }
+        dropped_ms += duration_ms;
+        drops.push(song);
+    }
+    drops
+}
+
+pub fn get_show_timing(
+    mtrack_state: &MtrackState,
+    durations: &SongDurations,
+    now: NaiveDateTime,
+    curfew: Option<NaiveTime>,
+) -> ShowTiming {
+    let setlist_durations: Vec<Option<u64>> = mtrack_state
+        .setlist
+        .iter()
+        .map(|entry| durations.get(entry))
+        .collect();
+    let total_ms = setlist_durations.iter().flatten().sum();
+
+    let current = match mtrack_state.song.is_empty() {
+        true => None,
+        false => mtrack_state
+            .setlist
+            .iter()
+            .position(|entry| playlist_entry_matches(entry, &mtrack_state.song)),
+    };
+    let current = current.unwrap_or(0);
+    let elapsed_ms = parse_elapsed(&mtrack_state.time_elapsed).unwrap_or(0);
+
+    let mut remaining_ms = 0;
+    let mut unknown_songs = 0;
+    let mut drop_candidates = vec![];
+    for (index, duration_ms) in setlist_durations.iter().enumerate().skip(current) {
+        match (index == current, duration_ms) {
+            (true, Some(duration_ms)) => remaining_ms += duration_ms.saturating_sub(elapsed_ms),
+            (false, Some(duration_ms)) => {
+                remaining_ms += duration_ms;
+                drop_candidates.push((mtrack_state.setlist[index].clone(), *duration_ms));
+            }
+            (_, None) => unknown_songs += 1,
+        }
+    }
+
+    let projected_end = now + Duration::milliseconds(remaining_ms as i64);
+    let curfew = curfew.map(|curfew| get_curfew_datetime(now, curfew));
+    let over_curfew_ms = match curfew {
+        Some(curfew) if projected_end > curfew => {
+            (projected_end - curfew).num_milliseconds() as u64
+        }
+        _ => 0,
+    };
+
+    ShowTiming {
+        total_ms,
+        remaining_ms,
+        unknown_songs,
+        projected_end,
+        curfew,
+        over_curfew_ms,
+        suggested_drops: get_suggested_drops(drop_candidates, over_curfew_ms),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{get_curfew_datetime, get_show_timing};
+    use crate::backend::{durations::SongDurations, osc::MtrackState};
+    use chrono::{NaiveDate, NaiveTime};
+
+    #[test]
+    fn curfew_after_midnight_is_tomorrow() {
+        let now = NaiveDate::from_ymd_opt(2026, 10, 18)
+            .unwrap()
+            .and_hms_opt(23, 0, 0)
+            .unwrap();
+        let curfew = get_curfew_datetime(now, NaiveTime::from_hms_opt(1, 0, 0).unwrap());
+        assert_eq!(
+            curfew.date(),
+            NaiveDate::from_ymd_opt(2026, 10, 19).unwrap()
+        );
+    }
+
+    #[test]
+    fn projection_and_suggested_drops() {
+        let mut durations = SongDurations::default();
+        durations.learn("Opener", 200_000);
+        durations.learn("Ballad", 300_000);
+        durations.learn("Closer", 240_000);
+        let mtrack_state = MtrackState {
+            is_playing: true,
+            time_elapsed: "1:40".to_string(),
+            song: "Opener".to_string(),
+            setlist: vec![
+                "1. Opener".to_string(),
+                "2. Ballad".to_string(),
+                "3. Unknown".to_string(),
+                "4. Closer".to_string(),
+            ],
+            ..MtrackState::default()
+        };
+        let now = NaiveDate::from_ymd_opt(2026, 10, 18)
+            .unwrap()
+            .and_hms_opt(22, 0, 0)
+            .unwrap();
+        let curfew = NaiveTime::from_hms_opt(22, 8, 0).unwrap();
+        let timing = get_show_timing(&mtrack_state, &durations, now, Some(curfew));
+        assert_eq!(timing.total_ms, 740_000);
+        assert_eq!(timing.remaining_ms, 640_000);
+        assert_eq!(timing.unknown_songs, 1);
+        assert_eq!(timing.over_curfew_ms, 160_000);
+        assert_eq!(timing.suggested_drops, vec!["4. Closer".to_string()]);
+    }
+}
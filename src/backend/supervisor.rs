@@ -0,0 +1,247 @@
+use std::{sync::Arc, time::Duration};
+
+use arc_swap::ArcSwap;
+use dioxus::logger::tracing::debug;
+use tokio::{
+    select,
+    sync::{watch, Notify, RwLock},
+};
+
+use super::{
+    config::Config,
+    metrics::METRICS,
+    osc::MtrackState,
+    server::{ensure_connected, OscStateMachine},
+    service::Shutdown,
+};
+
+/// How often the supervisor checks the connection when mtrack is quiet.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// How long commands wait for the supervisor to connect to mtrack.
+pub const COMMAND_CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// What clients see of the connection to mtrack, published by the supervisor.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StateSnapshot {
+    pub is_connected: bool,
+    pub mtrack_state: Option<MtrackState>,
+}
+
+/// The latest snapshot, read without locking the state machine or each other.
+/// Also the supervisor's mailbox for clients needing the connection right away.
+#[derive(Debug)]
+pub struct Snapshots {
+    current: ArcSwap<StateSnapshot>,
+    version: watch::Sender<u64>,
+    connect: Notify,
+}
+
+impl Default for Snapshots {
+    fn default() -> Self {
+        Self {
+            current: ArcSwap::from_pointee(StateSnapshot::default()),
+            version: watch::Sender::new(0),
+            connect: Notify::new(),
+        }
+    }
+}
+
+impl Snapshots {
+    pub fn load(&self) -> Arc<StateSnapshot> {
+        self.current.load_full()
+    }
+
+    /// Replaces the snapshot, waiting clients are only woken if it changed.
+    pub fn publish(&self, snapshot: StateSnapshot) {
+        if **self.current.load() == snapshot {
+            return;
+        }
+        self.current.store(Arc::new(snapshot));
+        self.version.send_modify(|version| *version += 1);
+    }
+
+    /// Changes whenever a new snapshot is published.
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.version.subscribe()
+    }
+
+    /// Asks the supervisor to connect right away if it is not connected,
+    /// and waits up to `timeout` for it, so commands never connect themselves.
+    pub async fn wait_connected(&self, timeout: Duration) -> bool {
+        if self.current.load().is_connected {
+            return true;
+        }
+        self.connect.notify_one();
+        self.wait_until(timeout, |snapshot| snapshot.is_connected)
+            .await
+            .is_connected
+    }
+
+    /// Waits up to `timeout` for a snapshot `is_done` accepts and returns the latest one.
+    pub async fn wait_until(
+        &self,
+        timeout: Duration,
+        is_done: impl Fn(&StateSnapshot) -> bool,
+    ) -> Arc<StateSnapshot> {
+        // Subscribing first, so a snapshot published after checking still wakes us.
+        let mut version = self.version.subscribe();
+        let _result = tokio::time::timeout(timeout, async {
+            while !is_done(&self.current.load()) {
+                if version.changed().await.is_err() {
+                    return;
+                }
+            }
+        })
+        .await;
+        self.load()
+    }
+}
+
+/// Keeps the state machine connected to mtrack and publishes every change of its state,
+/// so polling clients neither lock the state machine nor reconnect themselves.
+/// Stops on `shutdown`, so nothing reconnects while the server disconnects.
+pub async fn run(
+    state_machine_option: Arc<RwLock<Option<OscStateMachine>>>,
+    config: Arc<RwLock<Config>>,
+    snapshots: Arc<Snapshots>,
+    shutdown: Shutdown,
+) {
+    loop {
+        if shutdown.is_triggered() {
+            debug!("Supervisor stopped");
+            return;
+        }
+        let is_connected = match state_machine_option.read().await.as_ref() {
+            Some(state_machine) => state_machine.is_connected().await,
+            None => false,
+        };
+        if !is_connected {
+            debug!("Supervisor connecting to mtrack");
+            ensure_connected(&state_machine_option, *config.read().await).await;
+        }
+
+        let (is_connected, mtrack) = match state_machine_option.read().await.as_ref() {
+            Some(state_machine) => (
+                state_machine.is_connected().await,
+                state_machine.mtrack_state(),
+            ),
+            None => (false, None),
+        };
+        // Also drops when the OSC actor died without anybody disconnecting.
+        METRICS.osc_connected.set(is_connected.into());
+        let mut mtrack = match mtrack {
+            Some(mtrack) => mtrack,
+            None => {
+                snapshots.publish(StateSnapshot {
+                    is_connected,
+                    mtrack_state: snapshots.load().mtrack_state.clone(),
+                });
+                select! {
+                    _ = shutdown.wait() => {}
+                    _ = tokio::time::sleep(CHECK_INTERVAL) => {}
+                    _ = snapshots.connect.notified() => debug!("Connection requested"),
+                }
+                continue;
+            }
+        };
+        // Forwards state changes until mtrack goes quiet, then checks the connection again.
+        loop {
+            snapshots.publish(StateSnapshot {
+                is_connected,
+                mtrack_state: Some(mtrack.borrow_and_update().clone()),
+            });
+            let changed = select! {
+                _ = shutdown.wait() => break,
+                changed = tokio::time::timeout(CHECK_INTERVAL, mtrack.changed()) => changed,
+            };
+            match changed {
+                Ok(Ok(_)) => {}
+                Ok(Err(_)) | Err(_) => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::{Snapshots, StateSnapshot};
+    use crate::backend::osc::MtrackState;
+
+    #[tokio::test]
+    async fn waiting_clients_are_woken_by_new_snapshots() {
+        let snapshots = Arc::new(Snapshots::default());
+        let snapshot = snapshots
+            .wait_until(Duration::from_millis(10), |snapshot| snapshot.is_connected)
+            .await;
+        assert!(!snapshot.is_connected);
+
+        let waiting = snapshots.clone();
+        let waiter = tokio::spawn(async move {
+            waiting
+                .wait_until(Duration::from_secs(5), |snapshot| {
+                    snapshot
+                        .mtrack_state
+                        .as_ref()
+                        .is_some_and(|mtrack_state| mtrack_state.revision == 2)
+                })
+                .await
+        });
+        for revision in 1..=2 {
+            snapshots.publish(StateSnapshot {
+                is_connected: true,
+                mtrack_state: Some(MtrackState {
+                    revision,
+                    ..MtrackState::default()
+                }),
+            });
+        }
+        let snapshot = tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(snapshot.is_connected);
+        assert_eq!(snapshot, snapshots.load());
+    }
+
+    #[tokio::test]
+    async fn commands_wait_for_the_supervisor_to_connect() {
+        let snapshots = Arc::new(Snapshots::default());
+        assert!(!snapshots.wait_connected(Duration::from_millis(10)).await);
+
+        // Stands in for the supervisor, connecting once asked to.
+        let supervisor = snapshots.clone();
+        tokio::spawn(async move {
+            supervisor.connect.notified().await;
+            supervisor.publish(StateSnapshot {
+                is_connected: true,
+                mtrack_state: None,
+            });
+        });
+        assert!(snapshots.wait_connected(Duration::from_secs(1)).await);
+    }
+
+    #[tokio::test]
+    async fn supervisor_does_not_reconnect_after_shutdown() {
+        use tokio::sync::RwLock;
+
+        use crate::backend::{config::Config, service::Shutdown};
+
+        let state_machine_option = Arc::new(RwLock::new(None));
+        let shutdown = Shutdown::new();
+        shutdown.trigger();
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            super::run(
+                state_machine_option.clone(),
+                Arc::new(RwLock::new(Config::default())),
+                Arc::new(Snapshots::default()),
+                shutdown,
+            ),
+        )
+        .await
+        .unwrap();
+        assert!(state_machine_option.read().await.is_none());
+    }
+}
@@ -0,0 +1,235 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use std::path::PathBuf;
+
+#[cfg(feature = "server")]
+use axum::{
+    extract::Path,
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+
+#[cfg(feature = "server")]
+use dioxus::logger::tracing::warn;
+
+#[cfg(feature = "server")]
+use super::config::get_config_dir;
+
+/// Built-in themes, the first one is the default defined in `default_theme.css`.
+pub const BUILTIN_THEMES: [&str; 4] = ["dark", "light", "high-contrast", "night"];
+
+/// Local storage key the theme of a client is remembered by.
+pub const THEME_STORAGE_KEY: &str = "mtrack-remote-theme";
+
+/// Largest custom theme that can be uploaded.
+pub const MAX_THEME_SIZE: usize = 64 * 1024;
+
+#[cfg(feature = "server")]
+const THEME_EXTENSION: &str = "css";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ThemeError {
+    #[error("Invalid theme name! {0}")]
+    InvalidName(String),
+    #[error("Theme is larger than {MAX_THEME_SIZE} bytes!")]
+    TooLarge,
+    #[error("Could not get themes directory! {0}")]
+    Directory(String),
+    #[error("Theme IO error! {0}")]
+    Io(String),
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Theme {
+    Builtin(String),
+    /// Uploaded CSS served from `/themes/<name>.css`.
+    Custom(String),
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::Builtin(BUILTIN_THEMES[0].to_string())
+    }
+}
+
+/// Theme names end up in paths and scripts, so they are restricted to `[A-Za-z0-9_-]`.
+pub fn is_valid_theme_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+impl Theme {
+    pub fn to_key(&self) -> String {
+        match self {
+            Theme::Builtin(name) => format!("builtin:{name}"),
+            Theme::Custom(name) => format!("custom:{name}"),
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Theme> {
+        let theme = match key.split_once(':') {
+            Some(("builtin", name)) if BUILTIN_THEMES.contains(&name) => {
+                Theme::Builtin(name.to_string())
+            }
+            Some(("custom", name)) => Theme::Custom(name.to_string()),
+            _ => return None,
+        };
+        match theme.name() {
+            name if is_valid_theme_name(name) => Some(theme),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Theme::Builtin(name) => name,
+            Theme::Custom(name) => name,
+        }
+    }
+
+    /// Script that applies the theme to the document and remembers it in local storage.
+    pub fn apply_script(&self) -> String {
+        let key = self.to_key();
+        let (data_theme, custom_link) = match self {
+            Theme::Builtin(name) => (name.as_str(), String::new()),
+            Theme::Custom(name) => (
+                "custom",
+                format!(
+                    "link = document.createElement('link'); link.id = 'custom-theme'; \
+                     link.rel = 'stylesheet'; link.href = '/themes/{name}.css'; \
+                     document.head.appendChild(link);"
+                ),
+            ),
+        };
+        format!(
+            "document.documentElement.dataset.theme = '{data_theme}'; \
+             let link = document.getElementById('custom-theme'); \
+             if (link) {{ link.remove(); }} \
+             {custom_link} \
+             localStorage.setItem('{THEME_STORAGE_KEY}', '{key}');"
+        )
+    }
+}
+
+/// Script returning the key of the remembered theme, or null.
+pub fn stored_theme_script() -> String {
+    format!("return localStorage.getItem('{THEME_STORAGE_KEY}');")
+}
+
+#[cfg(feature = "server")]
+fn get_themes_dir() -> Result<PathBuf, ThemeError> {
+    match get_config_dir() {
+        Ok(dir) => Ok(dir.join("themes")),
+        Err(err) => Err(ThemeError::Directory(err.to_string())),
+    }
+}
+
+#[cfg(feature = "server")]
+fn get_theme_path(name: &str) -> Result<PathBuf, ThemeError> {
+    if !is_valid_theme_name(name) {
+        return Err(ThemeError::InvalidName(name.to_string()));
+    }
+    Ok(get_themes_dir()?.join(format!("{name}.{THEME_EXTENSION}")))
+}
+
+/// Names of the uploaded themes, sorted.
+#[cfg(feature = "server")]
+pub fn list_custom_themes() -> Result<Vec<String>, ThemeError> {
+    let dir = get_themes_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => return Err(ThemeError::Io(err.to_string())),
+    };
+    let mut themes: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == THEME_EXTENSION))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .filter(|name| is_valid_theme_name(name))
+        .collect();
+    themes.sort();
+    Ok(themes)
+}
+
+#[cfg(feature = "server")]
+pub fn write_custom_theme(name: &str, css: &str) -> Result<(), ThemeError> {
+    if css.len() > MAX_THEME_SIZE {
+        return Err(ThemeError::TooLarge);
+    }
+    let path = get_theme_path(name)?;
+    if let Some(dir) = path.parent() {
+        if let Err(err) = std::fs::create_dir_all(dir) {
+            return Err(ThemeError::Directory(err.to_string()));
+        }
+    }
+    match std::fs::write(path, css) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(ThemeError::Io(err.to_string())),
+    }
+}
+
+#[cfg(feature = "server")]
+pub fn read_custom_theme(name: &str) -> Result<String, ThemeError> {
+    match std::fs::read_to_string(get_theme_path(name)?) {
+        Ok(css) => Ok(css),
+        Err(err) => Err(ThemeError::Io(err.to_string())),
+    }
+}
+
+#[cfg(feature = "server")]
+pub fn delete_custom_theme(name: &str) -> Result<(), ThemeError> {
+    match std::fs::remove_file(get_theme_path(name)?) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(ThemeError::Io(err.to_string())),
+    }
+}
+
+/// Route serving the uploaded themes.
+#[cfg(feature = "server")]
+pub fn router() -> Router {
+    Router::new().route("/themes/:file", get(serve_custom_theme))
+}
+
+#[cfg(feature = "server")]
+async fn serve_custom_theme(Path(file): Path<String>) -> impl IntoResponse {
+    let name = match file.strip_suffix(".css") {
+        Some(name) => name,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+    match read_custom_theme(name) {
+        Ok(css) => ([(CONTENT_TYPE, "text/css")], css).into_response(),
+        Err(err) => {
+            warn!("Could not serve theme {name}! {err}");
+            StatusCode::NOT_FOUND.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{Theme, BUILTIN_THEMES};
+
+    #[test]
+    fn theme_keys_roundtrip_and_are_validated() {
+        for theme in [
+            Theme::default(),
+            Theme::Builtin("night".to_string()),
+            Theme::Custom("festival_2026".to_string()),
+        ] {
+            assert_eq!(Theme::from_key(&theme.to_key()), Some(theme));
+        }
+        assert_eq!(Theme::default().name(), BUILTIN_THEMES[0]);
+        assert_eq!(Theme::from_key("builtin:unknown"), None);
+        assert_eq!(Theme::from_key("custom:x'; alert(1); '"), None);
+        assert_eq!(Theme::from_key("custom:"), None);
+        assert_eq!(Theme::from_key("light"), None);
+    }
+}
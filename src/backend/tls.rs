@@ -0,0 +1,340 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use std::{
+    io::BufReader,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+#[cfg(feature = "server")]
+use axum::{
+    extract::State,
+    http::{header::HOST, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Redirect, Response},
+    Router,
+};
+
+#[cfg(feature = "server")]
+use axum_server::{tls_rustls::RustlsConfig, Handle};
+
+#[cfg(feature = "server")]
+use dioxus::logger::tracing::{info, warn};
+
+#[cfg(feature = "server")]
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "server")]
+use super::{
+    config::get_config_dir,
+    service::{self, Shutdown},
+};
+
+/// Time open connections get to finish once the server shuts down.
+#[cfg(feature = "server")]
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+#[derive(Debug, thiserror::Error)]
+pub enum TlsError {
+    #[error("Could not get config directory! {0}")]
+    Directory(String),
+    #[error("Could not generate certificate! {0}")]
+    Generate(String),
+    #[error("Could not read certificate! {0}")]
+    Read(String),
+    #[error("Could not write certificate! {0}")]
+    Write(String),
+    #[error("Certificate file contains no certificate!")]
+    NoCertificate,
+    #[error("Could not serve HTTPS! {0}")]
+    Serve(String),
+}
+
+/// What the config page shows about the certificate browsers are presented.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct TlsInfo {
+    pub enabled: bool,
+    pub self_signed: bool,
+    /// SHA-256 fingerprint of the certificate to compare with the browser's.
+    pub fingerprint: Option<String>,
+}
+
+/// Certificate and key the server uses for HTTPS.
+#[cfg(feature = "server")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TlsFiles {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    pub self_signed: bool,
+}
+
+#[cfg(feature = "server")]
+impl TlsFiles {
+    /// The given certificate and key, or the stored self-signed certificate,
+    /// which is generated for the machine's addresses on first use.
+    pub fn load_or_generate(
+        cert_path: Option<PathBuf>,
+        key_path: Option<PathBuf>,
+    ) -> Result<Self, TlsError> {
+        if let (Some(cert_path), Some(key_path)) = (cert_path, key_path) {
+            return Ok(Self {
+                cert_path,
+                key_path,
+                self_signed: false,
+            });
+        }
+        let dir = match get_config_dir() {
+            Ok(dir) => dir.join("tls"),
+            Err(err) => return Err(TlsError::Directory(err.to_string())),
+        };
+        let tls_files = Self {
+            cert_path: dir.join("cert.pem"),
+            key_path: dir.join("key.pem"),
+            self_signed: true,
+        };
+        if !tls_files.cert_path.exists() || !tls_files.key_path.exists() {
+            let names = get_local_names();
+            info!(
+                "Generating self-signed certificate for {}",
+                names.join(", ")
+            );
+            let (cert_pem, key_pem) = generate_self_signed(names)?;
+            write_file(&tls_files.cert_path, &cert_pem, false)?;
+            write_file(&tls_files.key_path, &key_pem, true)?;
+        }
+        Ok(tls_files)
+    }
+
+    pub fn info(&self) -> Result<TlsInfo, TlsError> {
+        Ok(TlsInfo {
+            enabled: true,
+            self_signed: self.self_signed,
+            fingerprint: Some(read_fingerprint(&self.cert_path)?),
+        })
+    }
+}
+
+/// Names a self-signed certificate is valid for, localhost and the addresses of all interfaces.
+#[cfg(feature = "server")]
+pub fn get_local_names() -> Vec<String> {
+    let mut names = vec!["localhost".to_string()];
+    let addresses = match if_addrs::get_if_addrs() {
+        Ok(interfaces) => interfaces
+            .into_iter()
+            .map(|interface| interface.ip())
+            .collect(),
+        Err(err) => {
+            warn!("Could not list network interfaces! {err}");
+            vec![IpAddr::from([127, 0, 0, 1])]
+        }
+    };
+    for address in addresses {
+        let is_link_local = match address {
+            IpAddr::V4(address) => address.is_link_local(),
+            IpAddr::V6(address) => (address.segments()[0] & 0xffc0) == 0xfe80,
+        };
+        let name = address.to_string();
+        if !is_link_local && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// Certificate and private key PEM of a new self-signed certificate.
+#[cfg(feature = "server")]
+pub fn generate_self_signed(names: Vec<String>) -> Result<(String, String), TlsError> {
+    let mut params = match rcgen::CertificateParams::new(names) {
+        Ok(params) => params,
+        Err(err) => return Err(TlsError::Generate(err.to_string())),
+    };
+    params
+        .distinguished_name
+        .push(rcgen::DnType::CommonName, "mtrack-remote");
+    let key_pair = match rcgen::KeyPair::generate() {
+        Ok(key_pair) => key_pair,
+        Err(err) => return Err(TlsError::Generate(err.to_string())),
+    };
+    match params.self_signed(&key_pair) {
+        Ok(cert) => Ok((cert.pem(), key_pair.serialize_pem())),
+        Err(err) => Err(TlsError::Generate(err.to_string())),
+    }
+}
+
+#[cfg(feature = "server")]
+fn write_file(path: &Path, content: &str, is_private: bool) -> Result<(), TlsError> {
+    use std::io::Write;
+
+    if let Some(dir) = path.parent() {
+        if let Err(err) = std::fs::create_dir_all(dir) {
+            return Err(TlsError::Write(err.to_string()));
+        }
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if is_private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = is_private;
+    let result = options
+        .open(path)
+        .and_then(|mut file| file.write_all(content.as_bytes()));
+    match result {
+        Ok(_) => Ok(()),
+        Err(err) => Err(TlsError::Write(err.to_string())),
+    }
+}
+
+/// SHA-256 fingerprint of a DER encoded certificate, formatted like browsers show it.
+#[cfg(feature = "server")]
+pub fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<String>>()
+        .join(":")
+}
+
+/// Fingerprint of the first certificate in a PEM file.
+#[cfg(feature = "server")]
+pub fn read_fingerprint(cert_path: &Path) -> Result<String, TlsError> {
+    let file = match std::fs::File::open(cert_path) {
+        Ok(file) => file,
+        Err(err) => return Err(TlsError::Read(err.to_string())),
+    };
+    match rustls_pemfile::certs(&mut BufReader::new(file)).next() {
+        Some(Ok(cert)) => Ok(fingerprint(&cert)),
+        Some(Err(err)) => Err(TlsError::Read(err.to_string())),
+        None => Err(TlsError::NoCertificate),
+    }
+}
+
+/// Serves `router` over HTTPS until shutdown, telling the service manager once listening.
+#[cfg(feature = "server")]
+pub async fn serve_https(
+    address: SocketAddr,
+    tls_files: &TlsFiles,
+    router: Router,
+    shutdown: Shutdown,
+) -> Result<(), TlsError> {
+    // Only ring is compiled in, so installing it as the default cannot conflict.
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let rustls_config =
+        match RustlsConfig::from_pem_file(&tls_files.cert_path, &tls_files.key_path).await {
+            Ok(rustls_config) => rustls_config,
+            Err(err) => return Err(TlsError::Read(err.to_string())),
+        };
+    let handle = Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            shutdown.wait().await;
+            handle.graceful_shutdown(Some(SHUTDOWN_GRACE_PERIOD));
+        }
+    });
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            if let Some(address) = handle.listening().await {
+                info!("Serving HTTPS on {address}");
+                if let Err(err) = service::notify("READY=1") {
+                    warn!("{err}");
+                }
+            }
+        }
+    });
+    let serving = axum_server::bind_rustls(address, rustls_config)
+        .handle(handle)
+        .serve(router.into_make_service_with_connect_info::<SocketAddr>());
+    match serving.await {
+        Ok(_) => Ok(()),
+        Err(err) => Err(TlsError::Serve(err.to_string())),
+    }
+}
+
+/// HTTPS URL for a request to `host`, which may carry the HTTP port.
+pub fn get_https_url(host: &str, https_port: u16, path_and_query: &str) -> Option<String> {
+    let hostname = match host.strip_prefix('[') {
+        Some(ipv6) => format!("[{}]", ipv6.split(']').next()?),
+        None => host.split(':').next()?.to_string(),
+    };
+    if hostname.is_empty() || hostname == "[]" {
+        return None;
+    }
+    match https_port {
+        443 => Some(format!("https://{hostname}{path_and_query}")),
+        port => Some(format!("https://{hostname}:{port}{path_and_query}")),
+    }
+}
+
+/// Routes every plain HTTP request to the same path on the HTTPS port.
+#[cfg(feature = "server")]
+pub fn redirect_router(https_port: u16) -> Router {
+    Router::new()
+        .fallback(redirect_to_https)
+        .with_state(https_port)
+}
+
+#[cfg(feature = "server")]
+async fn redirect_to_https(
+    State(https_port): State<u16>,
+    headers: HeaderMap,
+    uri: Uri,
+) -> Response {
+    let host = headers.get(HOST).and_then(|host| host.to_str().ok());
+    let path_and_query = uri
+        .path_and_query()
+        .map(|path_and_query| path_and_query.as_str())
+        .unwrap_or("/");
+    match host.and_then(|host| get_https_url(host, https_port, path_and_query)) {
+        // Temporary, so browsers do not stick to HTTPS if it is turned off again.
+        Some(url) => Redirect::temporary(&url).into_response(),
+        None => StatusCode::BAD_REQUEST.into_response(),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::get_https_url;
+
+    #[test]
+    fn redirects_keep_host_and_path_but_change_port() {
+        assert_eq!(
+            get_https_url("192.168.1.20:8080", 8443, "/mtrack?x=1").as_deref(),
+            Some("https://192.168.1.20:8443/mtrack?x=1")
+        );
+        assert_eq!(
+            get_https_url("[fe80::1]:80", 443, "/").as_deref(),
+            Some("https://[fe80::1]/")
+        );
+        assert_eq!(
+            get_https_url("stage.local", 8443, "/stage").as_deref(),
+            Some("https://stage.local:8443/stage")
+        );
+        assert_eq!(get_https_url("", 8443, "/"), None);
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn generated_certificate_has_a_fingerprint() {
+        use super::{fingerprint, generate_self_signed};
+
+        let (cert_pem, key_pem) =
+            generate_self_signed(vec!["localhost".to_string(), "192.168.1.20".to_string()])
+                .unwrap();
+        assert!(key_pem.contains("PRIVATE KEY"));
+        let cert = rustls_pemfile::certs(&mut cert_pem.as_bytes())
+            .next()
+            .unwrap()
+            .unwrap();
+        let fingerprint = fingerprint(&cert);
+        assert_eq!(fingerprint.len(), 32 * 3 - 1);
+        assert!(fingerprint
+            .split(':')
+            .all(|byte| byte.len() == 2 && byte.chars().all(|c| c.is_ascii_hexdigit())));
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::VecDeque;
+
+#[cfg(feature = "server")]
+use std::net::SocketAddr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use dioxus::logger::tracing::warn;
+
+#[cfg(feature = "server")]
+use rosc::{OscMessage, OscPacket, OscType};
+
+#[cfg(feature = "server")]
+use super::recording::{Recorder, RecordingError};
+
+pub const TRAFFIC_LOG_CAPACITY: usize = 500;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum TrafficDirection {
+    Inbound,
+    Outbound,
+    /// Inbound but dropped, e.g. bundles beyond the schedule limit.
+    Rejected,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TrafficArg {
+    pub type_tag: char,
+    pub value: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TrafficEntry {
+    pub sequence: u64,
+    pub timestamp: DateTime<Utc>,
+    pub direction: TrafficDirection,
+    pub address: String,
+    pub args: Vec<TrafficArg>,
+    /// Source of inbound and destination of outbound messages.
+    pub peer: String,
+}
+
+/// Bounded ring buffer of the most recent OSC messages.
+#[derive(Debug)]
+pub struct TrafficLog {
+    entries: VecDeque<TrafficEntry>,
+    next_sequence: u64,
+    capacity: usize,
+    #[cfg(feature = "server")]
+    recorder: Option<Recorder>,
+}
+
+impl Default for TrafficLog {
+    fn default() -> Self {
+        Self::new(TRAFFIC_LOG_CAPACITY)
+    }
+}
+
+impl TrafficLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            next_sequence: 0,
+            capacity,
+            #[cfg(feature = "server")]
+            recorder: None,
+        }
+    }
+
+    pub fn push_entry(&mut self, mut entry: TrafficEntry) {
+        entry.sequence = self.next_sequence;
+        self.next_sequence += 1;
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Entries newer than `sequence`, or all buffered entries if `None`.
+    pub fn entries_since(&self, sequence: Option<u64>) -> Vec<TrafficEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| sequence.is_none_or(|sequence| entry.sequence > sequence))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(feature = "server")]
+fn get_traffic_arg(arg: &OscType) -> TrafficArg {
+    let (type_tag, value) = match arg {
+        OscType::Int(value) => ('i', value.to_string()),
+        OscType::Float(value) => ('f', value.to_string()),
+        OscType::String(value) => ('s', value.clone()),
+        OscType::Blob(value) => ('b', format!("{} bytes", value.len())),
+        OscType::Time(value) => ('t', format!("{}.{}", value.seconds, value.fractional)),
+        OscType::Long(value) => ('h', value.to_string()),
+        OscType::Double(value) => ('d', value.to_string()),
+        OscType::Char(value) => ('c', value.to_string()),
+        OscType::Color(value) => (
+            'r',
+            format!(
+                "{} {} {} {}",
+                value.red, value.green, value.blue, value.alpha
+            ),
+        ),
+        OscType::Midi(value) => (
+            'm',
+            format!(
+                "{} {} {} {}",
+                value.port, value.status, value.data1, value.data2
+            ),
+        ),
+        OscType::Bool(value) => (if *value { 'T' } else { 'F' }, value.to_string()),
+        OscType::Array(value) => ('[', format!("{} items", value.content.len())),
+        OscType::Nil => ('N', String::new()),
+        OscType::Inf => ('I', String::new()),
+    };
+    TrafficArg { type_tag, value }
+}
+
+#[cfg(feature = "server")]
+impl TrafficLog {
+    pub fn push_message(
+        &mut self,
+        direction: TrafficDirection,
+        osc_message: &OscMessage,
+        peer: SocketAddr,
+    ) {
+        self.record(direction, &OscPacket::Message(osc_message.clone()), peer);
+        self.log_message(direction, osc_message, peer);
+    }
+
+    pub fn push_packet(
+        &mut self,
+        direction: TrafficDirection,
+        osc_packet: &OscPacket,
+        peer: SocketAddr,
+    ) {
+        self.record(direction, osc_packet, peer);
+        self.log_packet(direction, osc_packet, peer);
+    }
+
+    /// Shows a dropped inbound packet to the operator, it is not recorded.
+    pub fn push_rejected(&mut self, osc_packet: &OscPacket, peer: SocketAddr) {
+        self.log_packet(TrafficDirection::Rejected, osc_packet, peer);
+    }
+
+    /// Starts writing all packets to `recorder`, finishing a running recording.
+    pub fn start_recording(&mut self, recorder: Recorder) -> Result<(), RecordingError> {
+        self.stop_recording()?;
+        self.recorder = Some(recorder);
+        Ok(())
+    }
+
+    /// Finishes the running recording and returns its name.
+    pub fn stop_recording(&mut self) -> Result<Option<String>, RecordingError> {
+        match self.recorder.take() {
+            Some(recorder) => {
+                let name = recorder.name().to_string();
+                recorder.finish()?;
+                Ok(Some(name))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn recording_name(&self) -> Option<String> {
+        self.recorder
+            .as_ref()
+            .map(|recorder| recorder.name().to_string())
+    }
+
+    fn record(&mut self, direction: TrafficDirection, osc_packet: &OscPacket, peer: SocketAddr) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(err) = recorder.record(direction, osc_packet, peer) {
+                warn!("Could not record OSC packet! {err}");
+            }
+        }
+    }
+
+    fn log_message(
+        &mut self,
+        direction: TrafficDirection,
+        osc_message: &OscMessage,
+        peer: SocketAddr,
+    ) {
+        self.push_entry(TrafficEntry {
+            sequence: 0,
+            timestamp: Utc::now(),
+            direction,
+            address: osc_message.addr.clone(),
+            args: osc_message.args.iter().map(get_traffic_arg).collect(),
+            peer: peer.to_string(),
+        });
+    }
+
+    fn log_packet(
+        &mut self,
+        direction: TrafficDirection,
+        osc_packet: &OscPacket,
+        peer: SocketAddr,
+    ) {
+        match osc_packet {
+            OscPacket::Message(osc_message) => self.log_message(direction, osc_message, peer),
+            OscPacket::Bundle(osc_bundle) => {
+                for osc_packet in osc_bundle.content.iter() {
+                    self.log_packet(direction, osc_packet, peer);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    #[cfg(feature = "server")]
+    use super::{TrafficDirection, TrafficLog};
+    #[cfg(feature = "server")]
+    use rosc::{OscMessage, OscType};
+
+    #[cfg(feature = "server")]
+    fn message(addr: &str) -> OscMessage {
+        OscMessage {
+            addr: addr.to_string(),
+            args: vec![OscType::String("Playing".to_string()), OscType::Int(3)],
+        }
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn ring_buffer_drops_oldest_entries() {
+        let mut log = TrafficLog::new(2);
+        let peer = "127.0.0.1:43234".parse().unwrap();
+        for addr in ["/a", "/b", "/c"] {
+            log.push_message(TrafficDirection::Inbound, &message(addr), peer);
+        }
+        let entries = log.entries_since(None);
+        let addresses: Vec<&str> = entries.iter().map(|e| e.address.as_str()).collect();
+        assert_eq!(addresses, vec!["/b", "/c"]);
+        assert_eq!(entries[1].sequence, 2);
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn entries_since_returns_newer_entries_with_typed_args() {
+        let mut log = TrafficLog::default();
+        let peer = "127.0.0.1:43234".parse().unwrap();
+        log.push_message(TrafficDirection::Outbound, &message("/a"), peer);
+        log.push_message(TrafficDirection::Inbound, &message("/b"), peer);
+        let entries = log.entries_since(Some(0));
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].address, "/b");
+        assert_eq!(entries[0].args[0].type_tag, 's');
+        assert_eq!(entries[0].args[1].value, "3");
+        assert_eq!(entries[0].peer, "127.0.0.1:43234");
+    }
+}
@@ -0,0 +1,325 @@
+use std::{net::SocketAddr, process::ExitCode, sync::Arc, time::Duration};
+
+use clap::{Parser, Subcommand};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::RwLock;
+
+use mtrack_remote::backend::{
+    api::{ApiError, ApiStatus, GotoRequest, API_PREFIX},
+    command::{goto_song, run_command, CommandAck, CommandStatus, TransportCommand},
+    config::Config,
+    osc::MtrackState,
+    server::{ensure_connected, OscStateMachine},
+};
+
+/// Time mtrack has to report its state after connecting directly.
+const STATE_TIMEOUT: Duration = Duration::from_secs(3);
+const STATE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Headless client for scripting mtrack, printing JSON.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// Base URL of a running mtrack-remote server, e.g. http://localhost:8080.
+    /// Without it, mtrack is controlled directly over OSC.
+    #[arg(long, global = true)]
+    server: Option<String>,
+    /// Address of mtrack when connecting directly, defaults to the configured one.
+    #[arg(long, global = true)]
+    mtrack: Option<SocketAddr>,
+    /// Port to receive mtrack's replies on when connecting directly, defaults to the configured one.
+    #[arg(long, global = true)]
+    listen_port: Option<u16>,
+    #[command(subcommand)]
+    command: CliCommand,
+}
+
+#[derive(Subcommand)]
+enum CliCommand {
+    /// Print the connection and mtrack state.
+    Status,
+    Play,
+    Stop {
+        /// ID of the answer to a stop the show lock asked to confirm.
+        #[arg(long)]
+        confirm: Option<u64>,
+    },
+    Next,
+    Prev,
+    /// Step through the setlist until the song is current.
+    Goto {
+        song: String,
+    },
+    /// Print the current setlist.
+    Setlist,
+    /// Print the status as one JSON line whenever it changes.
+    Watch {
+        #[arg(long, default_value_t = 500)]
+        interval_ms: u64,
+    },
+}
+
+#[derive(Debug, thiserror::Error)]
+enum CliError {
+    #[error("Request failed! {0}")]
+    Http(String),
+    #[error("Could not (de)serialize JSON! {0}")]
+    Json(String),
+    #[error("{0}")]
+    Server(String),
+    #[error("{0}")]
+    Command(String),
+    #[error("mtrack did not report its state!")]
+    NoState,
+}
+
+enum Target {
+    Http {
+        client: reqwest::Client,
+        base_url: String,
+    },
+    /// Talks OSC to mtrack itself, so the show lock of a running server does not apply.
+    Direct {
+        state_machine_option: Arc<RwLock<Option<OscStateMachine>>>,
+    },
+}
+
+impl Target {
+    async fn connect(cli: &Cli) -> Result<Self, CliError> {
+        if let Some(server) = cli.server.as_ref() {
+            return Ok(Target::Http {
+                client: reqwest::Client::new(),
+                base_url: format!("{}{API_PREFIX}", server.trim_end_matches('/')),
+            });
+        }
+        let mut config = Config::read_config().unwrap_or_default();
+        if let Some(mtrack) = cli.mtrack {
+            config.mtrack_addr = mtrack;
+        }
+        if let Some(listen_port) = cli.listen_port {
+            config.listen_port = listen_port;
+        }
+        let state_machine_option = Arc::new(RwLock::new(Some(OscStateMachine::new())));
+        ensure_connected(&state_machine_option, config).await;
+        let target = Target::Direct {
+            state_machine_option,
+        };
+        target.wait_for_state().await;
+        Ok(target)
+    }
+
+    /// The OSC connection has to be closed before it is dropped.
+    async fn disconnect(self) {
+        if let Target::Direct {
+            state_machine_option,
+        } = self
+        {
+            if let Some(state_machine) = state_machine_option.write().await.take() {
+                state_machine.disconnect().await;
+            }
+        }
+    }
+
+    /// Waits until mtrack reported a song, the state is empty until then.
+    async fn wait_for_state(&self) {
+        let _ = tokio::time::timeout(STATE_TIMEOUT, async {
+            while self
+                .direct_state()
+                .await
+                .is_none_or(|mtrack| mtrack.song.is_empty())
+            {
+                tokio::time::sleep(STATE_POLL_INTERVAL).await;
+            }
+        })
+        .await;
+    }
+
+    async fn direct_state(&self) -> Option<MtrackState> {
+        match self {
+            Target::Http { .. } => None,
+            Target::Direct {
+                state_machine_option,
+            } => state_machine_option
+                .read()
+                .await
+                .as_ref()
+                .and_then(|state_machine| state_machine.get_mtrack_data().ok()),
+        }
+    }
+
+    async fn status(&self) -> Result<ApiStatus, CliError> {
+        match self {
+            Target::Http { .. } => self.request(reqwest::Method::GET, "status", None).await,
+            Target::Direct {
+                state_machine_option,
+            } => {
+                let connected = match state_machine_option.read().await.as_ref() {
+                    Some(state_machine) => state_machine.is_connected().await,
+                    None => false,
+                };
+                Ok(ApiStatus {
+                    connected,
+                    show_lock: false,
+                    mtrack: self.direct_state().await,
+                })
+            }
+        }
+    }
+
+    async fn setlist(&self) -> Result<Vec<String>, CliError> {
+        match self {
+            Target::Http { .. } => self.request(reqwest::Method::GET, "setlist", None).await,
+            Target::Direct { .. } => match self.direct_state().await {
+                Some(mtrack) => Ok(mtrack.setlist),
+                None => Err(CliError::NoState),
+            },
+        }
+    }
+
+    async fn command(
+        &self,
+        command: TransportCommand,
+        confirms: Option<u64>,
+    ) -> Result<CommandAck, CliError> {
+        match self {
+            Target::Http { .. } => {
+                let path = match confirms {
+                    Some(confirms) => format!("{}?confirm={confirms}", command.name()),
+                    None => command.name().to_string(),
+                };
+                self.request(reqwest::Method::POST, &path, None).await
+            }
+            Target::Direct {
+                state_machine_option,
+            } => match run_command(state_machine_option, false, command, confirms).await {
+                Ok(ack) => Ok(ack),
+                Err(err) => Err(CliError::Command(err.to_string())),
+            },
+        }
+    }
+
+    async fn goto(&self, song: String) -> Result<MtrackState, CliError> {
+        match self {
+            Target::Http { .. } => {
+                let body = match serde_json::to_string(&GotoRequest { song }) {
+                    Ok(body) => body,
+                    Err(err) => return Err(CliError::Json(err.to_string())),
+                };
+                self.request(reqwest::Method::POST, "goto", Some(body))
+                    .await
+            }
+            Target::Direct {
+                state_machine_option,
+            } => {
+                if let Err(err) = goto_song(state_machine_option, false, &song).await {
+                    return Err(CliError::Command(err.to_string()));
+                }
+                match self.direct_state().await {
+                    Some(mtrack) => Ok(mtrack),
+                    None => Err(CliError::NoState),
+                }
+            }
+        }
+    }
+
+    async fn request<T: DeserializeOwned>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<String>,
+    ) -> Result<T, CliError> {
+        let Target::Http { client, base_url } = self else {
+            return Err(CliError::Http("Not connected to a server!".to_string()));
+        };
+        let mut request = client.request(method, format!("{base_url}/{path}"));
+        if let Some(body) = body {
+            request = request
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body);
+        }
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(err) => return Err(CliError::Http(err.to_string())),
+        };
+        let is_success = response.status().is_success();
+        let text = match response.text().await {
+            Ok(text) => text,
+            Err(err) => return Err(CliError::Http(err.to_string())),
+        };
+        if !is_success {
+            return match serde_json::from_str::<ApiError>(&text) {
+                Ok(api_error) => Err(CliError::Server(api_error.error)),
+                Err(_err) => Err(CliError::Server(text)),
+            };
+        }
+        match serde_json::from_str(&text) {
+            Ok(value) => Ok(value),
+            Err(err) => Err(CliError::Json(err.to_string())),
+        }
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), CliError> {
+    match serde_json::to_string(value) {
+        Ok(json) => {
+            println!("{json}");
+            Ok(())
+        }
+        Err(err) => Err(CliError::Json(err.to_string())),
+    }
+}
+
+/// Runs the subcommand, returning whether it succeeded.
+async fn run(cli: Cli) -> Result<bool, CliError> {
+    let target = Target::connect(&cli).await?;
+    let result = execute(&target, cli.command).await;
+    target.disconnect().await;
+    result
+}
+
+async fn execute(target: &Target, command: CliCommand) -> Result<bool, CliError> {
+    let (command, confirms) = match command {
+        CliCommand::Status => return print_json(&target.status().await?).map(|_| true),
+        CliCommand::Setlist => return print_json(&target.setlist().await?).map(|_| true),
+        CliCommand::Goto { song } => return print_json(&target.goto(song).await?).map(|_| true),
+        CliCommand::Watch { interval_ms } => {
+            let mut last_status = None;
+            loop {
+                let status = target.status().await?;
+                if last_status.as_ref() != Some(&status) {
+                    print_json(&status)?;
+                    last_status = Some(status);
+                }
+                tokio::time::sleep(Duration::from_millis(interval_ms)).await;
+            }
+        }
+        CliCommand::Play => (TransportCommand::Play, None),
+        CliCommand::Stop { confirm } => (TransportCommand::Stop, confirm),
+        CliCommand::Next => (TransportCommand::Next, None),
+        CliCommand::Prev => (TransportCommand::Prev, None),
+    };
+    let ack = target.command(command, confirms).await?;
+    print_json(&ack)?;
+    Ok(matches!(
+        ack.status,
+        CommandStatus::Confirmed | CommandStatus::AlreadyInEffect
+    ))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            let error = ApiError {
+                error: err.to_string(),
+            };
+            match serde_json::to_string(&error) {
+                Ok(json) => eprintln!("{json}"),
+                Err(_err) => eprintln!("{err}"),
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use dioxus::{
+    logger::tracing::{debug, error},
+    prelude::*,
+};
+
+#[cfg(feature = "server")]
+use std::sync::Arc;
+
+#[cfg(feature = "server")]
+use tokio::sync::RwLock;
+
+#[cfg(feature = "server")]
+use crate::backend::{autoadvance::AutoAdvance, metrics::ServerFnTimer, server::OscStateMachine};
+
+use crate::backend::autoadvance::{AutoAdvanceSettings, DEFAULT_GAP_SECS};
+use crate::components::NumberInput;
+use crate::i18n::use_i18n;
+
+#[component]
+fn SongGap(song: String, gap_secs: u16, settings: Signal<Option<AutoAdvanceSettings>>) -> Element {
+    let gap_edit = use_signal(|| gap_secs);
+    let song_move = song.clone();
+    use_effect(move || {
+        let gap = gap_edit();
+        if let Some(settings) = settings.write().as_mut() {
+            match gap == settings.gap_secs {
+                true => settings.song_gaps.remove(&song_move),
+                false => settings.song_gaps.insert(song_move.clone(), gap),
+            };
+        }
+    });
+    rsx!(
+        div { class: "flex flex-row w-full",
+            label { class: "basis-1/3", "{song}" }
+            div { class: "basis-1/3" }
+            div { class: "basis-1/3",
+                NumberInput { value: gap_edit, default_value: gap_secs }
+            }
+        }
+    )
+}
+
+/// Settings for chaining songs with a gap in between.
+#[component]
+pub fn AutoAdvanceConfig() -> Element {
+    let i18n = use_i18n();
+    let mut settings: Signal<Option<AutoAdvanceSettings>> = use_signal(|| None);
+    let mut gap_edit = use_signal(|| DEFAULT_GAP_SECS);
+    let mut setlist = use_resource(move || async move {
+        match get_auto_advance().await {
+            Ok((server_settings, server_setlist)) => {
+                gap_edit.set(server_settings.gap_secs);
+                settings.set(Some(server_settings));
+                server_setlist
+            }
+            Err(e) => {
+                error!("Could not get auto-advance settings! {e}");
+                vec![]
+            }
+        }
+    });
+    use_effect(move || {
+        let gap = gap_edit();
+        if let Some(settings) = settings.write().as_mut() {
+            settings.gap_secs = gap;
+        }
+    });
+
+    let settings_read = settings.read();
+    let (enabled, enabled_text) = match settings_read.as_ref() {
+        Some(settings) if settings.enabled => (true, i18n.t("common.on")),
+        Some(_settings) => (false, i18n.t("common.off")),
+        None => (false, i18n.t("common.unknown")),
+    };
+    let song_gaps = match (settings_read.as_ref(), setlist.read().as_ref()) {
+        (Some(current_settings), Some(songs)) => rsx!(
+            for song in songs.iter() {
+                SongGap {
+                    key: "{song}",
+                    song: song.clone(),
+                    gap_secs: current_settings.gap_secs_for(song),
+                    settings,
+                }
+            }
+        ),
+        _ => rsx!(),
+    };
+
+    rsx! {
+        div { id: "auto-advance", class: "flex flex-col w-full",
+            header { {i18n.t("autoadvance.title")} }
+            div { class: "flex flex-row w-full",
+                label { class: "basis-1/3", {i18n.t("autoadvance.enabled")} }
+                div { class: "basis-1/3", "{enabled_text}" }
+                div { class: "basis-1/3",
+                    button {
+                        onclick: move |_event| {
+                            if let Some(settings) = settings.write().as_mut() {
+                                settings.enabled = !enabled;
+                            }
+                        },
+                        {i18n.t("common.toggle")}
+                    }
+                }
+            }
+            div { class: "flex flex-row w-full",
+                label { class: "basis-1/3", {i18n.t("autoadvance.gap")} }
+                div { class: "basis-1/3" }
+                div { class: "basis-1/3",
+                    NumberInput { value: gap_edit, default_value: DEFAULT_GAP_SECS }
+                }
+            }
+            {song_gaps}
+            div { class: "flex flex-row w-full",
+                button {
+                    class: "basis-1/2",
+                    onclick: move |_event| {
+                        setlist.restart();
+                    },
+                    {i18n.t("common.reset")}
+                }
+                button {
+                    class: "basis-1/2",
+                    onclick: move |_event| async move {
+                        debug!("Save auto-advance settings");
+                        let new_settings = match settings.peek().clone() {
+                            Some(new_settings) => new_settings,
+                            None => return,
+                        };
+                        if let Err(e) = set_auto_advance(new_settings).await {
+                            error!("Could not set auto-advance settings! {e}");
+                        }
+                    },
+                    {i18n.t("common.save")}
+                }
+            }
+        }
+    }
+}
+
+/// Get auto-advance settings and the current setlist
+#[server]
+async fn get_auto_advance() -> Result<(AutoAdvanceSettings, Vec<String>), ServerFnError> {
+    let _timer = ServerFnTimer::new("get_auto_advance");
+    let FromContext(auto_advance): FromContext<Arc<RwLock<AutoAdvance>>> = extract().await?;
+    let settings = auto_advance.read().await.settings.clone();
+    let FromContext(state_machine_option): FromContext<Arc<RwLock<Option<OscStateMachine>>>> =
+        extract().await?;
+    let setlist = match state_machine_option.read().await.as_ref() {
+        Some(state_machine) => state_machine
+            .get_mtrack_data()
+            .map(|mtrack_state| mtrack_state.setlist)
+            .unwrap_or_default(),
+        None => vec![],
+    };
+    Ok((settings, setlist))
+}
+
+#[server(SetAutoAdvance)]
+async fn set_auto_advance(settings: AutoAdvanceSettings) -> Result<(), ServerFnError> {
+    let _timer = ServerFnTimer::new("set_auto_advance");
+    let FromContext(auto_advance): FromContext<Arc<RwLock<AutoAdvance>>> = extract().await?;
+    if let Err(err) = settings.write_settings() {
+        return Err(ServerFnError::ServerError(err.to_string()));
+    }
+    auto_advance.write().await.settings = settings;
+    Ok(())
+}
@@ -3,6 +3,7 @@ use std::{
     str::FromStr,
 };
 
+use chrono::NaiveTime;
 use dioxus::{
     logger::tracing::{debug, error, warn},
     prelude::*,
@@ -12,13 +13,17 @@ use dioxus::{
 use std::sync::Arc;
 
 #[cfg(feature = "server")]
-use crate::backend::server::OscStateMachine;
+use crate::backend::{metrics::ServerFnTimer, server::OscStateMachine};
 
 #[cfg(feature = "server")]
 use tokio::sync::RwLock;
 
-use crate::backend::config::{Config, DEFAULT_LISTEN_PORT, DEFAULT_MTRACK_PORT};
-use crate::components::{NumberInput, TextInput};
+use crate::backend::config::{
+    Config, OscProtocol, DEFAULT_LISTEN_PORT, DEFAULT_MTRACK_PORT, DEFAULT_OSCQUERY_PORT,
+    DEFAULT_REFRESH_INTERVAL_SECS,
+};
+use crate::components::{DiscoveredHostsList, NumberInput, TextInput};
+use crate::i18n::use_i18n;
 
 enum OptionResource<T: 'static> {
     SomeResource(Resource<T>),
@@ -29,9 +34,23 @@ struct ConfigResetArguments {
     mtrack_host_edit: Signal<String>,
     mtrack_port_edit: Signal<u16>,
     listen_port_edit: Signal<u16>,
+    oscquery_port_edit: Signal<u16>,
+    curfew_edit: Signal<String>,
+    osc_protocol_edit: Signal<OscProtocol>,
+    refresh_interval_edit: Signal<u16>,
     used_config: OptionResource<Option<Config>>,
 }
 
+/// Format of the curfew input.
+const CURFEW_FORMAT: &str = "%H:%M";
+
+fn get_osc_protocol(value: &str) -> OscProtocol {
+    match value {
+        "tcp" => OscProtocol::Tcp,
+        _ => OscProtocol::Udp,
+    }
+}
+
 fn try_read_used_config(used_config: OptionResource<Option<Config>>) -> Option<Config> {
     match used_config {
         OptionResource::SomeResource(used_config_resource) => {
@@ -56,6 +75,17 @@ fn reset_config(mut config_editors: ConfigResetArguments) {
             config_editors.mtrack_port_edit.set(config_mtrack_port);
             let config_listen_port = config.listen_port;
             config_editors.listen_port_edit.set(config_listen_port);
+            let config_oscquery_port = config.oscquery_port;
+            config_editors.oscquery_port_edit.set(config_oscquery_port);
+            let config_curfew = config
+                .curfew
+                .map(|curfew| curfew.format(CURFEW_FORMAT).to_string())
+                .unwrap_or_default();
+            config_editors.curfew_edit.set(config_curfew);
+            config_editors.osc_protocol_edit.set(config.osc_protocol);
+            config_editors
+                .refresh_interval_edit
+                .set(config.refresh_interval_secs);
         }
         None => warn!("Current server config is not set! Cannot reset config signals."),
     };
@@ -64,9 +94,14 @@ fn reset_config(mut config_editors: ConfigResetArguments) {
 /// Config component that allows changing application settings.
 #[component]
 pub fn ConfigComponent() -> Element {
-    let mtrack_host_edit = use_signal(|| "0.0.0.0".to_string());
-    let mtrack_port_edit = use_signal(|| 0);
+    let i18n = use_i18n();
+    let mut mtrack_host_edit = use_signal(|| "0.0.0.0".to_string());
+    let mut mtrack_port_edit = use_signal(|| 0);
     let listen_port_edit = use_signal(|| 0);
+    let oscquery_port_edit = use_signal(|| 0);
+    let curfew_edit = use_signal(String::new);
+    let mut osc_protocol_edit = use_signal(OscProtocol::default);
+    let refresh_interval_edit = use_signal(|| DEFAULT_REFRESH_INTERVAL_SECS);
     let mut used_config = use_resource(move || async move {
         let server_config = match get_config().await {
             Ok(server_config) => Some(server_config),
@@ -81,6 +116,10 @@ pub fn ConfigComponent() -> Element {
                 mtrack_host_edit,
                 mtrack_port_edit,
                 listen_port_edit,
+                oscquery_port_edit,
+                curfew_edit,
+                osc_protocol_edit,
+                refresh_interval_edit,
                 used_config: OptionResource::NoResource(server_config),
             };
             reset_config(config_editors);
@@ -100,6 +139,30 @@ pub fn ConfigComponent() -> Element {
         NumberInput { value: listen_port_edit, default_value: DEFAULT_LISTEN_PORT }
     );
 
+    let oscquery_port_element = rsx!(
+        NumberInput { value: oscquery_port_edit, default_value: DEFAULT_OSCQUERY_PORT }
+    );
+
+    let curfew_element = rsx!(
+        TextInput { value: curfew_edit, default_value: "" }
+    );
+
+    let osc_protocol = *osc_protocol_edit.read();
+    let osc_protocol_element = rsx!(
+        select { onchange: move |event| osc_protocol_edit.set(get_osc_protocol(&event.value())),
+            option { value: "udp", selected: osc_protocol == OscProtocol::Udp,
+                {i18n.t("config.osc_protocol_udp")}
+            }
+            option { value: "tcp", selected: osc_protocol == OscProtocol::Tcp,
+                {i18n.t("config.osc_protocol_tcp")}
+            }
+        }
+    );
+
+    let refresh_interval_element = rsx!(
+        NumberInput { value: refresh_interval_edit, default_value: DEFAULT_REFRESH_INTERVAL_SECS }
+    );
+
     let edit_config_memo = use_memo(move || {
         debug!("Edit config memo!");
         let mtrack_host = mtrack_host_edit.read().to_string();
@@ -122,12 +185,42 @@ pub fn ConfigComponent() -> Element {
             }
         };
 
+        let oscquery_port = *oscquery_port_edit.read();
+
+        let curfew_text = curfew_edit.read().trim().to_string();
+        let curfew = match curfew_text.is_empty() {
+            true => None,
+            false => match NaiveTime::parse_from_str(&curfew_text, CURFEW_FORMAT) {
+                Ok(curfew) => Some(curfew),
+                Err(e) => {
+                    error!("Could not parse curfew {curfew_text}! {e}");
+                    return None;
+                }
+            },
+        };
+
+        let show_lock = match used_config.read().as_ref() {
+            Some(Some(config)) => config.show_lock,
+            Some(None) | None => false,
+        };
+
         Some(Config {
             mtrack_addr,
             listen_port,
+            oscquery_port,
+            show_lock,
+            curfew,
+            osc_protocol: *osc_protocol_edit.read(),
+            refresh_interval_secs: *refresh_interval_edit.read(),
         })
     });
 
+    let show_lock_text = match used_config.read().as_ref() {
+        Some(Some(config)) if config.show_lock => i18n.t("common.on"),
+        Some(Some(_config)) => i18n.t("common.off"),
+        Some(None) | None => i18n.t("common.unknown"),
+    };
+
     let is_unchanged = use_memo(move || {
         let used_config_read = match used_config.read().as_ref() {
             Some(Some(config)) => *config,
@@ -143,23 +236,43 @@ pub fn ConfigComponent() -> Element {
 
     rsx! {
         div { id: "config", class: "flex flex-col w-full",
-            header { "Configuration" }
+            header { {i18n.t("config.title")} }
             div { class: "flex flex-col w-full",
                 div { class: "flex flex-row w-full",
-                    label { class: "basis-1/3", "mtrack host" }
+                    label { class: "basis-1/3", {i18n.t("config.mtrack_host")} }
                     div { class: "basis-1/3" }
                     div { class: "basis-1/3", {mtrack_host_element} }
                 }
                 div { class: "flex flex-row  w-full",
-                    label { class: "basis-1/3", "mtrack port" }
+                    label { class: "basis-1/3", {i18n.t("config.mtrack_port")} }
                     div { class: "basis-1/3" }
                     div { class: "basis-1/3", {mtrack_port_element} }
                 }
                 div { class: "flex flex-row w-full",
-                    label { class: "basis-1/3", "listen port" }
+                    label { class: "basis-1/3", {i18n.t("config.listen_port")} }
                     div { class: "basis-1/3" }
                     div { class: "basis-1/3", {listen_port_element} }
                 }
+                div { class: "flex flex-row w-full",
+                    label { class: "basis-1/3", {i18n.t("config.oscquery_port")} }
+                    div { class: "basis-1/3" }
+                    div { class: "basis-1/3", {oscquery_port_element} }
+                }
+                div { class: "flex flex-row w-full",
+                    label { class: "basis-1/3", {i18n.t("config.curfew")} }
+                    div { class: "basis-1/3" }
+                    div { class: "basis-1/3", {curfew_element} }
+                }
+                div { class: "flex flex-row w-full",
+                    label { class: "basis-1/3", {i18n.t("config.osc_protocol")} }
+                    div { class: "basis-1/3" }
+                    div { class: "basis-1/3", {osc_protocol_element} }
+                }
+                div { class: "flex flex-row w-full",
+                    label { class: "basis-1/3", {i18n.t("config.refresh_interval")} }
+                    div { class: "basis-1/3" }
+                    div { class: "basis-1/3", {refresh_interval_element} }
+                }
                 div { class: "flex flex-row w-full",
                     button {
                         class: "basis-1/2",
@@ -169,11 +282,15 @@ pub fn ConfigComponent() -> Element {
                                 mtrack_host_edit,
                                 mtrack_port_edit,
                                 listen_port_edit,
+                                oscquery_port_edit,
+                                curfew_edit,
+                                osc_protocol_edit,
+                                refresh_interval_edit,
                                 used_config: OptionResource::SomeResource(used_config),
                             };
                             reset_config(config_editors);
                         },
-                        "Reset"
+                        {i18n.t("common.reset")}
                     }
                     button {
                         class: "basis-1/2",
@@ -196,7 +313,33 @@ pub fn ConfigComponent() -> Element {
                             };
                             used_config.restart();
                         },
-                        "Save"
+                        {i18n.t("common.save")}
+                    }
+                }
+                DiscoveredHostsList {
+                    on_select: move |addr: SocketAddr| {
+                        mtrack_host_edit.set(addr.ip().to_string());
+                        mtrack_port_edit.set(addr.port());
+                    },
+                }
+            }
+            header { {i18n.t("config.show_lock_title")} }
+            div { class: "flex flex-row w-full",
+                label { class: "basis-1/3", {i18n.t("config.show_lock")} }
+                div { class: "basis-1/3", "{show_lock_text}" }
+                div { class: "basis-1/3",
+                    button {
+                        onclick: move |_event| async move {
+                            let show_lock = match used_config.peek().as_ref() {
+                                Some(Some(config)) => config.show_lock,
+                                Some(None) | None => return,
+                            };
+                            match set_show_lock(!show_lock).await {
+                                Ok(_) => used_config.restart(),
+                                Err(e) => error!("Could not toggle show lock! {e}"),
+                            };
+                        },
+                        {i18n.t("common.toggle")}
                     }
                 }
             }
@@ -207,6 +350,7 @@ pub fn ConfigComponent() -> Element {
 /// Get server configuration
 #[server]
 async fn get_config() -> Result<Config, ServerFnError> {
+    let _timer = ServerFnTimer::new("get_config");
     debug!("Getting config...");
     let FromContext(config): FromContext<Arc<RwLock<Config>>> = extract().await?;
     let config = config.read().await;
@@ -224,9 +368,26 @@ async fn set_config_value(config: Arc<RwLock<Config>>, new_config: Config) {
     };
 }
 
+#[server(SetShowLock)]
+async fn set_show_lock(show_lock: bool) -> Result<(), ServerFnError> {
+    let _timer = ServerFnTimer::new("set_show_lock");
+    let FromContext(config): FromContext<Arc<RwLock<Config>>> = extract().await?;
+    let mut new_config = *config.read().await;
+    new_config.show_lock = show_lock;
+    warn!("Show lock turned {}", if show_lock { "on" } else { "off" });
+    set_config_value(config, new_config).await;
+    Ok(())
+}
+
 #[server(SetNewConfig)]
 async fn set_config(new_config: Config) -> Result<Config, ServerFnError> {
+    let _timer = ServerFnTimer::new("set_config");
     let FromContext(config): FromContext<Arc<RwLock<Config>>> = extract().await?;
+    // The show lock is only changed via `set_show_lock`, so stale forms cannot unlock it.
+    let new_config = Config {
+        show_lock: config.read().await.show_lock,
+        ..new_config
+    };
     set_config_value(config.clone(), new_config).await;
     let FromContext(state_option): FromContext<Arc<RwLock<Option<OscStateMachine>>>> =
         extract().await?;
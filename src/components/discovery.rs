@@ -0,0 +1,68 @@
+use std::net::SocketAddr;
+
+use dioxus::{logger::tracing::debug, prelude::*};
+use gloo_timers::future::TimeoutFuture;
+
+#[cfg(feature = "server")]
+use std::sync::Arc;
+
+#[cfg(feature = "server")]
+use tokio::sync::RwLock;
+
+#[cfg(feature = "server")]
+use crate::backend::{discovery::DiscoveredHosts, metrics::ServerFnTimer};
+
+use crate::{backend::discovery::DiscoveredHost, i18n::use_i18n};
+
+/// mtrack instances come and go rarely, so they are looked up less often than the state.
+const DISCOVERY_POLL_INTERVAL_MS: u32 = 3000;
+
+/// mtrack instances found on the LAN, selecting one fills in its address.
+#[component]
+pub fn DiscoveredHostsList(on_select: EventHandler<SocketAddr>) -> Element {
+    let i18n = use_i18n();
+    let mut hosts: Signal<Vec<DiscoveredHost>> = use_signal(Vec::new);
+    use_future(move || async move {
+        loop {
+            match get_discovered_hosts().await {
+                Ok(discovered_hosts) => {
+                    if *hosts.peek() != discovered_hosts {
+                        hosts.set(discovered_hosts);
+                    }
+                }
+                Err(err) => debug!("Could not get discovered hosts! {err}"),
+            }
+            TimeoutFuture::new(DISCOVERY_POLL_INTERVAL_MS).await;
+        }
+    });
+
+    rsx! {
+        div { class: "flex flex-col w-full",
+            label { {i18n.t("discovery.title")} }
+            if hosts.read().is_empty() {
+                div { class: "discovery-empty", {i18n.t("discovery.none")} }
+            }
+            for host in hosts.read().iter().cloned() {
+                div { key: "{host.name}", class: "flex flex-row w-full",
+                    label { class: "basis-1/3", "{host.name}" }
+                    div { class: "basis-1/3", "{host.hostname} ({host.addr})" }
+                    div { class: "basis-1/3",
+                        button {
+                            onclick: move |_event| on_select.call(host.addr),
+                            {i18n.t("discovery.use")}
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Get the mtrack instances advertised on the LAN
+#[server]
+async fn get_discovered_hosts() -> Result<Vec<DiscoveredHost>, ServerFnError> {
+    let _timer = ServerFnTimer::new("get_discovered_hosts");
+    let FromContext(hosts): FromContext<Arc<RwLock<DiscoveredHosts>>> = extract().await?;
+    let hosts = hosts.read().await.list();
+    Ok(hosts)
+}
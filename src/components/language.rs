@@ -0,0 +1,48 @@
+use dioxus::{logger::tracing::warn, prelude::*};
+
+use crate::i18n::{stored_language_script, use_i18n, Language};
+
+/// Option value that follows the browser's language.
+const AUTO_LANGUAGE: &str = "auto";
+
+/// Language selector that overrides the browser's language for this client.
+#[component]
+pub fn LanguageSelector() -> Element {
+    let mut i18n = use_i18n();
+    let mut is_overridden = use_signal(|| false);
+    use_future(move || async move {
+        match document::eval(&stored_language_script())
+            .join::<Option<String>>()
+            .await
+        {
+            Ok(stored_language) => is_overridden.set(stored_language.is_some()),
+            Err(err) => warn!("Could not read stored language! {err:?}"),
+        }
+    });
+
+    let selected = match *is_overridden.read() {
+        true => i18n.language.read().code(),
+        false => AUTO_LANGUAGE,
+    };
+
+    rsx!(
+        select {
+            class: "navbar-select",
+            onchange: move |event| {
+                let language = Language::from_tag(&event.value());
+                is_overridden.set(language.is_some());
+                i18n.set_language(language);
+            },
+            option { value: AUTO_LANGUAGE, selected: selected == AUTO_LANGUAGE,
+                {i18n.t("language.auto")}
+            }
+            for language in Language::ALL {
+                option {
+                    value: language.code(),
+                    selected: selected == language.code(),
+                    {language.native_name()}
+                }
+            }
+        }
+    )
+}
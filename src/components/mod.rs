@@ -1,20 +1,55 @@
+mod autoadvance;
+pub use autoadvance::AutoAdvanceConfig;
+
 mod config;
 pub use config::ConfigComponent;
 
 mod navbar;
 pub use navbar::Navbar;
 
+mod discovery;
+pub use discovery::DiscoveredHostsList;
+
+mod language;
+pub use language::LanguageSelector;
+
 mod mtrack;
+#[cfg(feature = "server")]
+pub use mtrack::read_state_update;
 pub use mtrack::Mtrack;
 
+mod notes;
+pub use notes::SongNotesConfig;
+
 mod numberinput;
 pub use numberinput::NumberInput;
 
 mod playlist;
 pub use playlist::Playlist;
 
+mod recordings;
+pub use recordings::Recordings;
+
+mod schedule;
+pub use schedule::ScheduleComponent;
+
+mod showtiming;
+pub use showtiming::ShowTimingComponent;
+
+mod stage;
+pub use stage::StageDisplay;
+
 mod textinput;
 pub use textinput::TextInput;
 
+mod theme;
+pub use theme::{restore_stored_theme, ThemeSelector, ThemesConfig};
+
+mod tls;
+pub use tls::TlsStatus;
+
+mod traffic;
+pub use traffic::Traffic;
+
 mod transport;
 pub use transport::Transport;
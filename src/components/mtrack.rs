@@ -1,6 +1,7 @@
-#[cfg(feature = "server")]
-use dioxus::logger::tracing::warn;
-use dioxus::{logger::tracing::debug, prelude::*};
+use dioxus::{
+    logger::tracing::{debug, warn},
+    prelude::*,
+};
 
 use gloo_timers::future::TimeoutFuture;
 
@@ -8,10 +9,18 @@ use gloo_timers::future::TimeoutFuture;
 use crate::backend::config::Config;
 
 #[cfg(feature = "server")]
-use crate::backend::server::OscStateMachine;
+use crate::backend::{
+    autoadvance::AutoAdvance, delta, durations::SongDurations, metrics::ServerFnTimer,
+    metrics::METRICS, showtiming::get_show_timing, supervisor::Snapshots,
+};
+
+use std::collections::BTreeMap;
 
 #[cfg(feature = "server")]
-use std::sync::Arc;
+use std::net::SocketAddr;
+
+#[cfg(feature = "server")]
+use std::{sync::Arc, time::Duration};
 
 #[cfg(feature = "server")]
 use tokio::sync::RwLock;
@@ -19,53 +28,178 @@ use tokio::sync::RwLock;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    backend::osc::MtrackState,
+    backend::{
+        autoadvance::Countdown,
+        delta::{DeltaCursor, MtrackDelta},
+        osc::MtrackState,
+        showtiming::ShowTiming,
+    },
     components::{self},
+    i18n::use_i18n,
 };
 
 enum UpdateMessage {}
 
+/// Poll interval while mtrack is not connected, otherwise the server holds each poll.
+const POLL_INTERVAL_MS: u32 = 500;
+/// How long the server holds a poll until mtrack changes.
+#[cfg(feature = "server")]
+const LONG_POLL_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long the server holds a poll during an auto-advance countdown.
+#[cfg(feature = "server")]
+const COUNTDOWN_POLL_TIMEOUT: Duration = Duration::from_millis(500);
+/// Upper bound of the reconnect backoff.
+const MAX_RETRY_INTERVAL_MS: u32 = 8000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ServerConnection {
+    Connecting,
+    Reachable,
+    Unreachable { retry_ms: u32 },
+}
+
+/// Doubles the wait before the next poll after an error, starting from the poll interval
+/// even if the last poll was held by the server and followed without a wait.
+fn next_retry_interval(timeout_ms: u32) -> u32 {
+    (timeout_ms.max(POLL_INTERVAL_MS) * 2).min(MAX_RETRY_INTERVAL_MS)
+}
+
 /// Mtrack component that controls mtrack via the Dioxus fullstack API.
+/// Keeps showing the last known state while the server or mtrack are unreachable.
 #[component]
 pub fn Mtrack() -> Element {
+    let i18n = use_i18n();
     let client_state: Signal<Option<ClientState>> = use_signal(|| None);
+    let server_connection = use_signal(|| ServerConnection::Connecting);
     let mut client_state_move = client_state;
+    let mut server_connection_move = server_connection;
     let _update_routine = use_coroutine(move |_rx: UnboundedReceiver<UpdateMessage>| async move {
         debug!("Starting coroutine");
         client_state_move.set(None);
 
+        let mut timeout_ms = POLL_INTERVAL_MS;
+        let mut cursor = None;
         loop {
-            client_state_move.set(get_state().await.ok());
-            let timeout_ms = 500;
-            TimeoutFuture::new(timeout_ms).await;
+            match get_state(cursor).await {
+                Ok(update) => {
+                    // Only what changed is sent, the rest is kept from the last known state.
+                    timeout_ms = if update.is_connected { 0 } else { POLL_INTERVAL_MS };
+                    if let Some(update_cursor) = update.cursor() {
+                        cursor = Some(update_cursor);
+                    }
+                    let state = ClientState::updated(client_state_move.peek().as_ref(), update);
+                    client_state_move.set(Some(state));
+                    server_connection_move.set(ServerConnection::Reachable);
+                }
+                Err(err) => {
+                    debug!("Could not get state from server! {err}");
+                    // The server may restart meanwhile, so the whole state is asked for again.
+                    cursor = None;
+                    timeout_ms = next_retry_interval(timeout_ms);
+                    server_connection_move.set(ServerConnection::Unreachable {
+                        retry_ms: timeout_ms,
+                    });
+                }
+            }
+            if timeout_ms > 0 {
+                TimeoutFuture::new(timeout_ms).await;
+            }
         }
     });
 
+    let connection_banner = match (*server_connection.read(), client_state.read().as_ref()) {
+        (ServerConnection::Unreachable { retry_ms }, _) => {
+            let retry_secs = retry_ms.div_ceil(1000).to_string();
+            let text = i18n.t_with("mtrack.server_unreachable", &[("seconds", retry_secs)]);
+            rsx!(
+                div { class: "offline-banner", "{text}" }
+            )
+        }
+        (_, Some(state)) if !state.is_connected => rsx!(
+            div { class: "offline-banner", {i18n.t("mtrack.mtrack_unreachable")} }
+        ),
+        (_, Some(state)) if !state.is_known() => rsx!(
+            div { class: "offline-banner", {i18n.t("mtrack.state_unknown")} }
+        ),
+        _ => rsx!(),
+    };
+    let is_stale = match (*server_connection.read(), client_state.read().as_ref()) {
+        (ServerConnection::Unreachable { .. }, _) => true,
+        (_, Some(state)) => !state.is_connected || !state.is_known(),
+        (_, None) => false,
+    };
+
     let client_state_view = match client_state.read().as_ref() {
         Some(state) => {
             let mtrack_state = state.mtrack_state.clone().unwrap_or(MtrackState::default());
+            let show_lock_banner = match state.show_lock {
+                true => rsx!(
+                    div { class: "show-lock", {i18n.t("mtrack.show_lock")} }
+                ),
+                false => rsx!(),
+            };
+            let countdown_element = match state.countdown.as_ref() {
+                Some(countdown) => {
+                    let remaining_secs = countdown.remaining_ms.div_ceil(1000).to_string();
+                    let text = i18n.t_with(
+                        "mtrack.countdown",
+                        &[("song", countdown.song.clone()), ("seconds", remaining_secs)],
+                    );
+                    rsx!(
+                        div { class: "countdown flex flex-row",
+                            div { class: "basis-1/2", "{text}" }
+                            button {
+                                class: "basis-1/2",
+                                onclick: move |_event| async move {
+                                    if let Err(err) = cancel_auto_advance().await {
+                                        warn!("Could not cancel auto-advance! {err}");
+                                    }
+                                },
+                                {i18n.t("common.cancel")}
+                            }
+                        }
+                    )
+                }
+                None => rsx!(),
+            };
+            let show_timing_element = match state.show_timing.clone() {
+                Some(timing) => rsx!(
+                    components::ShowTimingComponent { timing }
+                ),
+                None => rsx!(),
+            };
             rsx!(
-                div {
+                div { class: if is_stale { "stale" } else { "" },
+                    {show_lock_banner}
+                    {countdown_element}
                     components::Transport {
                         is_playing: mtrack_state.is_playing,
                         elapsed: mtrack_state.time_elapsed,
+                        duration_ms: state.current_duration_ms,
+                        show_lock: state.show_lock,
                     }
+                    {show_timing_element}
                     components::Playlist {
                         songs: mtrack_state.setlist,
                         current_song: mtrack_state.song,
+                        durations: state.durations.clone(),
                     }
                 }
             )
         }
-        None => {
-            rsx!(
-                {"No info from server"}
-            )
-        }
+        None => match *server_connection.read() {
+            ServerConnection::Connecting => rsx!(
+                {i18n.t("mtrack.connecting")}
+            ),
+            _ => rsx!(
+                {i18n.t("mtrack.no_info")}
+            ),
+        },
     }?;
     rsx! {
         div { id: "mtrack",
-            header { "mtrack remote" }
+            header { {i18n.t("mtrack.title")} }
+            {connection_banner}
             {client_state_view}
         }
     }
@@ -75,36 +209,167 @@ pub fn Mtrack() -> Element {
 pub struct ClientState {
     is_connected: bool,
     mtrack_state: Option<MtrackState>,
+    show_lock: bool,
+    countdown: Option<Countdown>,
+    /// Learned duration of the current song.
+    current_duration_ms: Option<u64>,
+    /// Learned durations of the setlist entries.
+    durations: BTreeMap<String, u64>,
+    show_timing: Option<ShowTiming>,
 }
 
-/// Get app state
-#[server]
-async fn get_state() -> Result<ClientState, ServerFnError> {
-    let FromContext(state_machine_option): FromContext<Arc<RwLock<Option<OscStateMachine>>>> =
-        extract().await?;
-    let mut state_machine_option = state_machine_option.write().await;
+impl ClientState {
+    /// Whether mtrack reported its state since the server connected to it.
+    fn is_known(&self) -> bool {
+        self.mtrack_state
+            .as_ref()
+            .is_some_and(|mtrack_state| mtrack_state.is_known)
+    }
 
+    /// Applies `update` to the last known state.
+    fn updated(last_state: Option<&ClientState>, update: StateUpdate) -> Self {
+        let mut mtrack_state = last_state.and_then(|last_state| last_state.mtrack_state.clone());
+        if let Some(delta) = update.mtrack {
+            mtrack_state
+                .get_or_insert_with(MtrackState::default)
+                .apply_delta(delta);
+        }
+        let durations = match update.durations {
+            Some(durations) => durations,
+            None => last_state
+                .map(|last_state| last_state.durations.clone())
+                .unwrap_or_default(),
+        };
+        Self {
+            is_connected: update.is_connected,
+            mtrack_state,
+            show_lock: update.show_lock,
+            countdown: update.countdown,
+            current_duration_ms: update.current_duration_ms,
+            durations,
+            show_timing: update.show_timing,
+        }
+    }
+}
+
+/// The client state as sent by the server, the mtrack state and durations only if they changed.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StateUpdate {
+    is_connected: bool,
+    mtrack: Option<MtrackDelta>,
+    show_lock: bool,
+    countdown: Option<Countdown>,
+    current_duration_ms: Option<u64>,
+    durations: Option<BTreeMap<String, u64>>,
+    show_timing: Option<ShowTiming>,
+}
+
+impl StateUpdate {
+    /// Where the next poll continues from, `None` without an mtrack state.
+    pub fn cursor(&self) -> Option<DeltaCursor> {
+        self.mtrack.as_ref().map(MtrackDelta::cursor)
+    }
+}
+
+/// Get app state, waiting until mtrack changed after `since` if there is one
+#[server]
+async fn get_state(since: Option<DeltaCursor>) -> Result<StateUpdate, ServerFnError> {
+    let _timer = ServerFnTimer::new("get_state");
+    match extract::<axum::extract::ConnectInfo<SocketAddr>, _>().await {
+        Ok(axum::extract::ConnectInfo(addr)) => METRICS.record_web_client(addr.ip()),
+        Err(err) => debug!("Could not get client address! {err}"),
+    };
+    // Connecting is up to the supervisor, polls only read its snapshots.
+    let FromContext(snapshots): FromContext<Arc<Snapshots>> = extract().await?;
     let FromContext(config): FromContext<Arc<RwLock<Config>>> = extract().await?;
+    let FromContext(auto_advance): FromContext<Arc<RwLock<AutoAdvance>>> = extract().await?;
+    let FromContext(durations): FromContext<Arc<RwLock<SongDurations>>> = extract().await?;
+    Ok(read_state_update(&snapshots, &config, &auto_advance, &durations, since).await)
+}
 
-    let mut is_connected = false;
-    let mut mtrack_state = None;
-    *state_machine_option = match state_machine_option.take() {
-        Some(mut state_machine) => {
-            state_machine = state_machine.ensure_connection(*config.read().await).await;
-            is_connected = state_machine.is_connected().await;
+/// The state update `get_state` answers with, without locking the state machine.
+#[cfg(feature = "server")]
+pub async fn read_state_update(
+    snapshots: &Snapshots,
+    config: &RwLock<Config>,
+    auto_advance: &RwLock<AutoAdvance>,
+    durations: &RwLock<SongDurations>,
+    since: Option<DeltaCursor>,
+) -> StateUpdate {
+    // A revision of an earlier server run is ignored, so the client gets the whole state.
+    let epoch = delta::epoch();
+    let revision = since
+        .filter(|since| since.epoch == epoch)
+        .map(|since| since.revision);
+    let timeout = match (revision, auto_advance.read().await.get_countdown()) {
+        (None, _) => Duration::ZERO,
+        (Some(_), Some(_)) => COUNTDOWN_POLL_TIMEOUT,
+        (Some(_), None) => LONG_POLL_TIMEOUT,
+    };
+    let snapshot = snapshots
+        .wait_until(timeout, |snapshot| match snapshot.mtrack_state.as_ref() {
+            Some(mtrack_state) => !snapshot.is_connected || Some(mtrack_state.revision) != revision,
+            None => true,
+        })
+        .await;
+    let is_connected = snapshot.is_connected;
+    let mtrack_state = snapshot.mtrack_state.as_ref();
+    let countdown = auto_advance.read().await.get_countdown();
+    let show_lock = config.read().await.show_lock;
 
-            mtrack_state = state_machine.get_mtrack_data().ok();
-            Some(state_machine)
-        }
-        None => {
-            warn!("Creating new OSC state machine");
-            Some(OscStateMachine::new())
-        }
+    let durations = durations.read().await;
+    let curfew = config.read().await.curfew;
+    let delta = mtrack_state.map(|mtrack_state| mtrack_state.delta_since(epoch, since));
+    let changes_song = delta.as_ref().is_some_and(|delta| delta.changes_song());
+    let (current_duration_ms, setlist_durations, show_timing) = match mtrack_state {
+        Some(mtrack_state) => (
+            durations.get(&mtrack_state.song),
+            changes_song.then(|| durations.for_setlist(&mtrack_state.setlist)),
+            Some(get_show_timing(
+                mtrack_state,
+                &durations,
+                chrono::Local::now().naive_local(),
+                curfew,
+            )),
+        ),
+        None => (None, None, None),
     };
 
-    debug!("Returning client state...");
-    Ok(ClientState {
+    debug!("Returning client state update...");
+    StateUpdate {
         is_connected,
-        mtrack_state,
-    })
+        mtrack: delta,
+        show_lock,
+        countdown,
+        current_duration_ms,
+        durations: setlist_durations,
+        show_timing,
+    }
+}
+
+#[server(CancelAutoAdvance)]
+async fn cancel_auto_advance() -> Result<(), ServerFnError> {
+    let _timer = ServerFnTimer::new("cancel_auto_advance");
+    let FromContext(auto_advance): FromContext<Arc<RwLock<AutoAdvance>>> = extract().await?;
+    auto_advance.write().await.cancel();
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    #[test]
+    fn errors_after_long_polls_back_off() {
+        use super::{next_retry_interval, MAX_RETRY_INTERVAL_MS, POLL_INTERVAL_MS};
+
+        // A connected long poll is followed right away, an error afterwards still waits.
+        let mut timeout_ms = 0;
+        timeout_ms = next_retry_interval(timeout_ms);
+        assert_eq!(timeout_ms, POLL_INTERVAL_MS * 2);
+        timeout_ms = next_retry_interval(timeout_ms);
+        assert_eq!(timeout_ms, POLL_INTERVAL_MS * 4);
+        for _ in 0..10 {
+            timeout_ms = next_retry_interval(timeout_ms);
+        }
+        assert_eq!(timeout_ms, MAX_RETRY_INTERVAL_MS);
+    }
 }
@@ -1,4 +1,8 @@
-use crate::route::Route;
+use crate::{
+    components::{LanguageSelector, ThemeSelector},
+    i18n::use_i18n,
+    route::Route,
+};
 use dioxus::prelude::*;
 
 #[component]
@@ -10,10 +14,16 @@ fn NavLink(to: Route, children: Element) -> Element {
 
 #[component]
 pub fn Navbar() -> Element {
+    let i18n = use_i18n();
     rsx! {
         div { id: "navbar",
-            NavLink { to: Route::Mtrack {}, "mtrack" }
-            NavLink { to: Route::Config {}, "config" }
+            NavLink { to: Route::Mtrack {}, {i18n.t("nav.mtrack")} }
+            NavLink { to: Route::Config {}, {i18n.t("nav.config")} }
+            NavLink { to: Route::Traffic {}, {i18n.t("nav.traffic")} }
+            NavLink { to: Route::Schedule {}, {i18n.t("nav.schedule")} }
+            NavLink { to: Route::Stage {}, {i18n.t("nav.stage")} }
+            ThemeSelector {}
+            LanguageSelector {}
         }
 
         Outlet::<Route> {}
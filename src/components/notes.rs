@@ -0,0 +1,113 @@
+use dioxus::{logger::tracing::error, prelude::*};
+
+#[cfg(feature = "server")]
+use dioxus::logger::tracing::debug;
+
+#[cfg(feature = "server")]
+use std::sync::Arc;
+
+#[cfg(feature = "server")]
+use tokio::sync::RwLock;
+
+#[cfg(feature = "server")]
+use crate::backend::{metrics::ServerFnTimer, notes::SongNotes, server::OscStateMachine};
+
+use crate::components::TextInput;
+use crate::i18n::use_i18n;
+
+#[component]
+fn SongNotesRow(song: String, notes: String) -> Element {
+    let i18n = use_i18n();
+    let notes_edit = use_signal(|| notes.clone());
+    let mut saved_notes = use_signal(|| notes);
+    let is_unchanged = *notes_edit.read() == *saved_notes.read();
+
+    rsx!(
+        div { class: "flex flex-row w-full",
+            label { class: "basis-1/3", "{song}" }
+            div { class: "basis-1/3",
+                TextInput { value: notes_edit, default_value: "" }
+            }
+            div { class: "basis-1/3",
+                button {
+                    disabled: is_unchanged,
+                    onclick: move |_event| {
+                        let song = song.clone();
+                        async move {
+                            let notes = notes_edit.peek().clone();
+                            match set_song_notes(song, notes.clone()).await {
+                                Ok(_) => saved_notes.set(notes),
+                                Err(err) => error!("Could not save song notes! {err}"),
+                            };
+                        }
+                    },
+                    {i18n.t("common.save")}
+                }
+            }
+        }
+    )
+}
+
+/// Song notes component to edit the notes of the setlist shown on the stage display.
+#[component]
+pub fn SongNotesConfig() -> Element {
+    let i18n = use_i18n();
+    let song_notes = use_resource(get_song_notes);
+
+    let rows = match song_notes.read().as_ref() {
+        Some(Ok(rows)) => rows.clone(),
+        Some(Err(err)) => {
+            error!("Could not get song notes! {err}");
+            Vec::new()
+        }
+        None => Vec::new(),
+    };
+
+    rsx! {
+        div { id: "song-notes", class: "flex flex-col w-full",
+            header { {i18n.t("notes.title")} }
+            for (song , notes) in rows {
+                SongNotesRow { key: "{song}", song: song.clone(), notes }
+            }
+        }
+    }
+}
+
+/// Get the notes of the songs in the current setlist
+#[server]
+async fn get_song_notes() -> Result<Vec<(String, String)>, ServerFnError> {
+    let _timer = ServerFnTimer::new("get_song_notes");
+    let FromContext(state_machine_option): FromContext<Arc<RwLock<Option<OscStateMachine>>>> =
+        extract().await?;
+    let setlist = match state_machine_option.read().await.as_ref() {
+        Some(state_machine) => match state_machine.get_mtrack_data() {
+            Ok(mtrack_state) => mtrack_state.setlist,
+            Err(err) => {
+                debug!("No setlist to get notes for! {err}");
+                Vec::new()
+            }
+        },
+        None => Vec::new(),
+    };
+    let FromContext(notes): FromContext<Arc<RwLock<SongNotes>>> = extract().await?;
+    let notes = notes.read().await;
+    Ok(setlist
+        .into_iter()
+        .map(|song| {
+            let song_notes = notes.get(&song).unwrap_or_default();
+            (song, song_notes)
+        })
+        .collect())
+}
+
+#[server(SetSongNotes)]
+async fn set_song_notes(song: String, notes: String) -> Result<(), ServerFnError> {
+    let _timer = ServerFnTimer::new("set_song_notes");
+    let FromContext(song_notes): FromContext<Arc<RwLock<SongNotes>>> = extract().await?;
+    let mut song_notes = song_notes.write().await;
+    song_notes.set(&song, &notes);
+    match song_notes.write_notes() {
+        Ok(_) => Ok(()),
+        Err(err) => Err(ServerFnError::ServerError(err.to_string())),
+    }
+}
@@ -1,19 +1,31 @@
+use std::collections::BTreeMap;
+
 use dioxus::{logger::tracing::debug, prelude::*};
 
+use crate::backend::{command::playlist_entry_matches, durations::format_duration};
+
 #[component]
-pub fn Song(song: String, is_current: bool) -> Element {
+pub fn Song(song: String, is_current: bool, duration_ms: Option<u64>) -> Element {
     let class = match is_current {
         true => "current_song",
         false => "song",
     };
+    let duration = duration_ms.map(format_duration).unwrap_or_default();
 
     rsx!(
-        div { class, "{song}" }
+        div { class,
+            "{song}"
+            span { class: "song-duration", "{duration}" }
+        }
     )
 }
 
 #[component]
-pub fn Playlist(songs: Vec<String>, current_song: String) -> Element {
+pub fn Playlist(
+    songs: Vec<String>,
+    current_song: String,
+    durations: BTreeMap<String, u64>,
+) -> Element {
     use_effect(|| {
         debug!("Now I'd like to scroll to the current song..");
     });
@@ -26,7 +38,8 @@ pub fn Playlist(songs: Vec<String>, current_song: String) -> Element {
                         text_anchor: "{current_song}",
                         Song {
                             song: format!("{element}"),
-                            is_current: element.ends_with(&current_song),
+                            is_current: playlist_entry_matches(element, &current_song),
+                            duration_ms: durations.get(element).copied(),
                         }
                     }
                 }
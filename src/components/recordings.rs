@@ -0,0 +1,250 @@
+use dioxus::{
+    logger::tracing::{debug, warn},
+    prelude::*,
+};
+use gloo_timers::future::TimeoutFuture;
+
+#[cfg(feature = "server")]
+use std::sync::Arc;
+
+#[cfg(feature = "server")]
+use tokio::sync::RwLock;
+
+#[cfg(feature = "server")]
+use crate::backend::{metrics::ServerFnTimer, recording, server::OscStateMachine};
+
+use crate::backend::recording::{RecordingInfo, RecordingStatus};
+use crate::i18n::use_i18n;
+
+enum UpdateMessage {}
+
+const REPLAY_SPEEDS: [u16; 4] = [1, 2, 4, 8];
+
+#[component]
+fn RecordingRow(recording: RecordingInfo, speed: Signal<u16>) -> Element {
+    let i18n = use_i18n();
+    let size_kb = i18n.number(recording.size_bytes as f64 / 1024.0, 0);
+    rsx!(
+        div { class: "flex flex-row w-full",
+            label { class: "basis-1/3", "{recording.name}" }
+            div { class: "basis-1/3", "{size_kb} kB" }
+            div { class: "basis-1/3",
+                button {
+                    onclick: move |_event| {
+                        let name = recording.name.clone();
+                        async move {
+                            debug!("Replay {name}");
+                            if let Err(err) = start_replay(name, *speed.peek() as f64).await {
+                                warn!("Could not start replay! {err}");
+                            }
+                        }
+                    },
+                    {i18n.t("recordings.replay")}
+                }
+            }
+        }
+    )
+}
+
+/// Recordings component that records OSC sessions and replays them.
+#[component]
+pub fn Recordings() -> Element {
+    let i18n = use_i18n();
+    let mut status: Signal<RecordingStatus> = use_signal(RecordingStatus::default);
+    let mut recordings: Signal<Vec<RecordingInfo>> = use_signal(Vec::new);
+    let mut speed = use_signal(|| 1);
+    let _update_routine = use_coroutine(move |_rx: UnboundedReceiver<UpdateMessage>| async move {
+        loop {
+            match get_recording_status().await {
+                Ok(new_status) => status.set(new_status),
+                Err(err) => warn!("Could not get recording status! {err}"),
+            };
+            match get_recordings().await {
+                Ok(new_recordings) => recordings.set(new_recordings),
+                Err(err) => warn!("Could not list recordings! {err}"),
+            };
+            let timeout_ms = 1000;
+            TimeoutFuture::new(timeout_ms).await;
+        }
+    });
+
+    let status_read = status.read();
+    let recording_element = match status_read.recording.as_ref() {
+        Some(name) => rsx!(
+            label { class: "basis-1/3",
+                {i18n.t_with("recordings.recording", &[("name", name.clone())])}
+            }
+            div { class: "basis-1/3" }
+            div { class: "basis-1/3",
+                button {
+                    onclick: move |_event| async move {
+                        if let Err(err) = stop_recording().await {
+                            warn!("Could not stop recording! {err}");
+                        }
+                    },
+                    {i18n.t("recordings.stop_recording")}
+                }
+            }
+        ),
+        None => rsx!(
+            label { class: "basis-1/3", {i18n.t("recordings.not_recording")} }
+            div { class: "basis-1/3" }
+            div { class: "basis-1/3",
+                button {
+                    onclick: move |_event| async move {
+                        if let Err(err) = start_recording().await {
+                            warn!("Could not start recording! {err}");
+                        }
+                    },
+                    {i18n.t("recordings.record")}
+                }
+            }
+        ),
+    };
+    let replay_state = match status_read.replay_finished {
+        true => i18n.t("recordings.finished"),
+        false => i18n.t("recordings.running"),
+    };
+    let replay_element = match status_read.replaying.as_ref() {
+        Some(name) => rsx!(
+            div { class: "flex flex-row w-full",
+                label { class: "basis-1/3",
+                    {i18n.t_with("recordings.replaying", &[("name", name.clone())])}
+                }
+                div { class: "basis-1/3", "{replay_state}" }
+                div { class: "basis-1/3",
+                    button {
+                        onclick: move |_event| async move {
+                            if let Err(err) = stop_replay().await {
+                                warn!("Could not stop replay! {err}");
+                            }
+                        },
+                        {i18n.t("recordings.stop_replay")}
+                    }
+                }
+            }
+        ),
+        None => rsx!(),
+    };
+
+    rsx! {
+        div { id: "recordings", class: "flex flex-col w-full",
+            header { {i18n.t("recordings.title")} }
+            div { class: "flex flex-row w-full", {recording_element} }
+            {replay_element}
+            div { class: "flex flex-row w-full",
+                label { class: "basis-1/3", {i18n.t("recordings.speed")} }
+                div { class: "basis-1/3" }
+                div { class: "basis-1/3",
+                    select {
+                        onchange: move |event| speed.set(event.value().parse().unwrap_or(1)),
+                        for replay_speed in REPLAY_SPEEDS {
+                            option { value: "{replay_speed}", "{replay_speed}x" }
+                        }
+                    }
+                }
+            }
+            for recording in recordings.read().iter() {
+                RecordingRow {
+                    key: "{recording.name}",
+                    recording: recording.clone(),
+                    speed,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+async fn get_state_machine_option() -> Result<Arc<RwLock<Option<OscStateMachine>>>, ServerFnError>
+{
+    let FromContext(state_machine_option): FromContext<Arc<RwLock<Option<OscStateMachine>>>> =
+        extract().await?;
+    Ok(state_machine_option)
+}
+
+/// Get the current recording and replay status
+#[server]
+async fn get_recording_status() -> Result<RecordingStatus, ServerFnError> {
+    let _timer = ServerFnTimer::new("get_recording_status");
+    let state_machine_option = get_state_machine_option().await?;
+    let state_machine_option = state_machine_option.read().await;
+    match state_machine_option.as_ref() {
+        Some(state_machine) => Ok(state_machine.get_recording_status().await),
+        None => Ok(RecordingStatus::default()),
+    }
+}
+
+/// List stored recordings
+#[server]
+async fn get_recordings() -> Result<Vec<RecordingInfo>, ServerFnError> {
+    let _timer = ServerFnTimer::new("get_recordings");
+    match recording::list_recordings() {
+        Ok(recordings) => Ok(recordings),
+        Err(err) => Err(ServerFnError::ServerError(err.to_string())),
+    }
+}
+
+#[server(StartRecording)]
+async fn start_recording() -> Result<String, ServerFnError> {
+    let _timer = ServerFnTimer::new("start_recording");
+    let name = chrono::Local::now()
+        .format("rehearsal-%Y%m%d-%H%M%S")
+        .to_string();
+    let state_machine_option = get_state_machine_option().await?;
+    let state_machine_option = state_machine_option.read().await;
+    match state_machine_option.as_ref() {
+        Some(state_machine) => match state_machine.start_recording(&name).await {
+            Ok(_) => Ok(name),
+            Err(err) => Err(ServerFnError::ServerError(err.to_string())),
+        },
+        None => Err(ServerFnError::ServerError(
+            "OSC state machine is None!".to_string(),
+        )),
+    }
+}
+
+#[server(StopRecording)]
+async fn stop_recording() -> Result<Option<String>, ServerFnError> {
+    let _timer = ServerFnTimer::new("stop_recording");
+    let state_machine_option = get_state_machine_option().await?;
+    let state_machine_option = state_machine_option.read().await;
+    match state_machine_option.as_ref() {
+        Some(state_machine) => match state_machine.stop_recording().await {
+            Ok(name) => Ok(name),
+            Err(err) => Err(ServerFnError::ServerError(err.to_string())),
+        },
+        None => Err(ServerFnError::ServerError(
+            "OSC state machine is None!".to_string(),
+        )),
+    }
+}
+
+#[server(StartReplay)]
+async fn start_replay(name: String, speed: f64) -> Result<(), ServerFnError> {
+    let _timer = ServerFnTimer::new("start_replay");
+    let state_machine_option = get_state_machine_option().await?;
+    let mut writable_state_option = state_machine_option.write().await;
+    let state_machine = writable_state_option.take().unwrap_or_default();
+    let (state_machine, result) = match state_machine.start_replay(&name, speed).await {
+        Ok(replaying_state_machine) => (replaying_state_machine, Ok(())),
+        Err((state_machine, err)) => (
+            state_machine,
+            Err(ServerFnError::ServerError(err.to_string())),
+        ),
+    };
+    *writable_state_option = Some(state_machine);
+    result
+}
+
+#[server(StopReplay)]
+async fn stop_replay() -> Result<(), ServerFnError> {
+    let _timer = ServerFnTimer::new("stop_replay");
+    let state_machine_option = get_state_machine_option().await?;
+    let mut writable_state_option = state_machine_option.write().await;
+    *writable_state_option = match writable_state_option.take() {
+        Some(state_machine) => Some(state_machine.disconnect().await),
+        None => Some(OscStateMachine::new()),
+    };
+    Ok(())
+}
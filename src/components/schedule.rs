@@ -0,0 +1,264 @@
+use dioxus::{
+    logger::tracing::{debug, error, warn},
+    prelude::*,
+};
+use gloo_timers::future::TimeoutFuture;
+
+#[cfg(feature = "server")]
+use std::sync::Arc;
+
+#[cfg(feature = "server")]
+use tokio::sync::RwLock;
+
+#[cfg(feature = "server")]
+use crate::backend::metrics::ServerFnTimer;
+
+use crate::backend::scheduler::{
+    ExecutedAction, Recurrence, Schedule, ScheduleEntry, ScheduledAction,
+};
+use crate::components::TextInput;
+use crate::i18n::{use_i18n, I18n};
+
+enum UpdateMessage {}
+
+/// Format of `datetime-local` inputs.
+const DATETIME_INPUT_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+fn get_action(kind: &str, argument: &str) -> Option<ScheduledAction> {
+    let argument = argument.trim();
+    match kind {
+        "play" => Some(ScheduledAction::Play),
+        "stop" => Some(ScheduledAction::Stop),
+        "goto" if !argument.is_empty() => Some(ScheduledAction::GotoSong(argument.to_string())),
+        "osc" => {
+            let mut words = argument.split_whitespace();
+            let address = words.next()?.to_string();
+            Some(ScheduledAction::Osc {
+                address,
+                args: words.map(str::to_string).collect(),
+            })
+        }
+        _ => None,
+    }
+}
+
+fn get_recurrence(value: &str) -> Recurrence {
+    match value {
+        "daily" => Recurrence::Daily,
+        "weekly" => Recurrence::Weekly,
+        _ => Recurrence::Once,
+    }
+}
+
+fn get_recurrence_text(i18n: &I18n, recurrence: Recurrence) -> String {
+    match recurrence {
+        Recurrence::Once => i18n.t("schedule.once"),
+        Recurrence::Daily => i18n.t("schedule.daily"),
+        Recurrence::Weekly => i18n.t("schedule.weekly"),
+    }
+}
+
+#[component]
+fn ScheduleRow(entry: ScheduleEntry) -> Element {
+    let i18n = use_i18n();
+    let next_run = i18n.datetime(entry.next_run);
+    let recurrence = get_recurrence_text(&i18n, entry.recurrence);
+    let action = entry.action.describe();
+    rsx!(
+        tr {
+            td { "{next_run}" }
+            td { "{recurrence}" }
+            td { "{action}" }
+            td {
+                button {
+                    onclick: move |_event| async move {
+                        if let Err(err) = remove_scheduled_action(entry.id).await {
+                            warn!("Could not remove scheduled action! {err}");
+                        }
+                    },
+                    {i18n.t("schedule.remove")}
+                }
+            }
+        }
+    )
+}
+
+#[component]
+fn ExecutedRow(executed: ExecutedAction) -> Element {
+    let i18n = use_i18n();
+    let scheduled_for = i18n.datetime(executed.scheduled_for);
+    let executed_at = i18n.datetime(executed.executed_at);
+    let action = executed.action.describe();
+    let (class, result) = match executed.error.as_ref() {
+        Some(error) => ("schedule-failed", error.clone()),
+        None => ("", i18n.t("schedule.ok")),
+    };
+    rsx!(
+        tr { class,
+            td { "{scheduled_for}" }
+            td { "{executed_at}" }
+            td { "{action}" }
+            td { "{result}" }
+        }
+    )
+}
+
+/// Schedule component that lists and adds actions run at wall-clock times.
+#[component]
+pub fn ScheduleComponent() -> Element {
+    let i18n = use_i18n();
+    let mut schedule: Signal<Schedule> = use_signal(Schedule::default);
+    let mut at_edit = use_signal(|| {
+        chrono::Local::now()
+            .format(DATETIME_INPUT_FORMAT)
+            .to_string()
+    });
+    let mut recurrence_edit = use_signal(|| Recurrence::Once);
+    let mut kind_edit = use_signal(|| "play".to_string());
+    let argument_edit = use_signal(String::new);
+    let _update_routine = use_coroutine(move |_rx: UnboundedReceiver<UpdateMessage>| async move {
+        loop {
+            match get_schedule().await {
+                Ok(new_schedule) => schedule.set(new_schedule),
+                Err(err) => warn!("Could not get schedule! {err}"),
+            };
+            let timeout_ms = 1000;
+            TimeoutFuture::new(timeout_ms).await;
+        }
+    });
+    let action = use_memo(move || get_action(&kind_edit.read(), &argument_edit.read()));
+    let argument_hint = match kind_edit.read().as_str() {
+        "goto" => i18n.t("schedule.hint_song"),
+        "osc" => i18n.t("schedule.hint_osc"),
+        _ => i18n.t("schedule.hint_none"),
+    };
+
+    rsx! {
+        div { id: "schedule", class: "flex flex-col w-full",
+            header { {i18n.t("schedule.title")} }
+            div { class: "flex flex-row w-full",
+                div { class: "basis-1/4",
+                    input {
+                        r#type: "datetime-local",
+                        value: "{at_edit}",
+                        oninput: move |event| at_edit.set(event.value()),
+                    }
+                }
+                div { class: "basis-1/4",
+                    select {
+                        onchange: move |event| recurrence_edit.set(get_recurrence(&event.value())),
+                        option { value: "once", {i18n.t("schedule.once")} }
+                        option { value: "daily", {i18n.t("schedule.daily")} }
+                        option { value: "weekly", {i18n.t("schedule.weekly")} }
+                    }
+                    select { onchange: move |event| kind_edit.set(event.value()),
+                        option { value: "play", {i18n.t("schedule.play")} }
+                        option { value: "stop", {i18n.t("schedule.stop")} }
+                        option { value: "goto", {i18n.t("schedule.goto")} }
+                        option { value: "osc", {i18n.t("schedule.osc")} }
+                    }
+                }
+                div { class: "basis-1/4",
+                    label { "{argument_hint}" }
+                    TextInput { value: argument_edit, default_value: "" }
+                }
+                div { class: "basis-1/4",
+                    button {
+                        disabled: action().is_none(),
+                        onclick: move |_event| async move {
+                            let action = match action() {
+                                Some(action) => action,
+                                None => return,
+                            };
+                            let at = at_edit.peek().clone();
+                            let recurrence = *recurrence_edit.peek();
+                            debug!("Scheduling {action:?} at {at}");
+                            if let Err(err) = add_scheduled_action(at, recurrence, action).await {
+                                error!("Could not schedule action! {err}");
+                            }
+                        },
+                        {i18n.t("schedule.add")}
+                    }
+                }
+            }
+            header { {i18n.t("schedule.upcoming")} }
+            table { class: "schedule",
+                thead {
+                    tr {
+                        th { {i18n.t("schedule.time")} }
+                        th { {i18n.t("schedule.repeat")} }
+                        th { {i18n.t("schedule.action")} }
+                        th {}
+                    }
+                }
+                tbody {
+                    for entry in schedule.read().entries.iter() {
+                        ScheduleRow { key: "{entry.id}", entry: entry.clone() }
+                    }
+                }
+            }
+            header { {i18n.t("schedule.executed")} }
+            table { class: "schedule",
+                thead {
+                    tr {
+                        th { {i18n.t("schedule.scheduled_for")} }
+                        th { {i18n.t("schedule.executed_at")} }
+                        th { {i18n.t("schedule.action")} }
+                        th { {i18n.t("schedule.result")} }
+                    }
+                }
+                tbody {
+                    for executed in schedule.read().executed.iter() {
+                        ExecutedRow { executed: executed.clone() }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Get scheduled and executed actions
+#[server]
+async fn get_schedule() -> Result<Schedule, ServerFnError> {
+    let _timer = ServerFnTimer::new("get_schedule");
+    let FromContext(schedule): FromContext<Arc<RwLock<Schedule>>> = extract().await?;
+    let schedule = schedule.read().await;
+    Ok(schedule.clone())
+}
+
+#[cfg(feature = "server")]
+fn save_schedule(schedule: &Schedule) -> Result<(), ServerFnError> {
+    match schedule.write_schedule() {
+        Ok(_) => Ok(()),
+        Err(err) => Err(ServerFnError::ServerError(err.to_string())),
+    }
+}
+
+#[server(AddScheduledAction)]
+async fn add_scheduled_action(
+    at: String,
+    recurrence: Recurrence,
+    action: ScheduledAction,
+) -> Result<u64, ServerFnError> {
+    let _timer = ServerFnTimer::new("add_scheduled_action");
+    let next_run = match chrono::NaiveDateTime::parse_from_str(&at, DATETIME_INPUT_FORMAT) {
+        Ok(next_run) => next_run,
+        Err(err) => return Err(ServerFnError::Args(err.to_string())),
+    };
+    let FromContext(schedule): FromContext<Arc<RwLock<Schedule>>> = extract().await?;
+    let mut schedule = schedule.write().await;
+    let id = schedule.add(next_run, recurrence, action);
+    save_schedule(&schedule)?;
+    Ok(id)
+}
+
+#[server(RemoveScheduledAction)]
+async fn remove_scheduled_action(id: u64) -> Result<(), ServerFnError> {
+    let _timer = ServerFnTimer::new("remove_scheduled_action");
+    let FromContext(schedule): FromContext<Arc<RwLock<Schedule>>> = extract().await?;
+    let mut schedule = schedule.write().await;
+    if schedule.remove(id) {
+        save_schedule(&schedule)?;
+    }
+    Ok(())
+}
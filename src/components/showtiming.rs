@@ -0,0 +1,67 @@
+use dioxus::prelude::*;
+
+use crate::{
+    backend::{durations::format_duration, showtiming::ShowTiming},
+    i18n::use_i18n,
+};
+
+/// Total setlist length, projected end of the show and curfew warnings.
+#[component]
+pub fn ShowTimingComponent(timing: ShowTiming) -> Element {
+    let i18n = use_i18n();
+    let total = format_duration(timing.total_ms);
+    let total = i18n.t_with("showtiming.total", &[("duration", total)]);
+    let unknown = match timing.unknown_songs {
+        0 => String::new(),
+        1 => i18n.t("showtiming.unknown_one"),
+        count => i18n.t_with("showtiming.unknown_many", &[("count", count.to_string())]),
+    };
+    let remaining = i18n.t_with(
+        "showtiming.remaining",
+        &[
+            ("duration", format_duration(timing.remaining_ms)),
+            ("unknown", unknown),
+        ],
+    );
+    let projected_end = i18n.t_with(
+        "showtiming.ends_at",
+        &[("time", i18n.time(timing.projected_end.time()))],
+    );
+    let curfew_element = match timing.curfew {
+        Some(curfew) if timing.over_curfew_ms > 0 => {
+            let warning = i18n.t_with(
+                "showtiming.over_curfew",
+                &[
+                    ("over", format_duration(timing.over_curfew_ms)),
+                    ("time", i18n.time(curfew.time())),
+                ],
+            );
+            let drops = timing.suggested_drops.join(", ");
+            let drops = i18n.t_with("showtiming.drops", &[("songs", drops)]);
+            rsx!(
+                div { class: "curfew-warning",
+                    "{warning}"
+                    if !timing.suggested_drops.is_empty() {
+                        div { "{drops}" }
+                    }
+                }
+            )
+        }
+        Some(curfew) => {
+            let curfew = i18n.t_with("showtiming.curfew", &[("time", i18n.time(curfew.time()))]);
+            rsx!(
+                div { class: "basis-1/4", "{curfew}" }
+            )
+        }
+        None => rsx!(),
+    };
+
+    rsx!(
+        div { class: "show-timing flex flex-row",
+            div { class: "basis-1/4", "{total}" }
+            div { class: "basis-1/4", "{remaining}" }
+            div { class: "basis-1/4", "{projected_end}" }
+            {curfew_element}
+        }
+    )
+}
@@ -0,0 +1,169 @@
+use dioxus::{logger::tracing::debug, prelude::*};
+use gloo_timers::future::TimeoutFuture;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use std::sync::Arc;
+
+#[cfg(feature = "server")]
+use tokio::sync::RwLock;
+
+#[cfg(feature = "server")]
+use crate::backend::{
+    command::playlist_entry_matches,
+    durations::{parse_elapsed, SongDurations},
+    metrics::ServerFnTimer,
+    notes::SongNotes,
+    supervisor::Snapshots,
+};
+
+use crate::{
+    backend::durations::format_duration,
+    components::restore_stored_theme,
+    i18n::use_i18n,
+};
+
+/// The stage display follows mtrack more closely than the remote.
+const STAGE_POLL_INTERVAL_MS: u32 = 250;
+
+/// Requests fullscreen right away and on the first touch, since browsers may require a gesture,
+/// and keeps the screen awake while the page is visible.
+const STAGE_SCRIPT: &str = r#"
+const requestFullscreen = () => {
+    if (!document.fullscreenElement && document.documentElement.requestFullscreen) {
+        document.documentElement.requestFullscreen().catch(() => {});
+    }
+};
+const requestWakeLock = () => {
+    if ("wakeLock" in navigator && document.visibilityState === "visible") {
+        navigator.wakeLock.request("screen").catch(() => {});
+    }
+};
+requestFullscreen();
+requestWakeLock();
+document.addEventListener("pointerdown", requestFullscreen, { once: true });
+document.addEventListener("visibilitychange", requestWakeLock);
+"#;
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct StageState {
+    is_connected: bool,
+    is_playing: bool,
+    song: String,
+    next_song: Option<String>,
+    time_elapsed: String,
+    /// Learned duration of the current song minus the elapsed time.
+    remaining_ms: Option<u64>,
+    notes: Option<String>,
+}
+
+/// Read-only stage display for performers with the current song in huge type.
+#[component]
+pub fn StageDisplay() -> Element {
+    let i18n = use_i18n();
+    let mut stage_state: Signal<Option<StageState>> = use_signal(|| None);
+    let mut is_reachable = use_signal(|| true);
+    use_effect(|| {
+        document::eval(STAGE_SCRIPT);
+    });
+    use_future(move || async move {
+        restore_stored_theme().await;
+    });
+    use_future(move || async move {
+        loop {
+            match get_stage_state().await {
+                Ok(state) => {
+                    stage_state.set(Some(state));
+                    is_reachable.set(true);
+                }
+                Err(err) => {
+                    debug!("Could not get stage state! {err}");
+                    is_reachable.set(false);
+                }
+            }
+            TimeoutFuture::new(STAGE_POLL_INTERVAL_MS).await;
+        }
+    });
+
+    let state = match stage_state.read().as_ref() {
+        Some(state) => state.clone(),
+        None => {
+            return rsx!(
+                div { class: "stage stage-stopped",
+                    div { class: "stage-song", {i18n.t("stage.waiting")} }
+                }
+            )
+        }
+    };
+    let status_class = match (*is_reachable.read() && state.is_connected, state.is_playing) {
+        (false, _) => "stage-offline",
+        (true, true) => "stage-playing",
+        (true, false) => "stage-stopped",
+    };
+    let status_text = match (*is_reachable.read(), state.is_connected, state.is_playing) {
+        (false, _, _) => i18n.t("stage.server_unreachable"),
+        (true, false, _) => i18n.t("stage.mtrack_unreachable"),
+        (true, true, true) => i18n.t("stage.playing"),
+        (true, true, false) => i18n.t("stage.stopped"),
+    };
+    let remaining = match state.remaining_ms {
+        Some(remaining_ms) => format!("-{}", format_duration(remaining_ms)),
+        None => String::new(),
+    };
+    let next_song = match state.next_song {
+        Some(song) => i18n.t_with("stage.next", &[("song", song)]),
+        None => String::new(),
+    };
+    let notes = state.notes.unwrap_or_default();
+
+    rsx! {
+        div { class: "stage {status_class}",
+            div { class: "stage-status", "{status_text}" }
+            div { class: "stage-song", "{state.song}" }
+            div { class: "stage-time flex flex-row",
+                div { class: "basis-1/2", "{state.time_elapsed}" }
+                div { class: "basis-1/2", "{remaining}" }
+            }
+            div { class: "stage-next", "{next_song}" }
+            div { class: "stage-notes", "{notes}" }
+        }
+    }
+}
+
+/// Get what the stage display shows
+#[server]
+async fn get_stage_state() -> Result<StageState, ServerFnError> {
+    let _timer = ServerFnTimer::new("get_stage_state");
+    let FromContext(snapshots): FromContext<Arc<Snapshots>> = extract().await?;
+    let mtrack_state = match snapshots.load().mtrack_state.clone() {
+        Some(mtrack_state) => mtrack_state,
+        None => return Ok(StageState::default()),
+    };
+
+    let FromContext(durations): FromContext<Arc<RwLock<SongDurations>>> = extract().await?;
+    let FromContext(notes): FromContext<Arc<RwLock<SongNotes>>> = extract().await?;
+    let remaining_ms = match (
+        durations.read().await.get(&mtrack_state.song),
+        parse_elapsed(&mtrack_state.time_elapsed),
+    ) {
+        (Some(duration_ms), Some(elapsed_ms)) => Some(duration_ms.saturating_sub(elapsed_ms)),
+        _ => None,
+    };
+    let next_song = mtrack_state
+        .setlist
+        .iter()
+        .position(|entry| playlist_entry_matches(entry, &mtrack_state.song))
+        .and_then(|index| mtrack_state.setlist.get(index + 1))
+        .cloned();
+    let notes = notes.read().await.get(&mtrack_state.song);
+
+    Ok(StageState {
+        is_connected: true,
+        is_playing: mtrack_state.is_playing,
+        song: mtrack_state.song,
+        next_song,
+        time_elapsed: mtrack_state.time_elapsed,
+        remaining_ms,
+        notes,
+    })
+}
@@ -0,0 +1,214 @@
+use dioxus::{
+    logger::tracing::{debug, error, warn},
+    prelude::*,
+};
+
+#[cfg(feature = "server")]
+use crate::backend::{metrics::ServerFnTimer, themes};
+
+use crate::backend::themes::{stored_theme_script, Theme, BUILTIN_THEMES, MAX_THEME_SIZE};
+use crate::components::TextInput;
+use crate::i18n::use_i18n;
+
+fn apply_theme(theme: &Theme) {
+    debug!("Applying theme {}", theme.to_key());
+    document::eval(&theme.apply_script());
+}
+
+/// Applies the theme remembered by this client, if any.
+pub async fn restore_stored_theme() -> Option<Theme> {
+    let stored_theme = match document::eval(&stored_theme_script())
+        .join::<Option<String>>()
+        .await
+    {
+        Ok(Some(key)) => Theme::from_key(&key),
+        Ok(None) => None,
+        Err(err) => {
+            warn!("Could not read stored theme! {err:?}");
+            None
+        }
+    };
+    if let Some(stored_theme) = stored_theme.as_ref() {
+        apply_theme(stored_theme);
+    }
+    stored_theme
+}
+
+/// Theme selector that switches the theme of this client and remembers it.
+#[component]
+pub fn ThemeSelector() -> Element {
+    let i18n = use_i18n();
+    let mut theme = use_signal(Theme::default);
+    let custom_themes = use_resource(get_custom_themes);
+    use_future(move || async move {
+        if let Some(stored_theme) = restore_stored_theme().await {
+            theme.set(stored_theme);
+        }
+    });
+
+    let custom_theme_names = match custom_themes.read().as_ref() {
+        Some(Ok(names)) => names.clone(),
+        Some(Err(err)) => {
+            warn!("Could not list custom themes! {err}");
+            Vec::new()
+        }
+        None => Vec::new(),
+    };
+    let selected_key = theme.read().to_key();
+
+    rsx!(
+        select {
+            class: "navbar-select",
+            onchange: move |event| match Theme::from_key(&event.value()) {
+                Some(new_theme) => {
+                    apply_theme(&new_theme);
+                    theme.set(new_theme);
+                }
+                None => warn!("Unknown theme {}", event.value()),
+            },
+            for name in BUILTIN_THEMES {
+                option {
+                    value: Theme::Builtin(name.to_string()).to_key(),
+                    selected: Theme::Builtin(name.to_string()).to_key() == selected_key,
+                    {i18n.t(&format!("themes.builtin.{name}"))}
+                }
+            }
+            for name in custom_theme_names {
+                option {
+                    value: Theme::Custom(name.clone()).to_key(),
+                    selected: Theme::Custom(name.clone()).to_key() == selected_key,
+                    {i18n.t_with("themes.custom", &[("name", name.clone())])}
+                }
+            }
+        }
+    )
+}
+
+/// Themes component to upload and delete the custom themes hosted by the server.
+#[component]
+pub fn ThemesConfig() -> Element {
+    let i18n = use_i18n();
+    let mut custom_themes = use_resource(get_custom_themes);
+    let name_edit = use_signal(String::new);
+    let mut css: Signal<Option<String>> = use_signal(|| None);
+
+    let custom_theme_names = match custom_themes.read().as_ref() {
+        Some(Ok(names)) => names.clone(),
+        Some(Err(err)) => {
+            warn!("Could not list custom themes! {err}");
+            Vec::new()
+        }
+        None => Vec::new(),
+    };
+    let is_incomplete = name_edit.read().trim().is_empty() || css.read().is_none();
+
+    rsx! {
+        div { id: "themes", class: "flex flex-col w-full",
+            header { {i18n.t("themes.title")} }
+            for name in custom_theme_names {
+                div { key: "{name}", class: "flex flex-row w-full",
+                    label { class: "basis-1/3", "{name}" }
+                    div { class: "basis-1/3" }
+                    div { class: "basis-1/3",
+                        button {
+                            onclick: {
+                                let name = name.clone();
+                                move |_event| {
+                                    let name = name.clone();
+                                    async move {
+                                        match delete_theme(name).await {
+                                            Ok(_) => custom_themes.restart(),
+                                            Err(err) => error!("Could not delete theme! {err}"),
+                                        };
+                                    }
+                                }
+                            },
+                            {i18n.t("common.delete")}
+                        }
+                    }
+                }
+            }
+            div { class: "flex flex-row w-full",
+                label { class: "basis-1/3", {i18n.t("themes.name")} }
+                div { class: "basis-1/3" }
+                div { class: "basis-1/3",
+                    TextInput { value: name_edit, default_value: "" }
+                }
+            }
+            div { class: "flex flex-row w-full",
+                label { class: "basis-1/3", {i18n.t("themes.file")} }
+                div { class: "basis-1/3" }
+                div { class: "basis-1/3",
+                    input {
+                        r#type: "file",
+                        accept: ".css,text/css",
+                        onchange: move |event| async move {
+                            let Some(file_engine) = event.files() else {
+                                return;
+                            };
+                            let content = match file_engine.files().first() {
+                                Some(file) => file_engine.read_file_to_string(file).await,
+                                None => None,
+                            };
+                            match content {
+                                Some(content) if content.len() > MAX_THEME_SIZE => {
+                                    error!("Theme is larger than {MAX_THEME_SIZE} bytes!");
+                                    css.set(None);
+                                }
+                                content => css.set(content),
+                            };
+                        },
+                    }
+                }
+            }
+            div { class: "flex flex-row w-full",
+                button {
+                    class: "w-full",
+                    disabled: is_incomplete,
+                    onclick: move |_event| async move {
+                        let name = name_edit.peek().trim().to_string();
+                        let Some(content) = css.peek().clone() else {
+                            return;
+                        };
+                        match upload_theme(name, content).await {
+                            Ok(_) => custom_themes.restart(),
+                            Err(err) => error!("Could not upload theme! {err}"),
+                        };
+                    },
+                    {i18n.t("themes.upload")}
+                }
+            }
+        }
+    }
+}
+
+/// List the custom themes hosted by the server
+#[server]
+async fn get_custom_themes() -> Result<Vec<String>, ServerFnError> {
+    let _timer = ServerFnTimer::new("get_custom_themes");
+    match themes::list_custom_themes() {
+        Ok(names) => Ok(names),
+        Err(err) => Err(ServerFnError::ServerError(err.to_string())),
+    }
+}
+
+#[server(UploadTheme)]
+async fn upload_theme(name: String, css: String) -> Result<(), ServerFnError> {
+    let _timer = ServerFnTimer::new("upload_theme");
+    match themes::write_custom_theme(&name, &css) {
+        Ok(_) => {
+            warn!("Uploaded custom theme {name}");
+            Ok(())
+        }
+        Err(err) => Err(ServerFnError::ServerError(err.to_string())),
+    }
+}
+
+#[server(DeleteTheme)]
+async fn delete_theme(name: String) -> Result<(), ServerFnError> {
+    let _timer = ServerFnTimer::new("delete_theme");
+    match themes::delete_custom_theme(&name) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(ServerFnError::ServerError(err.to_string())),
+    }
+}
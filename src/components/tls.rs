@@ -0,0 +1,51 @@
+use dioxus::{logger::tracing::error, prelude::*};
+
+#[cfg(feature = "server")]
+use crate::backend::metrics::ServerFnTimer;
+
+use crate::{backend::tls::TlsInfo, i18n::use_i18n};
+
+/// HTTPS component showing the certificate fingerprint to check the browser's warning against.
+#[component]
+pub fn TlsStatus() -> Element {
+    let i18n = use_i18n();
+    let tls_info = use_resource(get_tls_info);
+
+    let tls_info = match tls_info.read().as_ref() {
+        Some(Ok(tls_info)) => tls_info.clone(),
+        Some(Err(err)) => {
+            error!("Could not get TLS info! {err}");
+            TlsInfo::default()
+        }
+        None => TlsInfo::default(),
+    };
+    let status = match (tls_info.enabled, tls_info.self_signed) {
+        (false, _) => i18n.t("tls.disabled"),
+        (true, true) => i18n.t("tls.self_signed"),
+        (true, false) => i18n.t("tls.enabled"),
+    };
+
+    rsx! {
+        div { id: "tls", class: "flex flex-col w-full",
+            header { {i18n.t("tls.title")} }
+            div { class: "flex flex-row w-full",
+                label { class: "basis-1/3", {i18n.t("tls.status")} }
+                div { class: "basis-2/3", "{status}" }
+            }
+            if let Some(fingerprint) = tls_info.fingerprint {
+                div { class: "flex flex-row w-full",
+                    label { class: "basis-1/3", {i18n.t("tls.fingerprint")} }
+                    code { class: "basis-2/3 fingerprint", "{fingerprint}" }
+                }
+            }
+        }
+    }
+}
+
+/// Get whether HTTPS is served and the fingerprint of its certificate
+#[server]
+async fn get_tls_info() -> Result<TlsInfo, ServerFnError> {
+    let _timer = ServerFnTimer::new("get_tls_info");
+    let FromContext(tls_info): FromContext<TlsInfo> = extract().await?;
+    Ok(tls_info)
+}
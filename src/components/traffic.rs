@@ -0,0 +1,159 @@
+use dioxus::{
+    logger::tracing::{debug, warn},
+    prelude::*,
+};
+use gloo_timers::future::TimeoutFuture;
+
+#[cfg(feature = "server")]
+use std::sync::Arc;
+
+#[cfg(feature = "server")]
+use tokio::sync::RwLock;
+
+#[cfg(feature = "server")]
+use crate::backend::{metrics::ServerFnTimer, server::OscStateMachine};
+
+use crate::backend::traffic::{TrafficDirection, TrafficEntry, TRAFFIC_LOG_CAPACITY};
+use crate::components::TextInput;
+use crate::i18n::use_i18n;
+
+enum UpdateMessage {}
+
+fn matches_filter(entry: &TrafficEntry, address_filter: &str) -> bool {
+    let mut filters = address_filter
+        .split(',')
+        .map(str::trim)
+        .filter(|filter| !filter.is_empty())
+        .peekable();
+    filters.peek().is_none() || filters.any(|filter| entry.address.contains(filter))
+}
+
+#[component]
+fn TrafficRow(entry: TrafficEntry) -> Element {
+    let i18n = use_i18n();
+    let (class, direction) = match entry.direction {
+        TrafficDirection::Inbound => ("traffic-inbound", i18n.t("traffic.inbound")),
+        TrafficDirection::Outbound => ("traffic-outbound", i18n.t("traffic.outbound")),
+        TrafficDirection::Rejected => ("traffic-rejected", i18n.t("traffic.rejected")),
+    };
+    let time = entry
+        .timestamp
+        .with_timezone(&chrono::Local)
+        .format("%H:%M:%S%.3f")
+        .to_string();
+    let args = entry
+        .args
+        .iter()
+        .map(|arg| format!("{}:{}", arg.type_tag, arg.value))
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    rsx!(
+        tr { class,
+            td { "{time}" }
+            td { "{direction}" }
+            td { "{entry.address}" }
+            td { "{args}" }
+            td { "{entry.peer}" }
+        }
+    )
+}
+
+/// Traffic component that shows the OSC messages passing through the server.
+#[component]
+pub fn Traffic() -> Element {
+    let i18n = use_i18n();
+    let mut entries: Signal<Vec<TrafficEntry>> = use_signal(Vec::new);
+    let mut is_paused = use_signal(|| false);
+    let address_filter = use_signal(String::new);
+    let _update_routine = use_coroutine(move |_rx: UnboundedReceiver<UpdateMessage>| async move {
+        debug!("Starting traffic coroutine");
+        let mut last_sequence = None;
+
+        loop {
+            if !*is_paused.peek() {
+                match get_traffic(last_sequence).await {
+                    Ok(new_entries) => {
+                        if let Some(last_entry) = new_entries.last() {
+                            last_sequence = Some(last_entry.sequence);
+                            let mut entries = entries.write();
+                            entries.extend(new_entries);
+                            let overflow = entries.len().saturating_sub(TRAFFIC_LOG_CAPACITY);
+                            entries.drain(..overflow);
+                        }
+                    }
+                    Err(err) => warn!("Could not get OSC traffic! {err}"),
+                };
+            }
+            let timeout_ms = 250;
+            TimeoutFuture::new(timeout_ms).await;
+        }
+    });
+
+    let filter = address_filter.read().clone();
+    let entries_read = entries.read();
+    let visible_entries = entries_read
+        .iter()
+        .rev()
+        .filter(|entry| matches_filter(entry, &filter));
+
+    rsx! {
+        div { id: "traffic", class: "flex flex-col w-full",
+            header { {i18n.t("traffic.title")} }
+            div { class: "flex flex-row w-full",
+                label { class: "basis-1/3", {i18n.t("traffic.filter")} }
+                div { class: "basis-1/3",
+                    TextInput { value: address_filter, default_value: "" }
+                }
+                div { class: "basis-1/3",
+                    button {
+                        onclick: move |_event| {
+                            let paused = *is_paused.peek();
+                            is_paused.set(!paused);
+                        },
+                        if *is_paused.read() {
+                            {i18n.t("traffic.resume")}
+                        } else {
+                            {i18n.t("traffic.pause")}
+                        }
+                    }
+                    button {
+                        onclick: move |_event| entries.write().clear(),
+                        {i18n.t("traffic.clear")}
+                    }
+                }
+            }
+            table { class: "traffic",
+                thead {
+                    tr {
+                        th { {i18n.t("traffic.time")} }
+                        th { {i18n.t("traffic.direction")} }
+                        th { {i18n.t("traffic.address")} }
+                        th { {i18n.t("traffic.args")} }
+                        th { {i18n.t("traffic.peer")} }
+                    }
+                }
+                tbody {
+                    for entry in visible_entries {
+                        TrafficRow { key: "{entry.sequence}", entry: entry.clone() }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Get OSC traffic newer than the given sequence number
+#[server]
+async fn get_traffic(since: Option<u64>) -> Result<Vec<TrafficEntry>, ServerFnError> {
+    let _timer = ServerFnTimer::new("get_traffic");
+    let FromContext(state_machine_option): FromContext<Arc<RwLock<Option<OscStateMachine>>>> =
+        extract().await?;
+    let state_machine_option = state_machine_option.read().await;
+    match state_machine_option.as_ref() {
+        Some(state_machine) => Ok(state_machine.traffic.read().await.entries_since(since)),
+        None => Err(ServerFnError::ServerError(
+            "OSC state machine is None!".to_string(),
+        )),
+    }
+}
@@ -4,9 +4,7 @@ use dioxus::{
 };
 use dioxus_free_icons::icons::ld_icons::{LdCirclePlay, LdSkipBack, LdSkipForward};
 use dioxus_free_icons::Icon;
-
-#[cfg(feature = "server")]
-use std::future::Future;
+use gloo_timers::future::TimeoutFuture;
 
 #[cfg(feature = "server")]
 use std::sync::Arc;
@@ -15,40 +13,113 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 
 #[cfg(feature = "server")]
-use crate::backend::server::{OscStateMachine, OscStateMachineError};
+use crate::backend::{
+    command::run_command, config::Config, metrics::ServerFnTimer, server::OscStateMachine,
+};
+
+use crate::{
+    backend::{
+        command::{CommandAck, CommandStatus, TransportCommand},
+        durations::{format_duration, parse_elapsed},
+    },
+    i18n::use_i18n,
+};
+
+/// Command of a transport button and what became of it.
+#[derive(Clone, Debug, PartialEq)]
+struct CommandState {
+    command: TransportCommand,
+    status: CommandStatus,
+    id: Option<u64>,
+}
+
+/// How long a confirmed or failed command stays visible.
+const COMMAND_STATUS_DISPLAY_MS: u32 = 3000;
+
+fn command_class(
+    command_state: &Signal<Option<CommandState>>,
+    command: TransportCommand,
+) -> &'static str {
+    match command_state.read().as_ref() {
+        Some(state) if state.command == command => match state.status {
+            CommandStatus::Pending | CommandStatus::ConfirmationRequired => "command-pending",
+            CommandStatus::Confirmed | CommandStatus::AlreadyInEffect => "command-confirmed",
+            CommandStatus::TimedOut | CommandStatus::Failed(_) => "command-failed",
+        },
+        _ => "",
+    }
+}
+
+async fn send_command(mut command_state: Signal<Option<CommandState>>, command: TransportCommand) {
+    debug!("{command:?}");
+    // Pressing stop again confirms this client's own stop, not one of another client.
+    let confirms = match command_state.peek().as_ref() {
+        Some(CommandState {
+            command: TransportCommand::Stop,
+            status: CommandStatus::ConfirmationRequired,
+            id,
+        }) if command == TransportCommand::Stop => *id,
+        _ => None,
+    };
+    command_state.set(Some(CommandState {
+        command,
+        status: CommandStatus::Pending,
+        id: None,
+    }));
+    let result = match command {
+        TransportCommand::Play => play().await,
+        TransportCommand::Stop => stop(confirms).await,
+        TransportCommand::Next => next().await,
+        TransportCommand::Prev => prev().await,
+    };
+    let final_state = match result {
+        Ok(ack) => CommandState {
+            command,
+            status: ack.status,
+            id: Some(ack.id),
+        },
+        Err(err) => {
+            warn!("Error requesting {}! {err}", command.name());
+            CommandState {
+                command,
+                status: CommandStatus::Failed(err.to_string()),
+                id: None,
+            }
+        }
+    };
+    command_state.set(Some(final_state.clone()));
+    TimeoutFuture::new(COMMAND_STATUS_DISPLAY_MS).await;
+    if command_state.peek().as_ref() == Some(&final_state) {
+        command_state.set(None);
+    }
+}
 
 #[component]
-fn Prev() -> Element {
+fn Prev(command_state: Signal<Option<CommandState>>, is_locked: bool) -> Element {
     let icon = rsx!(Icon {
         class: "center",
         width: 24,
         height: 24,
         icon: LdSkipBack,
     })?;
+    let command_class = command_class(&command_state, TransportCommand::Prev);
     rsx!(
         button {
-            class: "transport-button basis-1/4",
-            onclick: move |_event| async move {
-                debug!("Next");
-                let _next_result = prev().await;
-            },
+            class: "transport-button basis-1/4 {command_class}",
+            disabled: is_locked,
+            onclick: move |_event| send_command(command_state, TransportCommand::Prev),
             {icon}
         }
     )
 }
 
 #[component]
-fn Play() -> Element {
+fn Play(command_state: Signal<Option<CommandState>>) -> Element {
+    let command_class = command_class(&command_state, TransportCommand::Play);
     rsx!(
         button {
-            class: "play-button basis-1/4",
-            onclick: move |_event| async move {
-                debug!("Play");
-                match play().await {
-                    Ok(_r) => debug!("OK"),
-                    Err(err) => warn!("Error requesting play! {err}"),
-                };
-            },
+            class: "play-button basis-1/4 {command_class}",
+            onclick: move |_event| send_command(command_state, TransportCommand::Play),
             Icon {
                 class: "center",
                 width: 24,
@@ -60,28 +131,25 @@ fn Play() -> Element {
 }
 
 #[component]
-fn Stop() -> Element {
+fn Stop(command_state: Signal<Option<CommandState>>) -> Element {
+    let command_class = command_class(&command_state, TransportCommand::Stop);
     rsx!(
         button {
-            class: "stop-button basis-1/4",
-            onclick: move |_event| async move {
-                debug!("Stop");
-                let _stop_result = stop().await;
-            },
+            class: "stop-button basis-1/4 {command_class}",
+            onclick: move |_event| send_command(command_state, TransportCommand::Stop),
             "#"
         }
     )
 }
 
 #[component]
-fn Next() -> Element {
+fn Next(command_state: Signal<Option<CommandState>>, is_locked: bool) -> Element {
+    let command_class = command_class(&command_state, TransportCommand::Next);
     rsx!(
         button {
-            class: "transport-button basis-1/4",
-            onclick: move |_event| async move {
-                debug!("Next");
-                let _next_result = next().await;
-            },
+            class: "transport-button basis-1/4 {command_class}",
+            disabled: is_locked,
+            onclick: move |_event| send_command(command_state, TransportCommand::Next),
             Icon {
                 class: "center",
                 width: 24,
@@ -93,92 +161,109 @@ fn Next() -> Element {
 }
 
 #[component]
-pub fn Transport(is_playing: bool, elapsed: String) -> Element {
+pub fn Transport(
+    is_playing: bool,
+    elapsed: String,
+    duration_ms: Option<u64>,
+    show_lock: bool,
+) -> Element {
+    let i18n = use_i18n();
+    let command_state: Signal<Option<CommandState>> = use_signal(|| None);
+    let is_locked = show_lock && is_playing;
     let play_or_stop = match is_playing {
-        true => rsx!(Stop {}),
-        false => rsx!(Play {}),
+        true => rsx!(Stop { command_state }),
+        false => rsx!(Play { command_state }),
+    };
+    let status_text = match command_state.read().as_ref() {
+        Some(state) => {
+            let name = i18n.t(&format!("command.{}", state.command.name()));
+            let (key, error) = match &state.status {
+                CommandStatus::Pending => ("command.pending", String::new()),
+                CommandStatus::ConfirmationRequired => ("command.confirm_again", String::new()),
+                CommandStatus::Confirmed => ("command.confirmed", String::new()),
+                CommandStatus::AlreadyInEffect => ("command.already_in_effect", String::new()),
+                CommandStatus::TimedOut => ("command.timed_out", String::new()),
+                CommandStatus::Failed(err) => ("command.failed", err.clone()),
+            };
+            i18n.t_with(key, &[("command", name), ("error", error)])
+        }
+        None => String::new(),
+    };
+    let progress_element = match (duration_ms, parse_elapsed(&elapsed)) {
+        (Some(duration_ms), Some(elapsed_ms)) if duration_ms > 0 => {
+            let percent = elapsed_ms.min(duration_ms) * 100 / duration_ms;
+            let remaining = format_duration(duration_ms.saturating_sub(elapsed_ms));
+            let length = format_duration(duration_ms);
+            rsx!(
+                div { class: "flex flex-row",
+                    div { class: "progress w-full",
+                        div { class: "progress-bar", width: "{percent}%" }
+                    }
+                    div { class: "time_remaining basis-1/4", "-{remaining} / {length}" }
+                }
+            )
+        }
+        _ => rsx!(),
     };
     rsx!(
         div { class: "flex flex-row",
-            Prev {}
+            Prev { command_state, is_locked }
             {play_or_stop}
-            Next {}
+            Next { command_state, is_locked }
             div { class: "time_elapsed basis-1/4", {elapsed} }
         }
+        {progress_element}
+        div { class: "command-status", {status_text} }
     )
 }
 
 #[cfg(feature = "server")]
-async fn run_osc_command<'a, T>(
-    state_machine: &'a OscStateMachine,
-    osc_fun: impl FnOnce(&'a OscStateMachine) -> T,
-) -> Result<(), ServerFnError>
-where
-    T: Future<Output = Result<(), OscStateMachineError>>,
-{
-    if !state_machine.is_connected().await {
-        return Err(ServerFnError::ServerError(
-            "OSC state machine is not connected!".to_string(),
-        ));
-    }
-
-    match osc_fun(state_machine).await {
-        Ok(_result) => Ok(()),
-        Err(err) => Err(ServerFnError::Response(err.to_string())),
+async fn run_osc_command(
+    state_machine_option: Arc<RwLock<Option<OscStateMachine>>>,
+    config: Arc<RwLock<Config>>,
+    command: TransportCommand,
+    confirms: Option<u64>,
+) -> Result<CommandAck, ServerFnError> {
+    let show_lock = config.read().await.show_lock;
+    match run_command(&state_machine_option, show_lock, command, confirms).await {
+        Ok(ack) => Ok(ack),
+        Err(err) => Err(ServerFnError::ServerError(err.to_string())),
     }
 }
 
 #[server(StartPlayback)]
-async fn play() -> Result<(), ServerFnError> {
+async fn play() -> Result<CommandAck, ServerFnError> {
+    let _timer = ServerFnTimer::new("play");
     let FromContext(state_machine_option): FromContext<Arc<RwLock<Option<OscStateMachine>>>> =
         extract().await?;
-    let state_machine_option = state_machine_option.read().await;
-    match state_machine_option.as_ref() {
-        Some(state_machine) => run_osc_command(state_machine, OscStateMachine::play).await,
-        None => Err(ServerFnError::ServerError(
-            "OSC state machine is None!".to_string(),
-        )),
-    }
+    let FromContext(config): FromContext<Arc<RwLock<Config>>> = extract().await?;
+    run_osc_command(state_machine_option, config, TransportCommand::Play, None).await
 }
 
+/// Stops playback, `confirms` is the ID of this client's stop that needed confirmation.
 #[server(StopPlayback)]
-async fn stop() -> Result<(), ServerFnError> {
+async fn stop(confirms: Option<u64>) -> Result<CommandAck, ServerFnError> {
+    let _timer = ServerFnTimer::new("stop");
     let FromContext(state_machine_option): FromContext<Arc<RwLock<Option<OscStateMachine>>>> =
         extract().await?;
-    let state_machine_option = state_machine_option.read().await;
-
-    match state_machine_option.as_ref() {
-        Some(state_machine) => run_osc_command(state_machine, OscStateMachine::stop).await,
-        None => Err(ServerFnError::ServerError(
-            "OSC state machine is None!".to_string(),
-        )),
-    }
+    let FromContext(config): FromContext<Arc<RwLock<Config>>> = extract().await?;
+    run_osc_command(state_machine_option, config, TransportCommand::Stop, confirms).await
 }
 
 #[server(NextSong)]
-async fn next() -> Result<(), ServerFnError> {
+async fn next() -> Result<CommandAck, ServerFnError> {
+    let _timer = ServerFnTimer::new("next");
     let FromContext(state_machine_option): FromContext<Arc<RwLock<Option<OscStateMachine>>>> =
         extract().await?;
-    let state_machine_option = state_machine_option.read().await;
-
-    match state_machine_option.as_ref() {
-        Some(state_machine) => run_osc_command(state_machine, OscStateMachine::next).await,
-        None => Err(ServerFnError::ServerError(
-            "OSC state machine is None!".to_string(),
-        )),
-    }
+    let FromContext(config): FromContext<Arc<RwLock<Config>>> = extract().await?;
+    run_osc_command(state_machine_option, config, TransportCommand::Next, None).await
 }
 
 #[server(PrevSong)]
-async fn prev() -> Result<(), ServerFnError> {
+async fn prev() -> Result<CommandAck, ServerFnError> {
+    let _timer = ServerFnTimer::new("prev");
     let FromContext(state_machine_option): FromContext<Arc<RwLock<Option<OscStateMachine>>>> =
         extract().await?;
-    let state_machine_option = state_machine_option.read().await;
-
-    match state_machine_option.as_ref() {
-        Some(state_machine) => run_osc_command(state_machine, OscStateMachine::prev).await,
-        None => Err(ServerFnError::ServerError(
-            "OSC state machine is None!".to_string(),
-        )),
-    }
+    let FromContext(config): FromContext<Arc<RwLock<Config>>> = extract().await?;
+    run_osc_command(state_machine_option, config, TransportCommand::Prev, None).await
 }
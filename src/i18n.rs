@@ -0,0 +1,258 @@
+use std::{collections::HashMap, sync::LazyLock};
+
+use chrono::{NaiveDateTime, NaiveTime};
+use dioxus::{logger::tracing::warn, prelude::*};
+
+/// Local storage key of the language a user picked over the browser's.
+pub const LANGUAGE_STORAGE_KEY: &str = "mtrack-remote-language";
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Language {
+    #[default]
+    En,
+    De,
+    Es,
+}
+
+type Catalogue = HashMap<String, String>;
+
+/// Translation catalogues, keyed by dotted message IDs.
+static CATALOGUES: LazyLock<HashMap<Language, Catalogue>> = LazyLock::new(|| {
+    [
+        (Language::En, include_str!("../i18n/en.json")),
+        (Language::De, include_str!("../i18n/de.json")),
+        (Language::Es, include_str!("../i18n/es.json")),
+    ]
+    .into_iter()
+    .map(|(language, json)| {
+        let catalogue = match serde_json::from_str(json) {
+            Ok(catalogue) => catalogue,
+            Err(err) => {
+                warn!("Could not parse {} catalogue! {err}", language.code());
+                Catalogue::new()
+            }
+        };
+        (language, catalogue)
+    })
+    .collect()
+});
+
+impl Language {
+    pub const ALL: [Language; 3] = [Language::En, Language::De, Language::Es];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::De => "de",
+            Language::Es => "es",
+        }
+    }
+
+    /// Name of the language in the language itself.
+    pub fn native_name(&self) -> &'static str {
+        match self {
+            Language::En => "English",
+            Language::De => "Deutsch",
+            Language::Es => "Español",
+        }
+    }
+
+    /// Language of a BCP 47 tag like `de-AT`, `None` if there is no catalogue for it.
+    pub fn from_tag(tag: &str) -> Option<Language> {
+        let primary = tag.split(['-', '_']).next()?.to_ascii_lowercase();
+        Language::ALL
+            .into_iter()
+            .find(|language| language.code() == primary)
+    }
+
+    /// Message for `key`, falling back to English and then to the key itself.
+    pub fn translate(&self, key: &str) -> String {
+        [*self, Language::En]
+            .iter()
+            .find_map(|language| CATALOGUES.get(language)?.get(key))
+            .cloned()
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    /// Message for `key` with its `{name}` placeholders replaced.
+    pub fn translate_with(&self, key: &str, args: &[(&str, String)]) -> String {
+        args.iter()
+            .fold(self.translate(key), |message, (name, value)| {
+                message.replace(&format!("{{{name}}}"), value)
+            })
+    }
+
+    /// Formats a number with the locale's decimal and thousands separators.
+    pub fn format_number(&self, value: f64, decimals: usize) -> String {
+        let (decimal_separator, thousands_separator) = match self {
+            Language::En => ('.', ','),
+            Language::De | Language::Es => (',', '.'),
+        };
+        let formatted = format!("{:.*}", decimals, value.abs());
+        let (integer, fraction) = match formatted.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (formatted.as_str(), None),
+        };
+        let mut grouped = String::new();
+        for (index, digit) in integer.chars().enumerate() {
+            if index > 0 && (integer.len() - index) % 3 == 0 {
+                grouped.push(thousands_separator);
+            }
+            grouped.push(digit);
+        }
+        if value < 0.0 && formatted.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            grouped.insert(0, '-');
+        }
+        match fraction {
+            Some(fraction) => format!("{grouped}{decimal_separator}{fraction}"),
+            None => grouped,
+        }
+    }
+
+    /// Formats a time of day, 12 hour clock for English and 24 hour clock otherwise.
+    pub fn format_time(&self, time: NaiveTime) -> String {
+        match self {
+            Language::En => time.format("%-I:%M %p").to_string(),
+            Language::De | Language::Es => time.format("%H:%M").to_string(),
+        }
+    }
+
+    pub fn format_datetime(&self, datetime: NaiveDateTime) -> String {
+        let date = match self {
+            Language::En => datetime.format("%Y-%m-%d"),
+            Language::De => datetime.format("%d.%m.%Y"),
+            Language::Es => datetime.format("%d/%m/%Y"),
+        };
+        format!("{date} {}", self.format_time(datetime.time()))
+    }
+}
+
+/// Handle components translate their strings with, re-rendering when the language changes.
+#[derive(Clone, Copy, PartialEq)]
+pub struct I18n {
+    pub language: Signal<Language>,
+}
+
+impl I18n {
+    pub fn t(&self, key: &str) -> String {
+        self.language.read().translate(key)
+    }
+
+    pub fn t_with(&self, key: &str, args: &[(&str, String)]) -> String {
+        self.language.read().translate_with(key, args)
+    }
+
+    pub fn number(&self, value: f64, decimals: usize) -> String {
+        self.language.read().format_number(value, decimals)
+    }
+
+    pub fn time(&self, time: NaiveTime) -> String {
+        self.language.read().format_time(time)
+    }
+
+    pub fn datetime(&self, datetime: NaiveDateTime) -> String {
+        self.language.read().format_datetime(datetime)
+    }
+
+    /// Switches the language, `None` returns to the browser's language.
+    pub fn set_language(&mut self, language: Option<Language>) {
+        let script = match language {
+            Some(language) => format!(
+                "localStorage.setItem('{LANGUAGE_STORAGE_KEY}', '{}'); return '{}';",
+                language.code(),
+                language.code()
+            ),
+            None => format!(
+                "localStorage.removeItem('{LANGUAGE_STORAGE_KEY}'); return navigator.language;"
+            ),
+        };
+        let mut language_signal = self.language;
+        spawn(async move {
+            apply_language_script(&script, &mut language_signal).await;
+        });
+    }
+}
+
+/// Script returning the language a user picked, or the browser's language.
+fn detect_language_script() -> String {
+    format!("return localStorage.getItem('{LANGUAGE_STORAGE_KEY}') || navigator.language;")
+}
+
+/// Script returning the language a user picked, or null.
+pub fn stored_language_script() -> String {
+    format!("return localStorage.getItem('{LANGUAGE_STORAGE_KEY}');")
+}
+
+async fn apply_language_script(script: &str, language: &mut Signal<Language>) {
+    match document::eval(script).join::<Option<String>>().await {
+        Ok(tag) => {
+            let detected = tag.as_deref().and_then(Language::from_tag);
+            language.set(detected.unwrap_or_default());
+        }
+        Err(err) => warn!("Could not detect language! {err:?}"),
+    }
+}
+
+/// Provides the language to all components, detected from the browser or the user's pick.
+pub fn use_i18n_provider() -> I18n {
+    let mut language = use_context_provider(|| Signal::new(Language::default()));
+    use_future(move || async move {
+        apply_language_script(&detect_language_script(), &mut language).await;
+    });
+    use_effect(move || {
+        let code = language.read().code();
+        document::eval(&format!("document.documentElement.lang = '{code}';"));
+    });
+    I18n { language }
+}
+
+pub fn use_i18n() -> I18n {
+    I18n {
+        language: use_context(),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::{NaiveDate, NaiveTime};
+
+    use super::{Language, CATALOGUES};
+
+    #[test]
+    fn catalogues_have_the_same_keys() {
+        let english: BTreeSet<&String> = CATALOGUES[&Language::En].keys().collect();
+        assert!(!english.is_empty());
+        for language in Language::ALL {
+            let keys: BTreeSet<&String> = CATALOGUES[&language].keys().collect();
+            assert_eq!(keys, english, "{} catalogue differs", language.code());
+        }
+    }
+
+    #[test]
+    fn languages_are_detected_and_messages_fall_back() {
+        assert_eq!(Language::from_tag("de-AT"), Some(Language::De));
+        assert_eq!(Language::from_tag("ES"), Some(Language::Es));
+        assert_eq!(Language::from_tag("fr-FR"), None);
+        assert_eq!(Language::De.translate("no.such.key"), "no.such.key");
+        assert_eq!(
+            Language::En.translate_with("showtiming.curfew", &[("time", "23:00".to_string())]),
+            "curfew 23:00"
+        );
+    }
+
+    #[test]
+    fn numbers_and_times_are_formatted_per_locale() {
+        assert_eq!(Language::En.format_number(1234567.891, 2), "1,234,567.89");
+        assert_eq!(Language::De.format_number(1234.5, 1), "1.234,5");
+        assert_eq!(Language::Es.format_number(-12.0, 0), "-12");
+        assert_eq!(Language::En.format_number(-0.001, 1), "0.0");
+        let time = NaiveTime::from_hms_opt(21, 5, 0).unwrap();
+        assert_eq!(Language::En.format_time(time), "9:05 PM");
+        assert_eq!(Language::De.format_time(time), "21:05");
+        let datetime = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap().and_time(time);
+        assert_eq!(Language::De.format_datetime(datetime), "18.10.2026 21:05");
+        assert_eq!(Language::Es.format_datetime(datetime), "18/10/2026 21:05");
+    }
+}
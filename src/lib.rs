@@ -1,4 +1,5 @@
 pub mod backend;
 pub mod components;
+pub mod i18n;
 pub mod route;
 pub mod views;
@@ -2,21 +2,108 @@ use dioxus::prelude::*;
 
 #[cfg(feature = "server")]
 use dioxus::logger::tracing::debug;
-use mtrack_remote::route::Route;
+use mtrack_remote::{i18n::use_i18n_provider, route::Route};
 
 const FAVICON: Asset = asset!("./assets/favicon_512x512.ico");
 const TAILWIND_CSS: Asset = asset!("./assets/tailwind.css");
+/// Registers the service worker that caches the app for offline use.
+const REGISTER_SERVICE_WORKER: &str =
+    "if ('serviceWorker' in navigator) { navigator.serviceWorker.register('/sw.js'); }";
 
+/// Options of the server, also settable through the environment for service managers.
 #[cfg(feature = "server")]
-fn main() {
+#[derive(clap::Parser)]
+#[command(version, about)]
+struct ServerArgs {
+    /// File to write the process ID to while the server runs.
+    #[arg(long, env = "MTRACK_REMOTE_PID_FILE")]
+    pid_file: Option<std::path::PathBuf>,
+    /// Serve HTTPS, with a self-signed certificate generated on first start unless one is given.
+    #[arg(long, env = "MTRACK_REMOTE_TLS")]
+    tls: bool,
+    /// PEM certificate chain to serve HTTPS with.
+    #[arg(long, env = "MTRACK_REMOTE_TLS_CERT", requires = "tls_key")]
+    tls_cert: Option<std::path::PathBuf>,
+    /// PEM private key of the certificate.
+    #[arg(long, env = "MTRACK_REMOTE_TLS_KEY", requires = "tls_cert")]
+    tls_key: Option<std::path::PathBuf>,
+    /// Port redirecting plain HTTP requests to HTTPS.
+    #[arg(long, env = "MTRACK_REMOTE_HTTP_REDIRECT_PORT")]
+    http_redirect_port: Option<u16>,
+    /// Address OSC packets are accepted from besides the mtrack host, may be repeated.
+    #[arg(long, env = "MTRACK_REMOTE_ALLOW_OSC_SOURCE", value_delimiter = ',')]
+    allow_osc_source: Vec<std::net::IpAddr>,
+}
+
+#[cfg(feature = "server")]
+#[tokio::main]
+async fn main() {
     use std::sync::Arc;
 
-    use dioxus::logger::tracing::warn;
-    use mtrack_remote::backend::config::Config;
+    use clap::Parser;
+    use dioxus::logger::tracing::{error, info, warn};
+    use std::net::SocketAddr;
+
+    use mtrack_remote::backend::{
+        api::{self, ApiState},
+        autoadvance::{self, AutoAdvance, AutoAdvanceSettings},
+        config::Config,
+        discovery::{self, DiscoveredHosts},
+        durations::{self, SongDurations},
+        metrics,
+        notes::SongNotes,
+        oscfilter, oscquery, pwa,
+        scheduler::{self, Schedule},
+        server::OscStateMachine,
+        service::{self, PidFile, Shutdown},
+        supervisor::{self, Snapshots},
+        themes,
+        tls::{self, TlsFiles, TlsInfo},
+    };
     use tokio::sync::RwLock;
 
+    dioxus::logger::initialize_default();
+
+    let args = ServerArgs::parse();
     debug!("Starting server");
-    let builder = dioxus::LaunchBuilder::server();
+    let _pid_file = match args.pid_file {
+        Some(path) => match PidFile::create(&path) {
+            Ok(pid_file) => Some(pid_file),
+            Err(err) => {
+                error!("{err}");
+                return;
+            }
+        },
+        None => None,
+    };
+    oscfilter::set_extra_sources(args.allow_osc_source);
+    let shutdown = Shutdown::new();
+    tokio::spawn(service::listen_for_signals(shutdown.clone()));
+
+    let tls_files = match args.tls || args.tls_cert.is_some() {
+        true => match TlsFiles::load_or_generate(args.tls_cert, args.tls_key) {
+            Ok(tls_files) => Some(tls_files),
+            Err(err) => {
+                error!("{err}");
+                return;
+            }
+        },
+        false => None,
+    };
+    let tls_info = match tls_files.as_ref().map(TlsFiles::info) {
+        Some(Ok(tls_info)) => tls_info,
+        Some(Err(err)) => {
+            warn!("{err}");
+            TlsInfo {
+                enabled: true,
+                ..TlsInfo::default()
+            }
+        }
+        None => TlsInfo::default(),
+    };
+    if let Some(fingerprint) = tls_info.fingerprint.as_ref() {
+        info!("Certificate fingerprint (SHA-256) {fingerprint}");
+    }
     let config = match Config::read_config() {
         Ok(config) => config,
         Err(err) => {
@@ -25,12 +112,212 @@ fn main() {
         }
     };
 
-    builder
-        .with_context(server_only!(Arc::new(RwLock::new(Some(
-            mtrack_remote::backend::server::OscStateMachine::new()
-        )))))
-        .with_context(Arc::new(RwLock::new(config)))
-        .launch(App);
+    let auto_advance_settings = match AutoAdvanceSettings::read_settings() {
+        Ok(settings) => settings,
+        Err(err) => {
+            warn!("Could not read auto-advance settings, using defaults. {err}");
+            AutoAdvanceSettings::default()
+        }
+    };
+
+    let schedule = match Schedule::read_schedule() {
+        Ok(schedule) => schedule,
+        Err(err) => {
+            warn!("Could not read schedule, starting with an empty one. {err}");
+            Schedule::default()
+        }
+    };
+
+    let durations = match SongDurations::read_durations() {
+        Ok(durations) => durations,
+        Err(err) => {
+            warn!("Could not read song durations, starting without. {err}");
+            SongDurations::default()
+        }
+    };
+
+    let notes = match SongNotes::read_notes() {
+        Ok(notes) => notes,
+        Err(err) => {
+            warn!("Could not read song notes, starting without. {err}");
+            SongNotes::default()
+        }
+    };
+
+    let state_machine = Arc::new(RwLock::new(Some(OscStateMachine::new())));
+    let config = Arc::new(RwLock::new(config));
+    let auto_advance = Arc::new(RwLock::new(AutoAdvance::new(auto_advance_settings)));
+    let schedule = Arc::new(RwLock::new(schedule));
+    let durations = Arc::new(RwLock::new(durations));
+    let notes = Arc::new(RwLock::new(notes));
+    let discovered_hosts = Arc::new(RwLock::new(DiscoveredHosts::default()));
+    let snapshots = Arc::new(Snapshots::default());
+
+    let address = dioxus::cli_config::fullstack_address_or_localhost();
+    let discovery_daemon = match discovery::start(
+        discovered_hosts.clone(),
+        address.port(),
+        tls_files.is_some(),
+    ) {
+        Ok(daemon) => Some(daemon),
+        Err(err) => {
+            warn!("mDNS discovery is not available! {err}");
+            None
+        }
+    };
+
+    let oscquery_snapshots = snapshots.clone();
+    let oscquery_config = config.clone();
+    let oscquery_shutdown = shutdown.clone();
+    let oscquery_task = tokio::spawn(async move {
+        if let Err(err) =
+            oscquery::serve(oscquery_snapshots, oscquery_config, oscquery_shutdown).await
+        {
+            error!("OSCQuery service stopped! {err}");
+        }
+    });
+
+    // Stopped on shutdown before disconnecting, so none of them reconnects or sends meanwhile.
+    let background_tasks = [
+        tokio::spawn(autoadvance::run(
+            state_machine.clone(),
+            auto_advance.clone(),
+            durations.clone(),
+            shutdown.clone(),
+        )),
+        tokio::spawn(scheduler::run(
+            state_machine.clone(),
+            snapshots.clone(),
+            schedule.clone(),
+            shutdown.clone(),
+        )),
+        tokio::spawn(durations::run(
+            state_machine.clone(),
+            durations.clone(),
+            shutdown.clone(),
+        )),
+        tokio::spawn(supervisor::run(
+            state_machine.clone(),
+            config.clone(),
+            snapshots.clone(),
+            shutdown.clone(),
+        )),
+    ];
+
+    let api_state = ApiState {
+        state_machine_option: state_machine.clone(),
+        config: config.clone(),
+        snapshots: snapshots.clone(),
+    };
+
+    let serve_config = {
+        let state_machine = state_machine.clone();
+        let config = config.clone();
+        let auto_advance = auto_advance.clone();
+        let schedule = schedule.clone();
+        let durations = durations.clone();
+        let notes = notes.clone();
+        let tls_info = tls_info.clone();
+        let discovered_hosts = discovered_hosts.clone();
+        let snapshots = snapshots.clone();
+        ServeConfigBuilder::new().context_providers(Arc::new(vec![
+            Box::new(move || Box::new(state_machine.clone())),
+            Box::new(move || Box::new(config.clone())),
+            Box::new(move || Box::new(auto_advance.clone())),
+            Box::new(move || Box::new(schedule.clone())),
+            Box::new(move || Box::new(durations.clone())),
+            Box::new(move || Box::new(notes.clone())),
+            Box::new(move || Box::new(tls_info.clone())),
+            Box::new(move || Box::new(discovered_hosts.clone())),
+            Box::new(move || Box::new(snapshots.clone())),
+        ]))
+    };
+    let router = axum::Router::new()
+        .serve_dioxus_application(serve_config, App)
+        .route("/metrics", axum::routing::get(metrics::serve_metrics))
+        .merge(pwa::router())
+        .merge(themes::router())
+        .merge(api::router(api_state));
+
+    tokio::spawn(service::run_watchdog(shutdown.clone()));
+    match tls_files {
+        Some(tls_files) => {
+            if let Some(port) = args.http_redirect_port {
+                let redirect_address = SocketAddr::new(address.ip(), port);
+                let redirect_shutdown = shutdown.clone();
+                match tokio::net::TcpListener::bind(redirect_address).await {
+                    Ok(listener) => {
+                        info!("Redirecting HTTP on {redirect_address} to HTTPS");
+                        let redirecting =
+                            axum::serve(listener, tls::redirect_router(address.port()))
+                                .with_graceful_shutdown(
+                                    async move { redirect_shutdown.wait().await },
+                                );
+                        tokio::spawn(async move {
+                            if let Err(err) = redirecting.await {
+                                error!("HTTP redirect stopped! {err}");
+                            }
+                        });
+                    }
+                    Err(err) => error!("Could not bind to {redirect_address}! {err}"),
+                }
+            }
+            if let Err(err) = tls::serve_https(address, &tls_files, router, shutdown.clone()).await
+            {
+                error!("{err}");
+            }
+        }
+        None => {
+            let listener = match tokio::net::TcpListener::bind(address).await {
+                Ok(listener) => listener,
+                Err(err) => {
+                    error!("Could not bind to {address}! {err}");
+                    return;
+                }
+            };
+            let service = router.into_make_service_with_connect_info::<SocketAddr>();
+            if let Err(err) = service::notify("READY=1") {
+                warn!("{err}");
+            }
+            let server_shutdown = shutdown.clone();
+            let serving = axum::serve(listener, service).with_graceful_shutdown(async move {
+                server_shutdown.wait().await;
+            });
+            if let Err(err) = serving.await {
+                error!("Server stopped! {err}");
+            }
+        }
+    }
+
+    shutdown.trigger();
+    if let Err(err) = oscquery_task.await {
+        error!("OSCQuery service did not stop cleanly! {err}");
+    }
+    for task in background_tasks {
+        if let Err(err) = task.await {
+            error!("Background task did not stop cleanly! {err}");
+        }
+    }
+    if let Some(daemon) = discovery_daemon.as_ref() {
+        discovery::stop(daemon);
+    }
+    service::disconnect_osc(&state_machine).await;
+    if let Err(err) = config.read().await.write_config() {
+        error!("Could not save config! {err}");
+    }
+    if let Err(err) = auto_advance.read().await.settings.write_settings() {
+        error!("Could not save auto-advance settings! {err}");
+    }
+    if let Err(err) = schedule.read().await.write_schedule() {
+        error!("Could not save schedule! {err}");
+    }
+    if let Err(err) = durations.read().await.write_durations() {
+        error!("Could not save song durations! {err}");
+    }
+    if let Err(err) = notes.read().await.write_notes() {
+        error!("Could not save song notes! {err}");
+    }
+    info!("Shut down cleanly");
 }
 
 #[cfg(feature = "web")]
@@ -43,10 +330,17 @@ fn main() {
 
 #[component]
 fn App() -> Element {
+    use_i18n_provider();
+    use_effect(|| {
+        document::eval(REGISTER_SERVICE_WORKER);
+    });
+
     rsx! {
         // Global app resources
         document::Link { rel: "icon", href: FAVICON }
         document::Link { rel: "stylesheet", href: TAILWIND_CSS }
+        document::Link { rel: "manifest", href: "/manifest.webmanifest" }
+        document::Meta { name: "theme-color", content: "#2e2e38" }
 
         Router::<Route> {}
     }
@@ -72,6 +366,25 @@ mod main_test {
             .with_context(Arc::new(RwLock::new(
                 mtrack_remote::backend::config::Config::new(),
             )))
+            .with_context(Arc::new(RwLock::new(
+                mtrack_remote::backend::autoadvance::AutoAdvance::default(),
+            )))
+            .with_context(Arc::new(RwLock::new(
+                mtrack_remote::backend::scheduler::Schedule::default(),
+            )))
+            .with_context(Arc::new(RwLock::new(
+                mtrack_remote::backend::durations::SongDurations::default(),
+            )))
+            .with_context(Arc::new(RwLock::new(
+                mtrack_remote::backend::notes::SongNotes::default(),
+            )))
+            .with_context(mtrack_remote::backend::tls::TlsInfo::default())
+            .with_context(Arc::new(RwLock::new(
+                mtrack_remote::backend::discovery::DiscoveredHosts::default(),
+            )))
+            .with_context(Arc::new(
+                mtrack_remote::backend::supervisor::Snapshots::default(),
+            ))
             .launch(App);
     }
 }
@@ -1,7 +1,7 @@
 use dioxus::prelude::*;
 
 use crate::components::Navbar;
-use crate::views::{Config, Mtrack};
+use crate::views::{Config, Mtrack, Schedule, Stage, Traffic};
 
 #[derive(Debug, Clone, Routable, PartialEq)]
 #[rustfmt::skip]
@@ -11,4 +11,11 @@ pub enum Route {
     Mtrack {},
     #[route("/config")]
     Config { },
+    #[route("/traffic")]
+    Traffic { },
+    #[route("/schedule")]
+    Schedule { },
+    #[end_layout]
+    #[route("/stage")]
+    Stage { },
 }
@@ -6,5 +6,9 @@ use crate::components;
 pub fn Config() -> Element {
     rsx! {
         components::ConfigComponent {}
+        components::AutoAdvanceConfig {}
+        components::SongNotesConfig {}
+        components::ThemesConfig {}
+        components::TlsStatus {}
     }
 }
@@ -3,3 +3,12 @@ pub use config::Config;
 
 mod mtrack;
 pub use mtrack::Mtrack;
+
+mod schedule;
+pub use schedule::Schedule;
+
+mod stage;
+pub use stage::Stage;
+
+mod traffic;
+pub use traffic::Traffic;
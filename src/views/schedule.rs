@@ -0,0 +1,10 @@
+use dioxus::prelude::*;
+
+use crate::components;
+
+#[component]
+pub fn Schedule() -> Element {
+    rsx! {
+        components::ScheduleComponent {}
+    }
+}
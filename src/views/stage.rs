@@ -0,0 +1,10 @@
+use dioxus::prelude::*;
+
+use crate::components;
+
+#[component]
+pub fn Stage() -> Element {
+    rsx! {
+        components::StageDisplay {}
+    }
+}
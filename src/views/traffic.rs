@@ -0,0 +1,11 @@
+use dioxus::prelude::*;
+
+use crate::components;
+
+#[component]
+pub fn Traffic() -> Element {
+    rsx! {
+        components::Traffic {}
+        components::Recordings {}
+    }
+}